@@ -0,0 +1,204 @@
+//! Timings of compiling typical scripts and running them on both
+//! backends through the public API. Run them with `cargo bench`, the arguments select the
+//! benchmarks containing them, e.g. `cargo bench -- sort`.
+
+use std::{
+    env, io,
+    time::{Duration, Instant},
+};
+
+use basic_like::{Backend, Config, FileMode, FileStream, Interpreter, IoBackend, Program};
+
+/// Each benchmark is sampled until it takes this long in total
+const MEASUREMENT_TIME: Duration = Duration::from_secs(1);
+const MIN_SAMPLES: usize = 5;
+const MAX_SAMPLES: usize = 1000;
+
+const NUMERIC_LOOP: &str = r"i = 0
+s = 0
+loop: s = s + i * 2 - 1
+i = i + 1
+IF i < 100000 THEN loop
+PRINT s";
+
+const STRING_BUILDING: &str = r#"s$ = ""
+i = 0
+c = 0
+loop: s$ = s$ + CHR$(65 + c)
+c = c + 1
+IF c > 25 THEN c = 0
+i = i + 1
+IF i < 10000 THEN loop
+PRINT LEN(s$)"#;
+
+/// Doubly recursive Fibonacci, n is restored by each call
+const RECURSION: &str = r"n = 20
+r = 0
+GOSUB fib
+PRINT r
+GOTO done
+fib: IF n < 2 THEN leaf
+n = n - 1
+GOSUB fib
+n = n - 1
+GOSUB fib
+n = n + 2
+RETURN
+leaf: r = r + n
+RETURN
+done:";
+
+/// Insertion sort of random numbers, the arrays are rebuilt with SPLIT
+/// since their elements can't be assigned
+const SORTING: &str = r#"RANDOMIZE 1
+n = 0
+list$ = ""
+gen: IF n > 0 THEN list$ = list$ + ","
+list$ = list$ + STR$(RND * 1000)
+n = n + 1
+IF n < 150 THEN gen
+items = SPLIT(list$, ",")
+sorted$ = ""
+i = 0
+outer: x = VAL(items(i))
+IF i = 0 THEN sorted$ = STR$(x): GOTO advance
+s = SPLIT(sorted$, ",")
+new$ = ""
+placed = 0
+j = 0
+inner: y = VAL(s(j))
+IF placed THEN copy
+IF x < y THEN place
+GOTO copy
+place: new$ = new$ + STR$(x) + ","
+placed = 1
+copy: new$ = new$ + STR$(y)
+j = j + 1
+IF j < LEN(s) THEN comma
+GOTO merged
+comma: new$ = new$ + ","
+GOTO inner
+merged: IF placed = 0 THEN new$ = new$ + "," + STR$(x)
+sorted$ = new$
+advance: i = i + 1
+IF i < LEN(items) THEN outer
+PRINT LEN(SPLIT(sorted$, ","))"#;
+
+/// Console which discards the output, so the terminal isn't measured
+struct NullIo;
+
+impl IoBackend for NullIo {
+    fn write(&mut self, _text: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn open(&mut self, _path: &str, _mode: FileMode) -> io::Result<FileStream> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn file_size(&mut self, _path: &str) -> io::Result<u64> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Straight line script of the given number of labeled blocks, each one
+/// assigns, branches and prints
+fn generated_source(blocks: usize) -> String {
+    let mut source = String::new();
+    for block in 0..blocks {
+        let variable = block % 26;
+        source.push_str(&format!(
+            "b{block}: v{variable} = v{variable} + {block} * 2 - LEN(\"block {block}\")\n\
+             IF v{variable} > {block} THEN b{next}\n\
+             PRINT \"block {block}: \"; v{variable}, MID$(\"abcdef\", 2, 3)\n",
+            next = block + 1
+        ));
+    }
+    source.push_str(&format!("b{blocks}:\n"));
+    source
+}
+
+fn compile(source: &str) -> Program {
+    Program::compile(source).expect("Benchmark doesn't compile")
+}
+
+fn interpreter(backend: Backend) -> Interpreter {
+    let mut interpreter = Interpreter::new(&Config {
+        backend,
+        ..Default::default()
+    });
+    interpreter.set_io(Box::new(NullIo));
+    interpreter
+}
+
+/// Times the run of the input the setup prepares, the setup isn't timed
+struct Bench {
+    filters: Vec<String>,
+}
+
+impl Bench {
+    fn run<T>(&self, name: &str, mut setup: impl FnMut() -> T, mut run: impl FnMut(T)) {
+        if !self.filters.is_empty() && !self.filters.iter().any(|f| name.contains(f.as_str())) {
+            return;
+        }
+        // The first run warms up the caches and the allocator
+        run(setup());
+        let mut samples = Vec::new();
+        let mut total = Duration::ZERO;
+        while samples.len() < MIN_SAMPLES
+            || (total < MEASUREMENT_TIME && samples.len() < MAX_SAMPLES)
+        {
+            let input = setup();
+            let started = Instant::now();
+            run(input);
+            let time = started.elapsed();
+            total += time;
+            samples.push(time);
+        }
+        samples.sort();
+        println!(
+            "{name:<28} median {:>10.3} ms   min {:>10.3} ms   {:>4} samples",
+            millis(samples[samples.len() / 2]),
+            millis(samples[0]),
+            samples.len()
+        );
+    }
+}
+
+fn millis(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
+
+fn main() {
+    // Cargo passes --bench, the other arguments are the filters
+    let filters = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .collect();
+    let bench = Bench { filters };
+
+    let source = generated_source(2000);
+    bench.run("compile/generated", || (), |_| drop(compile(&source)));
+
+    let workloads = [
+        ("numeric_loop", NUMERIC_LOOP),
+        ("string_building", STRING_BUILDING),
+        ("recursion", RECURSION),
+        ("sorting", SORTING),
+        ("generated", source.as_str()),
+    ];
+    for (workload, source) in workloads {
+        let program = compile(source);
+        for (backend, backend_name) in [(Backend::Ast, "ast"), (Backend::Bytecode, "bytecode")] {
+            bench.run(
+                &format!("run/{workload}/{backend_name}"),
+                || interpreter(backend),
+                |mut interpreter| interpreter.run(&program).expect("Benchmark has failed"),
+            );
+        }
+    }
+}
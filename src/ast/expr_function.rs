@@ -0,0 +1,74 @@
+use crate::{
+    builtins::{Builtin, BuiltinFunction},
+    error::RuntimeError,
+    interpreter::InterpreterContext,
+    variables::Slot,
+    vm::{compiler::Compiler, Instruction},
+};
+
+use super::{value::Value, Expression};
+
+#[derive(Clone)]
+pub struct FunctionExpression {
+    builtin: &'static Builtin,
+    arguments: Vec<Box<dyn Expression>>,
+}
+
+impl FunctionExpression {
+    pub fn new(builtin: &'static Builtin, arguments: Vec<Box<dyn Expression>>) -> Self {
+        Self { builtin, arguments }
+    }
+}
+
+impl Expression for FunctionExpression {
+    fn evaluate(&self, context: &mut InterpreterContext) -> Result<Value, RuntimeError> {
+        let args = self
+            .arguments
+            .iter()
+            .map(|argument| argument.evaluate(context))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.builtin.call(context, &args)
+    }
+
+    fn compile(&self, compiler: &mut Compiler) {
+        for argument in &self.arguments {
+            argument.compile(compiler);
+        }
+        compiler.emit(Instruction::Call {
+            builtin: self.builtin,
+            count: self.arguments.len(),
+        });
+    }
+
+    /// Pure functions of constants are called once, unless they're not
+    /// foldable. The errors are left to be reported when the script runs
+    fn optimize(self: Box<Self>) -> Box<dyn Expression> {
+        let arguments = self
+            .arguments
+            .into_iter()
+            .map(|argument| argument.optimize())
+            .collect::<Vec<_>>();
+        if let (BuiltinFunction::Pure(function), true) =
+            (&self.builtin.function, self.builtin.foldable)
+        {
+            let values = arguments
+                .iter()
+                .map(|argument| argument.constant().cloned())
+                .collect::<Option<Vec<_>>>();
+            if let Some(Ok(value)) = values.map(|values| function(&values)) {
+                return Box::new(value);
+            }
+        }
+        Box::new(Self::new(self.builtin, arguments))
+    }
+
+    fn clone_box(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+
+    fn rebind(&mut self, slots: &[Slot]) {
+        for argument in &mut self.arguments {
+            argument.rebind(slots);
+        }
+    }
+}
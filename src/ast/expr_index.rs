@@ -0,0 +1,66 @@
+use crate::{
+    error::RuntimeError,
+    interpreter::InterpreterContext,
+    variables::{Slot, Variable},
+    vm::{compiler::Compiler, Instruction},
+};
+
+use super::{value::Value, Expression};
+
+/// Access to an array element by its 0-based index
+#[derive(Clone)]
+pub struct IndexExpression {
+    array: Variable,
+    index: Box<dyn Expression>,
+}
+
+impl IndexExpression {
+    pub fn new(array: Variable, index: Box<dyn Expression>) -> Self {
+        Self { array, index }
+    }
+}
+
+impl Expression for IndexExpression {
+    fn evaluate(&self, context: &mut InterpreterContext) -> Result<Value, RuntimeError> {
+        let index = self.index.evaluate(context)?;
+        element(context, self.array.slot, &index)
+    }
+
+    fn compile(&self, compiler: &mut Compiler) {
+        self.index.compile(compiler);
+        compiler.emit(Instruction::LoadIndex(self.array.slot));
+    }
+
+    fn optimize(mut self: Box<Self>) -> Box<dyn Expression> {
+        self.index = self.index.optimize();
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+
+    fn rebind(&mut self, slots: &[Slot]) {
+        self.array.slot = slots[self.array.slot];
+        self.index.rebind(slots);
+    }
+}
+
+/// Element of the array variable in the slot at the index
+pub fn element(
+    context: &InterpreterContext,
+    slot: Slot,
+    index: &Value,
+) -> Result<Value, RuntimeError> {
+    let index = index.try_number()?.round();
+    let Some(Value::Array(items)) = context.variable_at(slot) else {
+        return Err(RuntimeError::TypeMismatch);
+    };
+    if index < 0.0 {
+        return Err(RuntimeError::SubscriptOutOfRange);
+    }
+    items
+        .get(index as usize)
+        .cloned()
+        .ok_or(RuntimeError::SubscriptOutOfRange)
+}
@@ -1,7 +1,13 @@
-use crate::interpreter::InterpreterContext;
+use crate::{
+    error::RuntimeError,
+    interpreter::InterpreterContext,
+    variables::Slot,
+    vm::{compiler::Compiler, Instruction},
+};
 
-use super::{Expression, Value};
+use super::{value::Double, Expression, Value};
 
+#[derive(Clone)]
 pub struct OperatorExpression {
     left: Box<dyn Expression>,
     operator: char,
@@ -19,66 +25,126 @@ impl OperatorExpression {
 }
 
 impl Expression for OperatorExpression {
-    fn evaluate(&self, context: &InterpreterContext) -> Value {
-        let left_value = self.left.evaluate(context);
-        let right_value = self.right.evaluate(context);
-        let is_left_numeric = left_value.is_numeric();
-        match self.operator {
-            '=' => {
-                // Coerce to the left argument's type, then compare
-                let is_equal = if is_left_numeric {
-                    left_value.to_number() == right_value.to_number()
-                } else {
-                    left_value.to_text() == right_value.to_text()
-                };
-                Value::with_bool(is_equal)
-            }
-            '+' => {
-                // Addition if the left argument is a number, otherwise do
-                // string concatenation
-                if is_left_numeric {
-                    let sum = left_value.to_number() + right_value.to_number();
-                    Value::number(sum)
-                } else {
-                    let concat = left_value.to_text() + &right_value.to_text();
-                    Value::string(concat)
-                }
-            }
-            '-' => {
-                let sub = left_value.to_number() - right_value.to_number();
-                Value::number(sub)
-            }
-            '*' => {
-                let mul = left_value.to_number() * right_value.to_number();
-                Value::number(mul)
-            }
-            '/' => {
-                let div = left_value.to_number() / right_value.to_number();
-                Value::number(div)
-            }
-            '<' => {
-                // Coerce to the left argument's type, then compare
-                let val = if is_left_numeric {
-                    left_value.to_number() < right_value.to_number()
-                } else {
-                    left_value.to_text() < right_value.to_text()
-                };
-                Value::with_bool(val)
-            }
-            '>' => {
-                // Coerce to the left argument's type, then compare.
-                let val = if is_left_numeric {
-                    left_value.to_number() > right_value.to_number()
-                } else {
-                    left_value.to_text() > right_value.to_text()
-                };
-                Value::with_bool(val)
-            }
-            _ => {
-                panic!("Unknown operator {}", self.operator)
+    fn evaluate(&self, context: &mut InterpreterContext) -> Result<Value, RuntimeError> {
+        let left_value = self.left.evaluate(context)?;
+        let right_value = self.right.evaluate(context)?;
+        apply(self.operator, &left_value, &right_value)
+    }
+
+    fn compile(&self, compiler: &mut Compiler) {
+        self.left.compile(compiler);
+        self.right.compile(compiler);
+        compiler.emit(Instruction::Operator(self.operator));
+    }
+
+    /// Operators of constants are applied once, the errors are left to be
+    /// reported when the script runs. The identities are simplified only
+    /// if the other operand is numeric, e.g. `a$ * 1` has to fail, and
+    /// `x + 0` is kept since it turns -0 into 0.
+    fn optimize(self: Box<Self>) -> Box<dyn Expression> {
+        let Self {
+            left,
+            operator,
+            right,
+        } = *self;
+        let left = left.optimize();
+        let right = right.optimize();
+        if let (Some(left), Some(right)) = (left.constant(), right.constant()) {
+            if let Ok(value) = apply(operator, left, right) {
+                return Box::new(value);
             }
         }
+        match operator {
+            '*' if is_number(right.as_ref(), 1.0) && left.is_numeric() => left,
+            '*' if is_number(left.as_ref(), 1.0) && right.is_numeric() => right,
+            '/' if is_number(right.as_ref(), 1.0) && left.is_numeric() => left,
+            '-' if is_number(right.as_ref(), 0.0) && left.is_numeric() => left,
+            _ => Box::new(Self::new(left, operator, right)),
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
     }
+
+    fn rebind(&mut self, slots: &[Slot]) {
+        self.left.rebind(slots);
+        self.right.rebind(slots);
+    }
+
+    /// Only + may result in a string, when the left operand is one
+    fn is_numeric(&self) -> bool {
+        self.operator != '+' || self.left.is_numeric()
+    }
+}
+
+fn is_number(expression: &dyn Expression, number: Double) -> bool {
+    matches!(expression.constant(), Some(Value::Number(value)) if *value == number)
+}
+
+/// Applies the binary operator to the evaluated arguments
+pub fn apply(
+    operator: char,
+    left_value: &Value,
+    right_value: &Value,
+) -> Result<Value, RuntimeError> {
+    let is_left_numeric = left_value.is_numeric();
+    let value = match operator {
+        '=' => {
+            // Coerce to the left argument's type, then compare
+            let is_equal = if is_left_numeric {
+                left_value.try_number()? == right_value.try_number()?
+            } else {
+                left_value.to_text() == right_value.to_text()
+            };
+            Value::with_bool(is_equal)
+        }
+        '+' => {
+            // Addition if the left argument is a number, otherwise do
+            // string concatenation
+            if is_left_numeric {
+                let sum = left_value.try_number()? + right_value.try_number()?;
+                Value::number(sum)
+            } else {
+                let concat = left_value.to_text() + &right_value.to_text();
+                Value::string(concat)
+            }
+        }
+        '-' => {
+            let sub = left_value.try_number()? - right_value.try_number()?;
+            Value::number(sub)
+        }
+        '*' => {
+            let mul = left_value.try_number()? * right_value.try_number()?;
+            Value::number(mul)
+        }
+        '/' => {
+            let div = left_value.try_number()? / right_value.try_number()?;
+            Value::number(div)
+        }
+        '<' => {
+            // Coerce to the left argument's type, then compare
+            let val = if is_left_numeric {
+                left_value.try_number()? < right_value.try_number()?
+            } else {
+                left_value.to_text() < right_value.to_text()
+            };
+            Value::with_bool(val)
+        }
+        '>' => {
+            // Coerce to the left argument's type, then compare.
+            let val = if is_left_numeric {
+                left_value.try_number()? > right_value.try_number()?
+            } else {
+                left_value.to_text() > right_value.to_text()
+            };
+            Value::with_bool(val)
+        }
+        _ => {
+            panic!("Unknown operator {operator}")
+        }
+    };
+    Ok(value)
 }
 
 #[cfg(test)]
@@ -91,10 +157,10 @@ mod test {
         let right = Box::new(Value::number(10.0));
         let operator = '=';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(val.is_numeric());
-        assert_eq!(val.to_number(), 1.0);
+        assert_eq!(val.try_number().unwrap(), 1.0);
     }
 
     #[test]
@@ -103,9 +169,9 @@ mod test {
         let right = Box::new(Value::number(2.0));
         let operator = '=';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
-        assert_eq!(val.to_number(), 0.0);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
+        assert_eq!(val.try_number().unwrap(), 0.0);
     }
 
     #[test]
@@ -114,9 +180,9 @@ mod test {
         let right = Box::new(Value::string("10".to_string()));
         let operator = '=';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
-        assert_eq!(val.to_number(), 1.0);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
+        assert_eq!(val.try_number().unwrap(), 1.0);
     }
 
     #[test]
@@ -125,9 +191,9 @@ mod test {
         let right = Box::new(Value::string("11".to_string()));
         let operator = '=';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
-        assert_eq!(val.to_number(), 0.0);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
+        assert_eq!(val.try_number().unwrap(), 0.0);
     }
 
     #[test]
@@ -136,10 +202,10 @@ mod test {
         let right = Box::new(Value::number(21.0));
         let operator = '+';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(val.is_numeric());
-        assert_eq!(val.to_number(), 31.0);
+        assert_eq!(val.try_number().unwrap(), 31.0);
     }
 
     #[test]
@@ -148,8 +214,8 @@ mod test {
         let right = Box::new(Value::string("def".to_string()));
         let operator = '+';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(!val.is_numeric());
         assert_eq!(val.to_text(), "abcdef");
     }
@@ -160,10 +226,10 @@ mod test {
         let right = Box::new(Value::string("20".to_string()));
         let operator = '+';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(val.is_numeric());
-        assert_eq!(val.to_number(), 30.0);
+        assert_eq!(val.try_number().unwrap(), 30.0);
     }
 
     #[test]
@@ -173,8 +239,8 @@ mod test {
         let right = Box::new(Value::string("abc".to_string()));
         let operator = '+';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let _ = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let _ = op_expr.evaluate(&mut context).unwrap();
     }
 
     #[test]
@@ -183,8 +249,8 @@ mod test {
         let right = Box::new(Value::number(10.0));
         let operator = '+';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(!val.is_numeric());
         assert_eq!(val.to_text(), "2010");
     }
@@ -195,10 +261,10 @@ mod test {
         let right = Box::new(Value::number(10.0));
         let operator = '-';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(val.is_numeric());
-        assert_eq!(val.to_number(), 11.0);
+        assert_eq!(val.try_number().unwrap(), 11.0);
     }
 
     #[test]
@@ -207,9 +273,9 @@ mod test {
         let right = Box::new(Value::number(10.0));
         let operator = '-';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
-        assert_eq!(val.to_number(), 10.0);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
+        assert_eq!(val.try_number().unwrap(), 10.0);
     }
 
     #[test]
@@ -219,8 +285,8 @@ mod test {
         let right = Box::new(Value::number(10.0));
         let operator = '-';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let _ = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let _ = op_expr.evaluate(&mut context).unwrap();
     }
 
     #[test]
@@ -229,10 +295,10 @@ mod test {
         let right = Box::new(Value::number(10.0));
         let operator = '*';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(val.is_numeric());
-        assert_eq!(val.to_number(), 210.0);
+        assert_eq!(val.try_number().unwrap(), 210.0);
     }
 
     #[test]
@@ -241,9 +307,9 @@ mod test {
         let right = Box::new(Value::number(10.0));
         let operator = '*';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
-        assert_eq!(val.to_number(), 200.0);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
+        assert_eq!(val.try_number().unwrap(), 200.0);
     }
 
     #[test]
@@ -253,8 +319,8 @@ mod test {
         let right = Box::new(Value::number(10.0));
         let operator = '*';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let _ = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let _ = op_expr.evaluate(&mut context).unwrap();
     }
 
     #[test]
@@ -263,10 +329,10 @@ mod test {
         let right = Box::new(Value::number(10.0));
         let operator = '/';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(val.is_numeric());
-        assert_eq!(val.to_number(), 2.0);
+        assert_eq!(val.try_number().unwrap(), 2.0);
     }
 
     #[test]
@@ -275,9 +341,9 @@ mod test {
         let right = Box::new(Value::number(10.0));
         let operator = '/';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
-        assert_eq!(val.to_number(), 2.0);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
+        assert_eq!(val.try_number().unwrap(), 2.0);
     }
 
     #[test]
@@ -287,8 +353,8 @@ mod test {
         let right = Box::new(Value::number(10.0));
         let operator = '/';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let _ = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let _ = op_expr.evaluate(&mut context).unwrap();
     }
 
     #[test]
@@ -297,10 +363,10 @@ mod test {
         let right = Box::new(Value::number(10.0));
         let operator = '<';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(val.is_numeric());
-        assert_eq!(val.to_number(), 0.0);
+        assert_eq!(val.try_number().unwrap(), 0.0);
     }
 
     #[test]
@@ -309,10 +375,10 @@ mod test {
         let right = Box::new(Value::string("10".to_string()));
         let operator = '<';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(val.is_numeric());
-        assert_eq!(val.to_number(), 0.0);
+        assert_eq!(val.try_number().unwrap(), 0.0);
     }
 
     #[test]
@@ -321,10 +387,10 @@ mod test {
         let right = Box::new(Value::string("bbc".to_string()));
         let operator = '<';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(val.is_numeric());
-        assert_eq!(val.to_number(), 1.0);
+        assert_eq!(val.try_number().unwrap(), 1.0);
     }
 
     #[test]
@@ -333,10 +399,10 @@ mod test {
         let right = Box::new(Value::string("10".to_string()));
         let operator = '>';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(val.is_numeric());
-        assert_eq!(val.to_number(), 1.0);
+        assert_eq!(val.try_number().unwrap(), 1.0);
     }
 
     #[test]
@@ -345,9 +411,9 @@ mod test {
         let right = Box::new(Value::string("bbc".to_string()));
         let operator = '>';
         let op_expr = OperatorExpression::new(left, operator, right);
-        let context = InterpreterContext::default();
-        let val = op_expr.evaluate(&context);
+        let mut context = InterpreterContext::default();
+        let val = op_expr.evaluate(&mut context).unwrap();
         assert!(val.is_numeric());
-        assert_eq!(val.to_number(), 0.0);
+        assert_eq!(val.try_number().unwrap(), 0.0);
     }
 }
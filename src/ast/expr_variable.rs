@@ -1,22 +1,41 @@
-use crate::interpreter::InterpreterContext;
+use crate::{
+    error::RuntimeError,
+    interpreter::InterpreterContext,
+    variables::{Slot, Variable},
+    vm::{compiler::Compiler, Instruction},
+};
 
 use super::{value::Value, Expression};
 
+#[derive(Clone)]
 pub struct VariableExpression {
-    name: String,
+    variable: Variable,
 }
 
 impl VariableExpression {
-    pub fn new(name: String) -> Self {
-        Self { name }
+    pub fn new(variable: Variable) -> Self {
+        Self { variable }
     }
 }
 
 impl Expression for VariableExpression {
-    fn evaluate(&self, context: &InterpreterContext) -> Value {
-        context
-            .variable(&self.name)
-            .unwrap_or(&Value::Number(0.0))
-            .clone()
+    fn evaluate(&self, context: &mut InterpreterContext) -> Result<Value, RuntimeError> {
+        Ok(context.value_at(self.variable.slot))
+    }
+
+    fn compile(&self, compiler: &mut Compiler) {
+        compiler.emit(Instruction::Load(self.variable.slot));
+    }
+
+    fn optimize(self: Box<Self>) -> Box<dyn Expression> {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+
+    fn rebind(&mut self, slots: &[Slot]) {
+        self.variable.slot = slots[self.variable.slot];
     }
 }
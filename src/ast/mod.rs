@@ -1,12 +1,41 @@
 use value::Value;
 
-use crate::interpreter::InterpreterContext;
+use crate::{
+    error::RuntimeError, interpreter::InterpreterContext, variables::Slot, vm::compiler::Compiler,
+};
 
+pub mod expr_function;
+pub mod expr_index;
 pub mod expr_operator;
 pub mod expr_variable;
 pub mod statement;
 pub mod value;
 
 pub trait Expression {
-    fn evaluate(&self, context: &InterpreterContext) -> Value;
+    fn evaluate(&self, context: &mut InterpreterContext) -> Result<Value, RuntimeError>;
+    /// Emits the instructions which leave the value on the VM stack
+    fn compile(&self, compiler: &mut Compiler);
+    /// Folds the constant subexpressions and simplifies the identities
+    fn optimize(self: Box<Self>) -> Box<dyn Expression>;
+    /// Copy of the expression, boxed as the statements keep it
+    fn clone_box(&self) -> Box<dyn Expression>;
+    /// Moves the variables to the new slots, the new slot of each old one
+    /// is at its index
+    fn rebind(&mut self, slots: &[Slot]);
+
+    /// Value of the expression if it's known before the script runs
+    fn constant(&self) -> Option<&Value> {
+        None
+    }
+
+    /// The expression evaluates to a number whenever it doesn't fail
+    fn is_numeric(&self) -> bool {
+        false
+    }
+}
+
+impl Clone for Box<dyn Expression> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
 }
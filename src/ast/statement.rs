@@ -1,43 +1,380 @@
+use crate::{
+    io::FileMode,
+    variables::{Slot, Variable},
+};
+
 use super::Expression;
 
+#[derive(Clone)]
+pub enum PrintItem {
+    Expression(Box<dyn Expression>),
+    /// TAB(n) moves to the n-th column
+    Tab(Box<dyn Expression>),
+    /// SPC(n) outputs n spaces
+    Spc(Box<dyn Expression>),
+    /// Comma separator moves to the next print zone
+    Zone,
+}
+
+#[derive(Clone)]
 pub enum Statement {
     Assign {
-        name: String,
+        variable: Variable,
         value: Box<dyn Expression>,
     },
     IfThen {
         condition: Box<dyn Expression>,
         label: String,
+        /// Position of the label, resolved before the statements run
+        target: Option<usize>,
+    },
+    /// Single line IF ... THEN with statements, they are executed only
+    /// if the condition is true, otherwise the execution continues at
+    /// the `end` position
+    If {
+        condition: Box<dyn Expression>,
+        end: usize,
     },
+    /// Jumps to the label, a jump to an undefined label fails when it runs
     Goto {
         label: String,
+        target: Option<usize>,
+    },
+    /// Jumps to the subroutine remembering where to RETURN
+    Gosub {
+        label: String,
+        target: Option<usize>,
     },
+    Return,
     Print {
-        expression: Box<dyn Expression>,
+        /// File number, the console is used if it's missing
+        channel: Option<Box<dyn Expression>>,
+        items: Vec<PrintItem>,
+        /// False if the statement ends with a separator
+        newline: bool,
+    },
+    /// PRINT USING format; values
+    PrintUsing {
+        channel: Option<Box<dyn Expression>>,
+        format: Box<dyn Expression>,
+        values: Vec<Box<dyn Expression>>,
+        newline: bool,
     },
+    /// Outputs comma separated values with strings in quotes
+    Write {
+        channel: Option<Box<dyn Expression>>,
+        values: Vec<Box<dyn Expression>>,
+    },
+    /// Reads comma separated values into the variables
     Input {
-        name: String,
+        prompt: String,
+        variables: Vec<Variable>,
+    },
+    /// Reads the whole line into a string variable
+    LineInput {
+        prompt: String,
+        variable: Variable,
+    },
+    /// INPUT #channel, names
+    InputFile {
+        channel: Box<dyn Expression>,
+        variables: Vec<Variable>,
+    },
+    /// LINE INPUT #channel, name
+    LineInputFile {
+        channel: Box<dyn Expression>,
+        variable: Variable,
+    },
+    /// Values are collected at parse time, so DATA does nothing when
+    /// executed. It keeps the statement positions of labels and DATA
+    /// values distinct for RESTORE.
+    Data,
+    /// Assigns the next DATA values to the variables
+    Read {
+        variables: Vec<Variable>,
+    },
+    /// Moves the DATA pointer to the beginning or to the label
+    Restore {
+        label: Option<String>,
+        target: Option<usize>,
+    },
+    /// OPEN path FOR mode AS #number
+    Open {
+        path: Box<dyn Expression>,
+        mode: FileMode,
+        number: Box<dyn Expression>,
+    },
+    /// Closes the files, all of them if the list is empty
+    Close {
+        numbers: Vec<Box<dyn Expression>>,
     },
+    /// Reseeds the random numbers generator
+    Randomize {
+        seed: Option<Box<dyn Expression>>,
+    },
+    /// Pauses execution for the given number of seconds
+    Sleep {
+        seconds: Box<dyn Expression>,
+    },
+    /// MID$(name, start[, length]) = value
+    MidAssign {
+        variable: Variable,
+        start: Box<dyn Expression>,
+        length: Option<Box<dyn Expression>>,
+        value: Box<dyn Expression>,
+    },
+    /// Turns the tracing of the executed statements on
+    Tron,
+    Troff,
 }
 
 impl Statement {
-    pub fn assign(name: String, value: Box<dyn Expression>) -> Self {
-        Self::Assign { name, value }
+    /// Variables the statement assigns when it's executed
+    pub fn assigned_variables(&self) -> Vec<&Variable> {
+        match self {
+            Self::Assign { variable, .. }
+            | Self::LineInput { variable, .. }
+            | Self::LineInputFile { variable, .. }
+            | Self::MidAssign { variable, .. } => vec![variable],
+            Self::Input { variables, .. }
+            | Self::InputFile { variables, .. }
+            | Self::Read { variables } => variables.iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Sets the target of the jump to the position of its label, the
+    /// target stays empty if the label is undefined
+    pub fn resolve_jump(&mut self, position: impl Fn(&str) -> Option<usize>) {
+        match self {
+            Self::Goto { label, target }
+            | Self::Gosub { label, target }
+            | Self::IfThen { label, target, .. } => *target = position(label),
+            Self::Restore { label, target } => *target = label.as_deref().and_then(position),
+            _ => {}
+        }
+    }
+
+    /// Target of GOTO, GOSUB or IF ... THEN label if the label is defined
+    pub fn jump_target_mut(&mut self) -> Option<&mut usize> {
+        match self {
+            Self::Goto { target, .. }
+            | Self::Gosub { target, .. }
+            | Self::IfThen { target, .. } => target.as_mut(),
+            _ => None,
+        }
+    }
+
+    /// Moves the statement positions the statement refers to, the new
+    /// position of each old one is at its index
+    pub fn remap(&mut self, positions: &[usize]) {
+        match self {
+            Self::If { end, .. } => *end = positions[*end],
+            Self::Restore {
+                target: Some(target),
+                ..
+            } => *target = positions[*target],
+            _ => {
+                if let Some(target) = self.jump_target_mut() {
+                    *target = positions[*target];
+                }
+            }
+        }
+    }
+
+    /// Moves the variables the statement refers to to the new slots, the
+    /// new slot of each old one is at its index
+    pub fn rebind(&mut self, slots: &[Slot]) {
+        match self {
+            Self::Assign { variable, .. }
+            | Self::LineInput { variable, .. }
+            | Self::LineInputFile { variable, .. }
+            | Self::MidAssign { variable, .. } => variable.slot = slots[variable.slot],
+            Self::Input { variables, .. }
+            | Self::InputFile { variables, .. }
+            | Self::Read { variables } => {
+                for variable in variables {
+                    variable.slot = slots[variable.slot];
+                }
+            }
+            _ => {}
+        }
+        for expression in self.expressions_mut() {
+            expression.rebind(slots);
+        }
+    }
+
+    /// Expressions of the statement in the order they are evaluated
+    pub fn expressions_mut(&mut self) -> Vec<&mut Box<dyn Expression>> {
+        match self {
+            Self::Assign { value, .. } => vec![value],
+            Self::IfThen { condition, .. } | Self::If { condition, .. } => vec![condition],
+            Self::Print { channel, items, .. } => channel
+                .iter_mut()
+                .chain(items.iter_mut().filter_map(|item| match item {
+                    PrintItem::Expression(expression)
+                    | PrintItem::Tab(expression)
+                    | PrintItem::Spc(expression) => Some(expression),
+                    PrintItem::Zone => None,
+                }))
+                .collect(),
+            Self::PrintUsing {
+                channel,
+                format,
+                values,
+                ..
+            } => [format]
+                .into_iter()
+                .chain(values.iter_mut())
+                .chain(channel.iter_mut())
+                .collect(),
+            Self::Write { channel, values } => {
+                channel.iter_mut().chain(values.iter_mut()).collect()
+            }
+            Self::InputFile { channel, .. } | Self::LineInputFile { channel, .. } => {
+                vec![channel]
+            }
+            Self::Open { path, number, .. } => vec![path, number],
+            Self::Close { numbers } => numbers.iter_mut().collect(),
+            Self::Randomize { seed } => seed.iter_mut().collect(),
+            Self::Sleep { seconds } => vec![seconds],
+            Self::MidAssign {
+                start,
+                length,
+                value,
+                ..
+            } => [start]
+                .into_iter()
+                .chain(length.iter_mut())
+                .chain([value])
+                .collect(),
+            Self::Goto { .. }
+            | Self::Gosub { .. }
+            | Self::Return
+            | Self::Input { .. }
+            | Self::LineInput { .. }
+            | Self::Data
+            | Self::Read { .. }
+            | Self::Restore { .. }
+            | Self::Tron
+            | Self::Troff => Vec::new(),
+        }
+    }
+
+    pub fn assign(variable: Variable, value: Box<dyn Expression>) -> Self {
+        Self::Assign { variable, value }
     }
 
     pub fn goto(label: String) -> Self {
-        Self::Goto { label }
+        Self::Goto {
+            label,
+            target: None,
+        }
+    }
+
+    pub fn gosub(label: String) -> Self {
+        Self::Gosub {
+            label,
+            target: None,
+        }
     }
 
     pub fn if_then(condition: Box<dyn Expression>, label: String) -> Self {
-        Self::IfThen { condition, label }
+        Self::IfThen {
+            condition,
+            label,
+            target: None,
+        }
+    }
+
+    pub fn if_block(condition: Box<dyn Expression>, end: usize) -> Self {
+        Self::If { condition, end }
+    }
+
+    pub fn input(prompt: String, variables: Vec<Variable>) -> Self {
+        Self::Input { prompt, variables }
+    }
+
+    pub fn line_input(prompt: String, variable: Variable) -> Self {
+        Self::LineInput { prompt, variable }
+    }
+
+    pub fn input_file(channel: Box<dyn Expression>, variables: Vec<Variable>) -> Self {
+        Self::InputFile { channel, variables }
+    }
+
+    pub fn line_input_file(channel: Box<dyn Expression>, variable: Variable) -> Self {
+        Self::LineInputFile { channel, variable }
+    }
+
+    pub fn print(
+        channel: Option<Box<dyn Expression>>,
+        items: Vec<PrintItem>,
+        newline: bool,
+    ) -> Self {
+        Self::Print {
+            channel,
+            items,
+            newline,
+        }
     }
 
-    pub fn input(name: String) -> Self {
-        Self::Input { name }
+    pub fn print_using(
+        channel: Option<Box<dyn Expression>>,
+        format: Box<dyn Expression>,
+        values: Vec<Box<dyn Expression>>,
+        newline: bool,
+    ) -> Self {
+        Self::PrintUsing {
+            channel,
+            format,
+            values,
+            newline,
+        }
     }
 
-    pub fn print(expression: Box<dyn Expression>) -> Self {
-        Self::Print { expression }
+    pub fn write(channel: Option<Box<dyn Expression>>, values: Vec<Box<dyn Expression>>) -> Self {
+        Self::Write { channel, values }
+    }
+
+    pub fn read(variables: Vec<Variable>) -> Self {
+        Self::Read { variables }
+    }
+
+    pub fn restore(label: Option<String>) -> Self {
+        Self::Restore {
+            label,
+            target: None,
+        }
+    }
+
+    pub fn open(path: Box<dyn Expression>, mode: FileMode, number: Box<dyn Expression>) -> Self {
+        Self::Open { path, mode, number }
+    }
+
+    pub fn close(numbers: Vec<Box<dyn Expression>>) -> Self {
+        Self::Close { numbers }
+    }
+
+    pub fn randomize(seed: Option<Box<dyn Expression>>) -> Self {
+        Self::Randomize { seed }
+    }
+
+    pub fn sleep(seconds: Box<dyn Expression>) -> Self {
+        Self::Sleep { seconds }
+    }
+
+    pub fn mid_assign(
+        variable: Variable,
+        start: Box<dyn Expression>,
+        length: Option<Box<dyn Expression>>,
+        value: Box<dyn Expression>,
+    ) -> Self {
+        Self::MidAssign {
+            variable,
+            start,
+            length,
+            value,
+        }
     }
 }
@@ -1,19 +1,48 @@
-use crate::interpreter::InterpreterContext;
+use crate::{
+    error::RuntimeError,
+    interpreter::InterpreterContext,
+    variables::Slot,
+    vm::{compiler::Compiler, Instruction},
+};
 
 use super::Expression;
 
 pub type Double = f32;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     /// A numeric value. All numbers are represented as doubles
     Number(Double),
     Str(String),
+    /// A list of values, e.g. the result of SPLIT
+    Array(Vec<Value>),
 }
 
 impl Expression for Value {
-    fn evaluate(&self, _context: &InterpreterContext) -> Value {
-        self.clone()
+    fn evaluate(&self, _context: &mut InterpreterContext) -> Result<Value, RuntimeError> {
+        Ok(self.clone())
+    }
+
+    fn compile(&self, compiler: &mut Compiler) {
+        compiler.emit(Instruction::Push(self.clone()));
+    }
+
+    fn optimize(self: Box<Self>) -> Box<dyn Expression> {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+
+    fn rebind(&mut self, _slots: &[Slot]) {}
+
+    fn constant(&self) -> Option<&Value> {
+        Some(self)
+    }
+
+    fn is_numeric(&self) -> bool {
+        Value::is_numeric(self)
     }
 }
 
@@ -26,6 +55,10 @@ impl Value {
         Self::Str(value)
     }
 
+    pub fn array(value: Vec<Value>) -> Self {
+        Self::Array(value)
+    }
+
     pub fn with_bool(value: bool) -> Self {
         Self::Number(if value { 1.0 } else { 0.0 })
     }
@@ -34,13 +67,23 @@ impl Value {
         match self {
             Value::Number(val) => val.to_string(),
             Value::Str(val) => val.clone(),
+            Value::Array(items) => items
+                .iter()
+                .map(|item| item.to_text())
+                .collect::<Vec<_>>()
+                .join(","),
         }
     }
 
-    pub fn to_number(&self) -> Double {
+    /// Fails with type mismatch if the value isn't a number or a numeric
+    /// string
+    pub fn try_number(&self) -> Result<Double, RuntimeError> {
         match self {
-            Value::Number(val) => *val,
-            Value::Str(val) => val.parse::<Double>().unwrap(),
+            Value::Number(val) => Ok(*val),
+            Value::Str(val) => val
+                .parse::<Double>()
+                .map_err(|_| RuntimeError::TypeMismatch),
+            Value::Array(_) => Err(RuntimeError::TypeMismatch),
         }
     }
 
@@ -0,0 +1,22 @@
+use crate::{
+    ast::value::{Double, Value},
+    error::RuntimeError,
+    interpreter::InterpreterContext,
+};
+
+use super::integer_arg;
+
+/// EOF(n) is true when there is nothing left to read from the file
+pub fn eof(context: &mut InterpreterContext, args: &[Value]) -> Result<Value, RuntimeError> {
+    let number = integer_arg(args, 0)?;
+    let val = context.files().is_eof(number)?;
+    Ok(Value::with_bool(val))
+}
+
+/// FREEFILE returns the smallest file number available for OPEN
+pub fn freefile(context: &mut InterpreterContext, _args: &[Value]) -> Result<Value, RuntimeError> {
+    let Some(number) = context.files().free_number() else {
+        return Err(RuntimeError::IllegalFunctionCall("FREEFILE".to_string()));
+    };
+    Ok(Value::number(number as Double))
+}
@@ -0,0 +1,26 @@
+use crate::{
+    ast::value::Value, error::RuntimeError, interpreter::InterpreterContext, random::Random,
+};
+
+use super::number_arg;
+
+/// RND returns the next random number in [0, 1), RND(0) repeats the
+/// previous number and RND(n) with negative n reseeds the generator first
+pub fn rnd(context: &mut InterpreterContext, args: &[Value]) -> Result<Value, RuntimeError> {
+    let random = context.random();
+    let val = match args.first() {
+        Some(_) => {
+            let val = number_arg(args, 0)?;
+            if val == 0.0 {
+                random.last_double()
+            } else {
+                if val < 0.0 {
+                    random.reseed(Random::seed_from(val));
+                }
+                random.next_double()
+            }
+        }
+        None => random.next_double(),
+    };
+    Ok(Value::number(val))
+}
@@ -0,0 +1,181 @@
+use crate::{
+    ast::value::{Double, Value},
+    error::RuntimeError,
+    interpreter::InterpreterContext,
+};
+
+pub mod file;
+pub mod math;
+pub mod string;
+pub mod time;
+
+pub enum BuiltinFunction {
+    /// Result depends on arguments only
+    Pure(fn(&[Value]) -> Result<Value, RuntimeError>),
+    /// Function reads or modifies the interpreter state
+    Stateful(fn(&mut InterpreterContext, &[Value]) -> Result<Value, RuntimeError>),
+}
+
+/// Description of a function available to scripts without declaration
+pub struct Builtin {
+    pub name: &'static str,
+    /// Parameters as they are shown to the user, the optional ones are in
+    /// brackets, e.g. `text$, start[, count]`
+    pub parameters: &'static str,
+    pub min_args: usize,
+    pub max_args: usize,
+    pub function: BuiltinFunction,
+    /// False if the size of the result depends on the arguments, such
+    /// functions aren't called before the script runs
+    pub foldable: bool,
+}
+
+impl Builtin {
+    const fn new(
+        name: &'static str,
+        parameters: &'static str,
+        min_args: usize,
+        max_args: usize,
+        function: fn(&[Value]) -> Result<Value, RuntimeError>,
+    ) -> Self {
+        Self {
+            name,
+            parameters,
+            min_args,
+            max_args,
+            function: BuiltinFunction::Pure(function),
+            foldable: true,
+        }
+    }
+
+    const fn stateful(
+        name: &'static str,
+        parameters: &'static str,
+        min_args: usize,
+        max_args: usize,
+        function: fn(&mut InterpreterContext, &[Value]) -> Result<Value, RuntimeError>,
+    ) -> Self {
+        Self {
+            name,
+            parameters,
+            min_args,
+            max_args,
+            function: BuiltinFunction::Stateful(function),
+            foldable: false,
+        }
+    }
+
+    /// Function that is never folded into a constant
+    const fn unfoldable(self) -> Self {
+        Self {
+            foldable: false,
+            ..self
+        }
+    }
+
+    /// Usage of the function, e.g. `LEFT$(text$, count)`
+    pub fn signature(&self) -> String {
+        if self.max_args == 0 {
+            return self.name.to_string();
+        }
+        format!("{}({})", self.name, self.parameters)
+    }
+
+    pub fn accepts(&self, count: usize) -> bool {
+        (self.min_args..=self.max_args).contains(&count)
+    }
+
+    pub fn call(
+        &self,
+        context: &mut InterpreterContext,
+        args: &[Value],
+    ) -> Result<Value, RuntimeError> {
+        match self.function {
+            BuiltinFunction::Pure(function) => function(args),
+            BuiltinFunction::Stateful(function) => function(context, args),
+        }
+    }
+}
+
+const BUILTINS: &[Builtin] = &[
+    Builtin::new("LEN", "text$", 1, 1, string::len),
+    Builtin::new("LEFT$", "text$, count", 2, 2, string::left),
+    Builtin::new("RIGHT$", "text$, count", 2, 2, string::right),
+    Builtin::new("MID$", "text$, start[, count]", 2, 3, string::mid),
+    Builtin::new("INSTR", "[start, ]text$, pattern$", 2, 3, string::instr),
+    Builtin::new("UCASE$", "text$", 1, 1, string::ucase),
+    Builtin::new("LCASE$", "text$", 1, 1, string::lcase),
+    Builtin::new("LTRIM$", "text$", 1, 1, string::ltrim),
+    Builtin::new("RTRIM$", "text$", 1, 1, string::rtrim),
+    Builtin::new("TRIM$", "text$", 1, 1, string::trim),
+    Builtin::new("SPACE$", "count", 1, 1, string::space).unfoldable(),
+    Builtin::new("STRING$", "count, char", 2, 2, string::string).unfoldable(),
+    Builtin::new(
+        "REPLACE$",
+        "text$, pattern$, replacement$",
+        3,
+        3,
+        string::replace,
+    ),
+    Builtin::new("SPLIT", "text$, delimiter$", 2, 2, string::split),
+    Builtin::new("JOIN$", "items, delimiter$", 2, 2, string::join),
+    Builtin::new("STR$", "value", 1, 1, string::str),
+    Builtin::new("VAL", "text$", 1, 1, string::val),
+    Builtin::new("CHR$", "code", 1, 1, string::chr),
+    Builtin::new("ASC", "text$", 1, 1, string::asc),
+    Builtin::new("FORMAT$", "value, pattern$", 2, 2, string::format),
+    Builtin::stateful("RND", "[n]", 0, 1, math::rnd),
+    Builtin::stateful("TIMER", "", 0, 0, time::timer),
+    Builtin::stateful("NOW", "", 0, 0, time::now),
+    Builtin::stateful("DATE$", "", 0, 0, time::date),
+    Builtin::stateful("TIME$", "", 0, 0, time::time),
+    Builtin::new("DATEADD", "interval$, count, date$", 3, 3, time::date_add),
+    Builtin::new(
+        "DATEDIFF",
+        "interval$, date1$, date2$",
+        3,
+        3,
+        time::date_diff,
+    ),
+    Builtin::new("FORMATDATE$", "date$, pattern$", 2, 2, time::format_date),
+    Builtin::stateful("EOF", "number", 1, 1, file::eof),
+    Builtin::stateful("FREEFILE", "", 0, 0, file::freefile),
+];
+
+/// All built-in functions in the order they are listed in the table
+#[allow(dead_code)] // Only the language server of the binary lists them
+pub fn all() -> &'static [Builtin] {
+    BUILTINS
+}
+
+/// Finds a built-in function by its case insensitive name
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    BUILTINS
+        .iter()
+        .find(|builtin| builtin.name.eq_ignore_ascii_case(name))
+}
+
+fn text_arg(args: &[Value], index: usize) -> Result<&str, RuntimeError> {
+    match &args[index] {
+        Value::Str(val) => Ok(val),
+        _ => Err(RuntimeError::TypeMismatch),
+    }
+}
+
+fn number_arg(args: &[Value], index: usize) -> Result<Double, RuntimeError> {
+    match &args[index] {
+        Value::Number(val) => Ok(*val),
+        _ => Err(RuntimeError::TypeMismatch),
+    }
+}
+
+/// Numeric argument rounded to the nearest integer
+fn integer_arg(args: &[Value], index: usize) -> Result<i64, RuntimeError> {
+    number_arg(args, index).map(|val| val.round() as i64)
+}
+
+/// Integer argument that can't be negative, e.g. a length or a count
+fn count_arg(args: &[Value], index: usize, name: &str) -> Result<usize, RuntimeError> {
+    let val = integer_arg(args, index)?;
+    usize::try_from(val).map_err(|_| RuntimeError::IllegalFunctionCall(name.to_string()))
+}
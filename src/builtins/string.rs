@@ -0,0 +1,416 @@
+//! String functions. All positions and lengths are measured in characters
+//! rather than bytes, positions are 1-based as usual in BASIC.
+
+use crate::{
+    ast::value::{Double, Value},
+    error::RuntimeError,
+    format::format_using,
+};
+
+use super::{count_arg, integer_arg, number_arg, text_arg};
+
+/// Longest string SPACE$ and STRING$ build, so that a large count fails
+/// with an error instead of exhausting the memory
+const MAX_STRING_LENGTH: usize = 65535;
+
+pub fn len(args: &[Value]) -> Result<Value, RuntimeError> {
+    let length = match &args[0] {
+        Value::Str(val) => val.chars().count(),
+        Value::Array(items) => items.len(),
+        Value::Number(_) => return Err(RuntimeError::TypeMismatch),
+    };
+    Ok(Value::number(length as Double))
+}
+
+pub fn left(args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = text_arg(args, 0)?;
+    let count = count_arg(args, 1, "LEFT$")?;
+    Ok(Value::string(text.chars().take(count).collect()))
+}
+
+pub fn right(args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = text_arg(args, 0)?;
+    let count = count_arg(args, 1, "RIGHT$")?;
+    let skip = text.chars().count().saturating_sub(count);
+    Ok(Value::string(text.chars().skip(skip).collect()))
+}
+
+pub fn mid(args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = text_arg(args, 0)?;
+    let start = start_arg(args, 1, "MID$")?;
+    let count = if args.len() > 2 {
+        count_arg(args, 2, "MID$")?
+    } else {
+        usize::MAX
+    };
+    Ok(Value::string(
+        text.chars().skip(start).take(count).collect(),
+    ))
+}
+
+/// Overwrites characters of `text` starting at 1-based `start` position
+/// with `replacement` keeping the original length, as MID$ statement does
+pub fn mid_replace(
+    text: &str,
+    start: i64,
+    count: Option<i64>,
+    replacement: &str,
+) -> Result<String, RuntimeError> {
+    let illegal_call = || RuntimeError::IllegalFunctionCall("MID$".to_string());
+    let mut chars = text.chars().collect::<Vec<_>>();
+    if start < 1 || start as usize > chars.len() {
+        return Err(illegal_call());
+    }
+    let start = start as usize - 1;
+    let count = match count {
+        Some(val) => usize::try_from(val).map_err(|_| illegal_call())?,
+        None => usize::MAX,
+    };
+    chars
+        .iter_mut()
+        .skip(start)
+        .zip(replacement.chars().take(count))
+        .for_each(|(dest, src)| *dest = src);
+    Ok(chars.into_iter().collect())
+}
+
+pub fn instr(args: &[Value]) -> Result<Value, RuntimeError> {
+    let (start, text, pattern) = if args.len() > 2 {
+        (
+            start_arg(args, 0, "INSTR")?,
+            text_arg(args, 1)?,
+            text_arg(args, 2)?,
+        )
+    } else {
+        (0, text_arg(args, 0)?, text_arg(args, 1)?)
+    };
+    let haystack = text.chars().collect::<Vec<_>>();
+    let needle = pattern.chars().collect::<Vec<_>>();
+    let position = if start >= haystack.len() || needle.len() > haystack.len() {
+        0
+    } else {
+        (start..=haystack.len() - needle.len())
+            .find(|pos| haystack[*pos..].starts_with(&needle))
+            .map(|pos| pos + 1)
+            .unwrap_or(0)
+    };
+    Ok(Value::number(position as Double))
+}
+
+pub fn ucase(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::string(text_arg(args, 0)?.to_uppercase()))
+}
+
+pub fn lcase(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::string(text_arg(args, 0)?.to_lowercase()))
+}
+
+pub fn ltrim(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::string(text_arg(args, 0)?.trim_start().to_string()))
+}
+
+pub fn rtrim(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::string(text_arg(args, 0)?.trim_end().to_string()))
+}
+
+pub fn trim(args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::string(text_arg(args, 0)?.trim().to_string()))
+}
+
+pub fn space(args: &[Value]) -> Result<Value, RuntimeError> {
+    let count = length_arg(args, 0, "SPACE$")?;
+    Ok(Value::string(" ".repeat(count)))
+}
+
+pub fn string(args: &[Value]) -> Result<Value, RuntimeError> {
+    let count = length_arg(args, 0, "STRING$")?;
+    let ch = match &args[1] {
+        Value::Str(val) => val.chars().next(),
+        Value::Number(_) => code_to_char(integer_arg(args, 1)?),
+        Value::Array(_) => return Err(RuntimeError::TypeMismatch),
+    };
+    let Some(ch) = ch else {
+        return Err(RuntimeError::IllegalFunctionCall("STRING$".to_string()));
+    };
+    Ok(Value::string(std::iter::repeat_n(ch, count).collect()))
+}
+
+pub fn replace(args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = text_arg(args, 0)?;
+    let pattern = text_arg(args, 1)?;
+    let replacement = text_arg(args, 2)?;
+    if pattern.is_empty() {
+        return Ok(Value::string(text.to_string()));
+    }
+    Ok(Value::string(text.replace(pattern, replacement)))
+}
+
+pub fn split(args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = text_arg(args, 0)?;
+    let delimiter = text_arg(args, 1)?;
+    let items = if delimiter.is_empty() {
+        text.chars()
+            .map(|ch| Value::string(ch.to_string()))
+            .collect()
+    } else {
+        text.split(delimiter)
+            .map(|item| Value::string(item.to_string()))
+            .collect()
+    };
+    Ok(Value::array(items))
+}
+
+pub fn join(args: &[Value]) -> Result<Value, RuntimeError> {
+    let Value::Array(items) = &args[0] else {
+        return Err(RuntimeError::TypeMismatch);
+    };
+    let delimiter = text_arg(args, 1)?;
+    let text = items
+        .iter()
+        .map(|item| item.to_text())
+        .collect::<Vec<_>>()
+        .join(delimiter);
+    Ok(Value::string(text))
+}
+
+pub fn str(args: &[Value]) -> Result<Value, RuntimeError> {
+    let val = number_arg(args, 0)?;
+    // Non-negative numbers get a leading space in place of a sign
+    let text = if val.is_sign_negative() {
+        val.to_string()
+    } else {
+        format!(" {val}")
+    };
+    Ok(Value::string(text))
+}
+
+pub fn val(args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = text_arg(args, 0)?.trim_start();
+    let bytes = text.as_bytes();
+    let digits_from = |mut pos: usize| {
+        while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+            pos += 1;
+        }
+        pos
+    };
+    let mut end = 0;
+    if matches!(bytes.first(), Some(b'+' | b'-')) {
+        end += 1;
+    }
+    end = digits_from(end);
+    if bytes.get(end) == Some(&b'.') {
+        end = digits_from(end + 1);
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let mut exp_start = end + 1;
+        if matches!(bytes.get(exp_start), Some(b'+' | b'-')) {
+            exp_start += 1;
+        }
+        let exp_end = digits_from(exp_start);
+        if exp_end > exp_start {
+            end = exp_end;
+        }
+    }
+    let val = text[..end].parse::<Double>().unwrap_or(0.0);
+    Ok(Value::number(val))
+}
+
+pub fn chr(args: &[Value]) -> Result<Value, RuntimeError> {
+    let Some(ch) = code_to_char(integer_arg(args, 0)?) else {
+        return Err(RuntimeError::IllegalFunctionCall("CHR$".to_string()));
+    };
+    Ok(Value::string(ch.to_string()))
+}
+
+pub fn asc(args: &[Value]) -> Result<Value, RuntimeError> {
+    let Some(ch) = text_arg(args, 0)?.chars().next() else {
+        return Err(RuntimeError::IllegalFunctionCall("ASC".to_string()));
+    };
+    Ok(Value::number(ch as u32 as Double))
+}
+
+/// FORMAT$(value, pattern$) formats the value like PRINT USING does
+pub fn format(args: &[Value]) -> Result<Value, RuntimeError> {
+    let pattern = text_arg(args, 1)?;
+    format_using(pattern, &args[..1]).map(Value::string)
+}
+
+fn code_to_char(code: i64) -> Option<char> {
+    u32::try_from(code).ok().and_then(char::from_u32)
+}
+
+/// Length of a string to build, at most `MAX_STRING_LENGTH` characters
+fn length_arg(args: &[Value], index: usize, name: &str) -> Result<usize, RuntimeError> {
+    let count = count_arg(args, index, name)?;
+    if count > MAX_STRING_LENGTH {
+        return Err(RuntimeError::IllegalFunctionCall(name.to_string()));
+    }
+    Ok(count)
+}
+
+/// 1-based position argument converted to 0-based index
+fn start_arg(args: &[Value], index: usize, name: &str) -> Result<usize, RuntimeError> {
+    let val = integer_arg(args, index)?;
+    if val < 1 {
+        return Err(RuntimeError::IllegalFunctionCall(name.to_string()));
+    }
+    Ok(val as usize - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn text(val: &str) -> Value {
+        Value::string(val.to_string())
+    }
+
+    fn number(val: Double) -> Value {
+        Value::number(val)
+    }
+
+    #[test]
+    fn len_counts_characters() {
+        let val = len(&[text("héllo, мир")]).unwrap();
+        assert_eq!(val.try_number().unwrap(), 10.0);
+    }
+
+    #[test]
+    fn len_number_fail() {
+        assert_eq!(len(&[number(1.0)]), Err(RuntimeError::TypeMismatch));
+    }
+
+    #[test]
+    fn left_right_unicode() {
+        let val = left(&[text("привет"), number(3.0)]).unwrap();
+        assert_eq!(val.to_text(), "при");
+        let val = right(&[text("привет"), number(3.0)]).unwrap();
+        assert_eq!(val.to_text(), "вет");
+        let val = right(&[text("ab"), number(5.0)]).unwrap();
+        assert_eq!(val.to_text(), "ab");
+    }
+
+    #[test]
+    fn left_negative_fail() {
+        let result = left(&[text("abc"), number(-1.0)]);
+        assert!(matches!(result, Err(RuntimeError::IllegalFunctionCall(_))));
+    }
+
+    #[test]
+    fn mid_substring() {
+        let val = mid(&[text("日本語テキスト"), number(3.0), number(2.0)]).unwrap();
+        assert_eq!(val.to_text(), "語テ");
+        let val = mid(&[text("abcdef"), number(4.0)]).unwrap();
+        assert_eq!(val.to_text(), "def");
+        let val = mid(&[text("abc"), number(10.0)]).unwrap();
+        assert_eq!(val.to_text(), "");
+    }
+
+    #[test]
+    fn mid_zero_start_fail() {
+        let result = mid(&[text("abc"), number(0.0)]);
+        assert!(matches!(result, Err(RuntimeError::IllegalFunctionCall(_))));
+    }
+
+    #[test]
+    fn mid_replace_keeps_length() {
+        assert_eq!(mid_replace("abcdef", 2, None, "XY").unwrap(), "aXYdef");
+        assert_eq!(mid_replace("abcdef", 5, None, "XYZ").unwrap(), "abcdXY");
+        assert_eq!(mid_replace("abcdef", 1, Some(1), "XYZ").unwrap(), "Xbcdef");
+        assert_eq!(mid_replace("мир", 2, None, "ЁЖ").unwrap(), "мЁЖ");
+        assert!(mid_replace("abc", 4, None, "X").is_err());
+    }
+
+    #[test]
+    fn instr_positions() {
+        let val = instr(&[text("hello world"), text("o")]).unwrap();
+        assert_eq!(val.try_number().unwrap(), 5.0);
+        let val = instr(&[number(6.0), text("hello world"), text("o")]).unwrap();
+        assert_eq!(val.try_number().unwrap(), 8.0);
+        let val = instr(&[text("привет"), text("вет")]).unwrap();
+        assert_eq!(val.try_number().unwrap(), 4.0);
+        let val = instr(&[text("abc"), text("x")]).unwrap();
+        assert_eq!(val.try_number().unwrap(), 0.0);
+        let val = instr(&[text("abc"), text("")]).unwrap();
+        assert_eq!(val.try_number().unwrap(), 1.0);
+    }
+
+    #[test]
+    fn case_conversion() {
+        assert_eq!(ucase(&[text("straße")]).unwrap().to_text(), "STRASSE");
+        assert_eq!(lcase(&[text("ÀBC")]).unwrap().to_text(), "àbc");
+    }
+
+    #[test]
+    fn trimming() {
+        assert_eq!(ltrim(&[text("  a ")]).unwrap().to_text(), "a ");
+        assert_eq!(rtrim(&[text("  a ")]).unwrap().to_text(), "  a");
+        assert_eq!(trim(&[text("  a ")]).unwrap().to_text(), "a");
+    }
+
+    #[test]
+    fn repeated_strings() {
+        assert_eq!(space(&[number(3.0)]).unwrap().to_text(), "   ");
+        let val = string(&[number(3.0), text("ж")]).unwrap();
+        assert_eq!(val.to_text(), "жжж");
+        let val = string(&[number(2.0), number(65.0)]).unwrap();
+        assert_eq!(val.to_text(), "AA");
+    }
+
+    #[test]
+    fn space_too_long_fail() {
+        let limit = MAX_STRING_LENGTH as Double;
+        assert_eq!(
+            space(&[number(limit)]).unwrap().to_text().len(),
+            MAX_STRING_LENGTH
+        );
+        let result = space(&[number(1e18)]);
+        assert!(matches!(result, Err(RuntimeError::IllegalFunctionCall(_))));
+    }
+
+    #[test]
+    fn string_too_long_fail() {
+        let result = string(&[number(MAX_STRING_LENGTH as Double + 1.0), text("a")]);
+        assert!(matches!(result, Err(RuntimeError::IllegalFunctionCall(_))));
+        let result = string(&[number(1e12), number(65.0)]);
+        assert!(matches!(result, Err(RuntimeError::IllegalFunctionCall(_))));
+    }
+
+    #[test]
+    fn replace_all() {
+        let val = replace(&[text("a-b-c"), text("-"), text("+")]).unwrap();
+        assert_eq!(val.to_text(), "a+b+c");
+    }
+
+    #[test]
+    fn split_join() {
+        let parts = split(&[text("a,b,,c"), text(",")]).unwrap();
+        let Value::Array(items) = &parts else {
+            panic!("Array expected")
+        };
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[2].to_text(), "");
+        let val = join(&[parts, text("; ")]).unwrap();
+        assert_eq!(val.to_text(), "a; b; ; c");
+    }
+
+    #[test]
+    fn str_val_conversion() {
+        assert_eq!(str(&[number(5.0)]).unwrap().to_text(), " 5");
+        assert_eq!(str(&[number(-2.5)]).unwrap().to_text(), "-2.5");
+        assert_eq!(
+            val(&[text(" 12.5abc")]).unwrap().try_number().unwrap(),
+            12.5
+        );
+        assert_eq!(val(&[text("-3e2")]).unwrap().try_number().unwrap(), -300.0);
+        assert_eq!(val(&[text("abc")]).unwrap().try_number().unwrap(), 0.0);
+    }
+
+    #[test]
+    fn chr_asc_conversion() {
+        assert_eq!(chr(&[number(1046.0)]).unwrap().to_text(), "Ж");
+        assert_eq!(asc(&[text("Ж")]).unwrap().try_number().unwrap(), 1046.0);
+        assert!(asc(&[text("")]).is_err());
+        assert!(chr(&[number(-1.0)]).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{
+    ast::value::{Double, Value},
+    datetime::{DateTime, Interval},
+    error::RuntimeError,
+    interpreter::InterpreterContext,
+};
+
+use super::{integer_arg, text_arg};
+
+/// Seconds since midnight
+pub fn timer(context: &mut InterpreterContext, _args: &[Value]) -> Result<Value, RuntimeError> {
+    let seconds = context.clock().now().as_secs_f64() % 86400.0;
+    Ok(Value::number(seconds as Double))
+}
+
+pub fn now(context: &mut InterpreterContext, _args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::string(current_date(context).to_string()))
+}
+
+pub fn date(context: &mut InterpreterContext, _args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::string(current_date(context).format("mm-dd-yyyy")))
+}
+
+pub fn time(context: &mut InterpreterContext, _args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::string(current_date(context).format("hh:nn:ss")))
+}
+
+/// DATEADD(interval$, count, date$)
+pub fn date_add(args: &[Value]) -> Result<Value, RuntimeError> {
+    let interval = interval_arg(args, 0, "DATEADD")?;
+    let count = integer_arg(args, 1)?;
+    let date = date_arg(args, 2, "DATEADD")?;
+    let date = date
+        .add(interval, count)
+        .ok_or_else(|| RuntimeError::IllegalFunctionCall("DATEADD".to_string()))?;
+    Ok(Value::string(date.to_string()))
+}
+
+/// DATEDIFF(interval$, date1$, date2$)
+pub fn date_diff(args: &[Value]) -> Result<Value, RuntimeError> {
+    let interval = interval_arg(args, 0, "DATEDIFF")?;
+    let first = date_arg(args, 1, "DATEDIFF")?;
+    let second = date_arg(args, 2, "DATEDIFF")?;
+    Ok(Value::number(first.diff(&second, interval) as Double))
+}
+
+/// FORMATDATE$(date$, pattern$)
+pub fn format_date(args: &[Value]) -> Result<Value, RuntimeError> {
+    let date = date_arg(args, 0, "FORMATDATE$")?;
+    let pattern = text_arg(args, 1)?;
+    Ok(Value::string(date.format(pattern)))
+}
+
+fn current_date(context: &InterpreterContext) -> DateTime {
+    DateTime::from_timestamp(context.clock().now().as_secs() as i64)
+}
+
+fn interval_arg(args: &[Value], index: usize, name: &str) -> Result<Interval, RuntimeError> {
+    Interval::parse(text_arg(args, index)?)
+        .ok_or_else(|| RuntimeError::IllegalFunctionCall(name.to_string()))
+}
+
+fn date_arg(args: &[Value], index: usize, name: &str) -> Result<DateTime, RuntimeError> {
+    DateTime::parse(text_arg(args, index)?)
+        .ok_or_else(|| RuntimeError::IllegalFunctionCall(name.to_string()))
+}
@@ -0,0 +1,211 @@
+use std::path::PathBuf;
+
+use crate::{
+    interpreter::{Backend, Config},
+    trace::TraceFilter,
+};
+
+/// Options passed to the binary in the command line
+#[derive(Default)]
+pub struct Options {
+    pub script: Option<String>,
+    pub config: Config,
+    /// Serve the debug adapter protocol on the standard input and output
+    pub dap: bool,
+    /// Serve the language server protocol on the standard input and output
+    pub lsp: bool,
+}
+
+pub fn parse_options(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let value = args.next().ok_or("Missing value for --seed")?;
+                let seed = value
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid seed '{value}'"))?;
+                options.config.seed = Some(seed);
+            }
+            "--allow-dir" => {
+                let dir = args.next().ok_or("Missing value for --allow-dir")?;
+                options.config.sandbox.allowed_dirs.push(PathBuf::from(dir));
+            }
+            "--debug" => options.config.debug = true,
+            "--trace" => options.config.trace.enabled = true,
+            "--profile" => options.config.profile.enabled = true,
+            "--coverage" => options.config.coverage.enabled = true,
+            "--lcov" => {
+                let path = args.next().ok_or("Missing value for --lcov")?;
+                options.config.coverage.enabled = true;
+                options.config.coverage.lcov_file = Some(PathBuf::from(path));
+            }
+            "--profile-stacks" => {
+                let path = args.next().ok_or("Missing value for --profile-stacks")?;
+                options.config.profile.enabled = true;
+                options.config.profile.stacks_file = Some(PathBuf::from(path));
+            }
+            "--trace-values" => options.config.trace.values = true,
+            "--trace-file" => {
+                let path = args.next().ok_or("Missing value for --trace-file")?;
+                options.config.trace.file = Some(PathBuf::from(path));
+            }
+            "--trace-filter" => {
+                let value = args.next().ok_or("Missing value for --trace-filter")?;
+                let filter = TraceFilter::parse_list(&value)?;
+                options.config.trace.filter.extend(filter);
+            }
+            "--backend" => {
+                let value = args.next().ok_or("Missing value for --backend")?;
+                options.config.backend = match value.as_str() {
+                    "ast" => Backend::Ast,
+                    "bytecode" => Backend::Bytecode,
+                    _ => return Err(format!("Unknown backend '{value}'")),
+                };
+            }
+            "--no-optimize" => options.config.no_optimize = true,
+            "--dap" => options.dap = true,
+            "--lsp" => options.lsp = true,
+            "--read-only" => options.config.sandbox.read_only = true,
+            "--no-files" => options.config.sandbox.deny_all = true,
+            "--max-file-size" => {
+                let value = args.next().ok_or("Missing value for --max-file-size")?;
+                let size = value
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid file size '{value}'"))?;
+                options.config.sandbox.max_file_size = Some(size);
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {arg}")),
+            _ if options.script.is_none() => options.script = Some(arg),
+            _ => return Err(format!("Unexpected argument {arg}")),
+        }
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_options(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn options_script() {
+        let options = parse(&["script.bas"]).unwrap();
+        assert_eq!(options.script.as_deref(), Some("script.bas"));
+        assert_eq!(options.config.seed, None);
+    }
+
+    #[test]
+    fn options_seed() {
+        let options = parse(&["--seed", "42", "script.bas"]).unwrap();
+        assert_eq!(options.script.as_deref(), Some("script.bas"));
+        assert_eq!(options.config.seed, Some(42));
+        let options = parse(&["--seed", "18446744073709551615"]).unwrap();
+        assert_eq!(options.config.seed, Some(u64::MAX));
+        assert!(!options.config.debug);
+        assert!(parse(&["--debug", "script.bas"]).unwrap().config.debug);
+        assert!(parse(&["--dap"]).unwrap().dap);
+        assert!(parse(&["--lsp"]).unwrap().lsp);
+    }
+
+    #[test]
+    fn options_sandbox() {
+        let args = [
+            "--allow-dir",
+            "data",
+            "--read-only",
+            "--allow-dir",
+            "/tmp",
+            "--max-file-size",
+            "1024",
+            "script.bas",
+        ];
+        let options = parse(&args).unwrap();
+        let sandbox = options.config.sandbox;
+        assert_eq!(
+            sandbox.allowed_dirs,
+            [PathBuf::from("data"), PathBuf::from("/tmp")]
+        );
+        assert!(sandbox.read_only);
+        assert_eq!(sandbox.max_file_size, Some(1024));
+        assert!(!sandbox.deny_all);
+        assert!(parse(&["--no-files"]).unwrap().config.sandbox.deny_all);
+    }
+
+    #[test]
+    fn options_trace() {
+        let args = [
+            "--trace",
+            "--trace-values",
+            "--trace-file",
+            "trace.log",
+            "--trace-filter",
+            "sub,1-5",
+            "script.bas",
+        ];
+        let trace = parse(&args).unwrap().config.trace;
+        assert!(trace.enabled);
+        assert!(trace.values);
+        assert_eq!(trace.file, Some(PathBuf::from("trace.log")));
+        assert_eq!(trace.filter.len(), 2);
+        assert!(!parse(&["script.bas"]).unwrap().config.trace.enabled);
+        assert!(parse(&["--trace-filter", "9-1"]).is_err());
+        assert!(parse(&["--trace-file"]).is_err());
+    }
+
+    #[test]
+    fn options_profile() {
+        let profile = parse(&["--profile", "script.bas"]).unwrap().config.profile;
+        assert!(profile.enabled);
+        assert_eq!(profile.stacks_file, None);
+        let profile = parse(&["--profile-stacks", "out.folded"])
+            .unwrap()
+            .config
+            .profile;
+        assert!(profile.enabled);
+        assert_eq!(profile.stacks_file, Some(PathBuf::from("out.folded")));
+        assert!(!parse(&[]).unwrap().config.profile.enabled);
+        assert!(parse(&["--profile-stacks"]).is_err());
+    }
+
+    #[test]
+    fn options_coverage() {
+        let coverage = parse(&["--coverage", "test.bas"]).unwrap().config.coverage;
+        assert!(coverage.enabled);
+        assert_eq!(coverage.lcov_file, None);
+        let coverage = parse(&["--lcov", "lcov.info", "test.bas"])
+            .unwrap()
+            .config
+            .coverage;
+        assert!(coverage.enabled);
+        assert_eq!(coverage.lcov_file, Some(PathBuf::from("lcov.info")));
+        assert!(parse(&["--lcov"]).is_err());
+    }
+
+    #[test]
+    fn options_backend() {
+        assert_eq!(parse(&[]).unwrap().config.backend, Backend::Bytecode);
+        let options = parse(&["--backend", "ast", "test.bas"]).unwrap();
+        assert_eq!(options.config.backend, Backend::Ast);
+        assert!(parse(&["--backend", "jit"]).is_err());
+        assert!(parse(&["--backend"]).is_err());
+        assert!(!parse(&[]).unwrap().config.no_optimize);
+        assert!(parse(&["--no-optimize"]).unwrap().config.no_optimize);
+    }
+
+    #[test]
+    fn options_invalid() {
+        assert!(parse(&["--seed"]).is_err());
+        assert!(parse(&["--allow-dir"]).is_err());
+        assert!(parse(&["--max-file-size", "-1"]).is_err());
+        assert!(parse(&["--seed", "abc"]).is_err());
+        assert!(parse(&["--seed", "1.5"]).is_err());
+        assert!(parse(&["--seed", "-1"]).is_err());
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["a.bas", "b.bas"]).is_err());
+    }
+}
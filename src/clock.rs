@@ -0,0 +1,51 @@
+use std::{
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Source of the current time for the date and time functions
+pub trait Clock {
+    /// Time elapsed since UNIX epoch (UTC)
+    fn now(&self) -> Duration;
+
+    /// Suspends the script for the given time
+    fn sleep(&mut self, duration: Duration);
+}
+
+/// Wall clock of the operating system
+#[derive(Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+/// Clock frozen at the given time, sleeping just moves it forward
+/// without actual waiting
+pub struct FakeClock {
+    now: Duration,
+}
+
+impl FakeClock {
+    pub fn new(now: Duration) -> Self {
+        Self { now }
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        self.now
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        self.now += duration;
+    }
+}
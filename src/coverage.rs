@@ -0,0 +1,285 @@
+use std::{collections::BTreeMap, fmt::Write, path::PathBuf};
+
+use crate::{ast::statement::Statement, interpreter::InterpreterContext};
+
+/// Which coverage of the script is recorded
+#[derive(Debug, Clone, Default)]
+pub struct CoverageConfig {
+    /// Count the runs of the lines and the outcomes of the IF statements
+    pub enabled: bool,
+    /// File the LCOV tracefile is written to
+    pub lcov_file: Option<PathBuf>,
+    /// Path of the script as it's written to the tracefile
+    pub source_file: Option<String>,
+}
+
+/// Counts of the IF outcomes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Branches {
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+/// Coverage of a source line
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineCoverage {
+    /// Executions of the most executed statement on the line
+    pub hits: u64,
+    /// Statement index and the outcomes of each IF on the line
+    pub branches: Vec<(usize, Branches)>,
+}
+
+/// Records which statements and which IF branches have run
+#[derive(Default)]
+pub struct Coverage {
+    /// Indexed by the statement positions
+    hits: Vec<u64>,
+    branches: BTreeMap<usize, Branches>,
+}
+
+impl Coverage {
+    pub fn hit(&mut self, index: usize) {
+        if self.hits.len() <= index {
+            self.hits.resize(index + 1, 0);
+        }
+        self.hits[index] += 1;
+    }
+
+    /// The IF statement has jumped to its target or entered the block
+    /// after THEN if `taken` is true
+    pub fn branch(&mut self, index: usize, taken: bool) {
+        let branches = self.branches.entry(index).or_default();
+        if taken {
+            branches.taken += 1;
+        } else {
+            branches.not_taken += 1;
+        }
+    }
+
+    /// Coverage of the source lines with statements, DATA is skipped as
+    /// it isn't executed
+    pub fn lines(&self, context: &InterpreterContext) -> BTreeMap<usize, LineCoverage> {
+        let mut lines: BTreeMap<usize, LineCoverage> = BTreeMap::new();
+        for index in 0..context.statements_count() {
+            let (Some(statement), Some(line)) =
+                (context.statement(index), context.statement_line(index))
+            else {
+                continue;
+            };
+            if matches!(statement, Statement::Data) {
+                continue;
+            }
+            let coverage = lines.entry(line).or_default();
+            let hits = self.hits.get(index).copied().unwrap_or_default();
+            coverage.hits = coverage.hits.max(hits);
+            if matches!(statement, Statement::If { .. } | Statement::IfThen { .. }) {
+                let branches = self.branches.get(&index).copied().unwrap_or_default();
+                coverage.branches.push((index, branches));
+            }
+        }
+        lines
+    }
+
+    /// Summary followed by the listing of the source with the hits
+    pub fn report(&self, context: &InterpreterContext, source: &str) -> String {
+        let lines = self.lines(context);
+        let lines_hit = lines.values().filter(|line| line.hits > 0).count();
+        let branches = lines
+            .values()
+            .flat_map(|line| &line.branches)
+            .map(|(_, branches)| *branches)
+            .collect::<Vec<_>>();
+        let branches_hit = branches.iter().map(branches_covered).sum::<usize>();
+        let mut report = format!(
+            "Coverage: {lines_hit} of {} lines ({}), {branches_hit} of {} branches ({})\n",
+            lines.len(),
+            percent(lines_hit, lines.len()),
+            branches.len() * 2,
+            percent(branches_hit, branches.len() * 2),
+        );
+        let _ = writeln!(
+            report,
+            "{:>6} {:>8} {:>8}  source",
+            "line", "hits", "branches"
+        );
+        for (number, text) in source.lines().enumerate() {
+            let number = number + 1;
+            let Some(line) = lines.get(&number) else {
+                let _ = writeln!(report, "{number:>6} {:>8} {:>8}  {text}", "", "");
+                continue;
+            };
+            // Missed lines stand out as in gcov
+            let hits = match line.hits {
+                0 => "#####".to_string(),
+                hits => hits.to_string(),
+            };
+            let branches = if line.branches.is_empty() {
+                String::new()
+            } else {
+                let covered = line
+                    .branches
+                    .iter()
+                    .map(|(_, branches)| branches_covered(branches))
+                    .sum::<usize>();
+                format!("{covered}/{}", line.branches.len() * 2)
+            };
+            let _ = writeln!(report, "{number:>6} {hits:>8} {branches:>8}  {text}");
+        }
+        report
+    }
+
+    /// Tracefile in the LCOV format
+    pub fn lcov(&self, context: &InterpreterContext, source_file: &str) -> String {
+        let lines = self.lines(context);
+        let mut lcov = format!("TN:\nSF:{source_file}\n");
+        let mut found = 0;
+        let mut hit = 0;
+        for (number, line) in &lines {
+            for (index, branches) in &line.branches {
+                for (branch, count) in [branches.taken, branches.not_taken].into_iter().enumerate()
+                {
+                    // Dash means the IF itself hasn't run
+                    let count = if line.hits == 0 {
+                        "-".to_string()
+                    } else {
+                        count.to_string()
+                    };
+                    let _ = writeln!(lcov, "BRDA:{number},{index},{branch},{count}");
+                }
+                found += 2;
+                hit += branches_covered(branches);
+            }
+        }
+        let _ = writeln!(lcov, "BRF:{found}\nBRH:{hit}");
+        for (number, line) in &lines {
+            let _ = writeln!(lcov, "DA:{number},{}", line.hits);
+        }
+        let lines_hit = lines.values().filter(|line| line.hits > 0).count();
+        let _ = writeln!(lcov, "LF:{}\nLH:{lines_hit}", lines.len());
+        lcov.push_str("end_of_record\n");
+        lcov
+    }
+}
+
+fn branches_covered(branches: &Branches) -> usize {
+    usize::from(branches.taken > 0) + usize::from(branches.not_taken > 0)
+}
+
+fn percent(part: usize, total: usize) -> String {
+    if total == 0 {
+        return "-".to_string();
+    }
+    format!("{:.1}%", part as f64 / total as f64 * 100.0)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        interpreter::{Backend, Config},
+        io::MemoryIo,
+        parser::Parser,
+        tokenizer::Tokenizer,
+    };
+
+    use super::*;
+
+    const SCRIPT: &str = "\
+i = 0
+loop: i = i + 1
+IF i < 3 THEN loop
+IF i > 5 THEN PRINT \"big\": GOTO done
+GOTO done
+PRINT \"never\"
+done:
+DATA 1";
+
+    /// Runs the script on each backend and checks they record the same
+    /// coverage
+    fn cover(script: &str) -> InterpreterContext {
+        let [ast, bytecode] = [Backend::Ast, Backend::Bytecode].map(|backend| {
+            let config = Config {
+                coverage: CoverageConfig {
+                    enabled: true,
+                    ..Default::default()
+                },
+                backend,
+                ..Default::default()
+            };
+            let mut context = InterpreterContext::new(&config);
+            context.set_io(Box::new(MemoryIo::default()));
+            let tokens = Tokenizer::new().tokenize(script);
+            Parser::new(tokens, &mut context).parse().unwrap();
+            context.run().unwrap();
+            context
+        });
+        let lines = |context: &InterpreterContext| context.coverage().unwrap().lines(context);
+        assert_eq!(lines(&ast), lines(&bytecode));
+        bytecode
+    }
+
+    #[test]
+    fn coverage_lines() {
+        let context = cover(SCRIPT);
+        let lines = context.coverage().unwrap().lines(&context);
+        let hits = lines
+            .iter()
+            .map(|(number, line)| (*number, line.hits))
+            .collect::<Vec<_>>();
+        assert_eq!(hits, [(1, 1), (2, 3), (3, 3), (4, 1), (5, 1), (6, 0)]);
+        let branches = |number: usize| {
+            lines[&number]
+                .branches
+                .iter()
+                .map(|(_, branches)| (branches.taken, branches.not_taken))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(branches(3), [(2, 1)]);
+        assert_eq!(branches(4), [(0, 1)]);
+        assert!(branches(5).is_empty());
+    }
+
+    #[test]
+    fn coverage_report() {
+        let context = cover(SCRIPT);
+        let report = context.coverage().unwrap().report(&context, SCRIPT);
+        let expected = "\
+Coverage: 5 of 6 lines (83.3%), 3 of 4 branches (75.0%)
+  line     hits branches  source
+     1        1           i = 0
+     2        3           loop: i = i + 1
+     3        3      2/2  IF i < 3 THEN loop
+     4        1      1/2  IF i > 5 THEN PRINT \"big\": GOTO done
+     5        1           GOTO done
+     6    #####           PRINT \"never\"
+     7                    done:
+     8                    DATA 1
+";
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn coverage_lcov() {
+        let context = cover(SCRIPT);
+        let lcov = context.coverage().unwrap().lcov(&context, "test.bas");
+        let expected = "\
+TN:
+SF:test.bas
+BRDA:3,2,0,2
+BRDA:3,2,1,1
+BRDA:4,3,0,0
+BRDA:4,3,1,1
+BRF:4
+BRH:3
+DA:1,1
+DA:2,3
+DA:3,3
+DA:4,1
+DA:5,1
+DA:6,0
+LF:6
+LH:5
+end_of_record
+";
+        assert_eq!(lcov, expected);
+    }
+}
@@ -0,0 +1,785 @@
+use std::{
+    cell::RefCell,
+    io::{self, BufRead, Read, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use crate::{
+    ast::value::Value,
+    debugger::{assign, describe, evaluate, variables, Breakpoint, Condition, Debugger, Mode},
+    error::RuntimeError,
+    interpreter::{Config, InterpreterContext},
+    io::{FileMode, FileStream, IoBackend},
+    json::Json,
+    parser::Parser,
+    protocol::{read_message, write_message},
+    tokenizer::Tokenizer,
+};
+
+/// The scripts run in a single thread
+const THREAD_ID: usize = 1;
+/// Reference of the only variables scope, all variables are global
+const GLOBALS_REFERENCE: usize = 1;
+
+/// Connection to the client shared by the debugger and the console of
+/// the script
+struct Connection {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    /// Sequence number of the last sent message
+    seq: usize,
+    /// The client has ended the session
+    disconnected: bool,
+}
+
+type SharedConnection = Rc<RefCell<Connection>>;
+
+impl Connection {
+    /// Returns None when the client closes the input
+    fn read(&mut self) -> io::Result<Option<Json>> {
+        let message = read_message(&mut self.input)?;
+        if message.is_none() {
+            self.disconnected = true;
+        }
+        Ok(message)
+    }
+
+    fn send(&mut self, message_type: &str, fields: Json) -> io::Result<()> {
+        self.seq += 1;
+        let mut message = Json::object([("seq", self.seq.into()), ("type", message_type.into())]);
+        if let Json::Object(entries) = fields {
+            for (key, value) in entries {
+                message.set(&key, value);
+            }
+        }
+        write_message(&mut self.output, &message)
+    }
+
+    fn respond(&mut self, request: &Json, result: Result<Json, String>) -> io::Result<()> {
+        let mut response = Json::object([
+            ("request_seq", request.get("seq").clone()),
+            ("success", result.is_ok().into()),
+            ("command", request.get("command").clone()),
+        ]);
+        match result {
+            Ok(body) => response.set("body", body),
+            Err(message) => response.set("message", message.into()),
+        }
+        self.send("response", response)
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        self.send(
+            "event",
+            Json::object([("event", event.into()), ("body", body)]),
+        )
+    }
+
+    fn output(&mut self, category: &str, text: &str) -> io::Result<()> {
+        let body = Json::object([("category", category.into()), ("output", text.into())]);
+        self.event("output", body)
+    }
+}
+
+/// Console of the script, the output is sent to the client and there is
+/// no input because the standard input carries the protocol messages
+struct DapIo {
+    connection: SharedConnection,
+    files: Box<dyn IoBackend>,
+}
+
+impl IoBackend for DapIo {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.connection.borrow_mut().output("stdout", text)
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn open(&mut self, path: &str, mode: FileMode) -> io::Result<FileStream> {
+        self.files.open(path, mode)
+    }
+
+    fn file_size(&mut self, path: &str) -> io::Result<u64> {
+        self.files.file_size(path)
+    }
+}
+
+/// Debugger controlled by the client through the debug adapter protocol
+struct DapDebugger {
+    connection: SharedConnection,
+    /// Source object of the launched script
+    source: Json,
+    /// Breakpoints with their identifiers
+    breakpoints: Vec<(usize, Breakpoint)>,
+    last_id: usize,
+    mode: Mode,
+    /// The next stop is the entry of the script
+    entry: bool,
+    /// Index of the statement the execution is paused before
+    index: usize,
+}
+
+impl Debugger for DapDebugger {
+    /// Notifies the client when the execution stops and serves its
+    /// requests until it resumes the execution
+    fn pause(
+        &mut self,
+        context: &mut InterpreterContext,
+        index: usize,
+    ) -> Result<(), RuntimeError> {
+        let mut hit = Vec::new();
+        for (id, breakpoint) in &self.breakpoints {
+            match breakpoint.is_hit(context, index) {
+                Ok(false) => {}
+                Ok(true) => hit.push(Json::from(*id)),
+                Err(message) => {
+                    let text = format!("{message}\n");
+                    self.connection.borrow_mut().output("console", &text)?;
+                    hit.push(Json::from(*id));
+                }
+            }
+        }
+        let reason = if self.entry {
+            "entry"
+        } else if !hit.is_empty() {
+            "breakpoint"
+        } else if self.mode.stops_at(context.call_stack().len()) {
+            "step"
+        } else {
+            return Ok(());
+        };
+        self.entry = false;
+        self.index = index;
+        let mut body = Json::object([
+            ("reason", reason.into()),
+            ("threadId", THREAD_ID.into()),
+            ("allThreadsStopped", true.into()),
+        ]);
+        if !hit.is_empty() {
+            body.set("hitBreakpointIds", hit.into());
+        }
+        self.connection.borrow_mut().event("stopped", body)?;
+        loop {
+            let request = self.connection.borrow_mut().read()?;
+            let Some(request) = request else {
+                return Err(RuntimeError::Break);
+            };
+            let command = request.get("command").as_str().unwrap_or_default();
+            let depth = context.call_stack().len();
+            let mode = match command {
+                "continue" => Mode::Continue,
+                "next" => Mode::Next { depth },
+                "stepIn" => Mode::Step,
+                "stepOut" => Mode::Out { depth },
+                "disconnect" | "terminate" => {
+                    let mut connection = self.connection.borrow_mut();
+                    connection.respond(&request, Ok(Json::object([])))?;
+                    connection.disconnected = command == "disconnect";
+                    return Err(RuntimeError::Break);
+                }
+                _ => {
+                    let result = self.request(context, command, request.get("arguments"));
+                    self.connection.borrow_mut().respond(&request, result)?;
+                    continue;
+                }
+            };
+            self.mode = mode;
+            let body = Json::object([("allThreadsContinued", true.into())]);
+            self.connection.borrow_mut().respond(&request, Ok(body))?;
+            return Ok(());
+        }
+    }
+}
+
+impl DapDebugger {
+    fn new(connection: SharedConnection) -> Self {
+        Self {
+            connection,
+            source: Json::Null,
+            breakpoints: Vec::new(),
+            last_id: 0,
+            mode: Mode::Continue,
+            entry: false,
+            index: 0,
+        }
+    }
+
+    /// Serves the requests which don't change the execution state
+    fn request(
+        &mut self,
+        context: &mut InterpreterContext,
+        command: &str,
+        arguments: &Json,
+    ) -> Result<Json, String> {
+        match command {
+            "threads" => {
+                let thread = Json::object([("id", THREAD_ID.into()), ("name", "main".into())]);
+                Ok(Json::object([("threads", vec![thread].into())]))
+            }
+            "setBreakpoints" => Ok(self.set_breakpoints(context, arguments)),
+            "stackTrace" => Ok(self.stack_trace(context)),
+            "scopes" => {
+                let scope = Json::object([
+                    ("name", "Globals".into()),
+                    ("variablesReference", GLOBALS_REFERENCE.into()),
+                    ("expensive", false.into()),
+                ]);
+                Ok(Json::object([("scopes", vec![scope].into())]))
+            }
+            "variables" => {
+                let items = match arguments.get("variablesReference").as_usize() {
+                    Some(GLOBALS_REFERENCE) => variables(context)
+                        .into_iter()
+                        .map(|(name, value)| variable(name, value))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                Ok(Json::object([("variables", items.into())]))
+            }
+            "evaluate" => {
+                let expression = arguments.get("expression").as_str().unwrap_or_default();
+                let value = evaluate(context, expression)?;
+                Ok(Json::object([
+                    ("result", describe(&value).into()),
+                    ("variablesReference", 0.into()),
+                ]))
+            }
+            "setVariable" => {
+                let name = arguments.get("name").as_str().unwrap_or_default();
+                let expression = arguments.get("value").as_str().unwrap_or_default();
+                let value = assign(context, name, expression)?;
+                Ok(Json::object([("value", describe(&value).into())]))
+            }
+            // The execution is already paused when the request is served
+            "pause" => Ok(Json::object([])),
+            _ => Err(format!("Unsupported request '{command}'")),
+        }
+    }
+
+    /// Replaces the breakpoints, the lines without statements are moved to
+    /// the next statement
+    fn set_breakpoints(&mut self, context: &mut InterpreterContext, arguments: &Json) -> Json {
+        self.breakpoints.clear();
+        let mut items = Vec::new();
+        for item in arguments.get("breakpoints").as_array() {
+            self.last_id += 1;
+            let mut result = Json::object([("id", self.last_id.into())]);
+            let line = item.get("line").as_usize().unwrap_or_default();
+            let index = context.line_statement(line);
+            let condition = match item.get("condition").as_str() {
+                Some(text) if !text.trim().is_empty() => Some(Condition::parse(context, text)),
+                _ => None,
+            };
+            match (index, condition) {
+                (None, _) => {
+                    result.set("verified", false.into());
+                    result.set("line", line.into());
+                    result.set("message", "No statements at the line".into());
+                }
+                (_, Some(Err(message))) => {
+                    result.set("verified", false.into());
+                    result.set("line", line.into());
+                    result.set("message", message.into());
+                }
+                (Some(index), condition) => {
+                    let line = context.statement_line(index).unwrap_or(line);
+                    result.set("verified", true.into());
+                    result.set("line", line.into());
+                    let condition = condition.and_then(Result::ok);
+                    self.breakpoints
+                        .push((self.last_id, Breakpoint { index, condition }));
+                }
+            }
+            items.push(result);
+        }
+        Json::object([("breakpoints", items.into())])
+    }
+
+    /// The current statement followed by the GOSUB statements of the
+    /// active calls
+    fn stack_trace(&self, context: &InterpreterContext) -> Json {
+        let mut positions = vec![self.index];
+        // The GOSUB statement precedes its return position
+        positions.extend(
+            context
+                .call_stack()
+                .iter()
+                .rev()
+                .map(|position| position.saturating_sub(1)),
+        );
+        let line = |index: &usize| context.statement_line(*index).unwrap_or_default();
+        let frames = positions
+            .iter()
+            .enumerate()
+            .map(|(id, index)| {
+                // The frame is entered by the GOSUB of the outer frame
+                let name = match positions.get(id + 1) {
+                    Some(caller) => format!("GOSUB at line {}", line(caller)),
+                    None => "main".to_string(),
+                };
+                Json::object([
+                    ("id", id.into()),
+                    ("name", name.into()),
+                    ("line", line(index).into()),
+                    ("column", 1.into()),
+                    ("source", self.source.clone()),
+                ])
+            })
+            .collect::<Vec<_>>();
+        Json::object([
+            ("totalFrames", frames.len().into()),
+            ("stackFrames", frames.into()),
+        ])
+    }
+}
+
+fn variable(name: &str, value: &Value) -> Json {
+    let value_type = match value {
+        Value::Number(_) => "number",
+        Value::Str(_) => "string",
+        Value::Array(_) => "array",
+    };
+    Json::object([
+        ("name", name.into()),
+        ("value", describe(value).into()),
+        ("type", value_type.into()),
+        ("variablesReference", 0.into()),
+    ])
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        ("supportsConfigurationDoneRequest", true.into()),
+        ("supportsConditionalBreakpoints", true.into()),
+        ("supportsSetVariable", true.into()),
+        ("supportsEvaluateForHovers", true.into()),
+        ("supportsTerminateRequest", true.into()),
+    ])
+}
+
+/// Reads the script and parses it into the context
+fn launch(
+    context: &mut InterpreterContext,
+    files: &mut dyn IoBackend,
+    path: &str,
+) -> Result<(), String> {
+    let mut source = String::new();
+    let read = match files.open(path, FileMode::Input) {
+        Ok(FileStream::Reader(mut reader)) => reader.read_to_string(&mut source).map(|_| ()),
+        Ok(FileStream::Writer(_)) => Err(io::ErrorKind::InvalidInput.into()),
+        Err(err) => Err(err),
+    };
+    read.map_err(|err| format!("Failed to read '{path}': {err}"))?;
+    let tokens = Tokenizer::new().tokenize(&source);
+    Parser::new(tokens, context)
+        .parse()
+        .map_err(|err| format!("Parse error: {err}"))
+}
+
+/// Runs the debug adapter session: the client launches a script and
+/// controls its execution. The lines are 1-based, the script files are
+/// accessed through the backend.
+pub fn run_dap_server(
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    files: Box<dyn IoBackend>,
+    config: &Config,
+) -> io::Result<()> {
+    let connection = Rc::new(RefCell::new(Connection {
+        input,
+        output,
+        seq: 0,
+        disconnected: false,
+    }));
+    let mut context = InterpreterContext::new(config);
+    let mut debugger = DapDebugger::new(connection.clone());
+    // The files are taken by the console of the launched script
+    let mut files = Some(files);
+    // Configuration requests before the script is started
+    loop {
+        let request = connection.borrow_mut().read()?;
+        let Some(request) = request else {
+            return Ok(());
+        };
+        let arguments = request.get("arguments");
+        let mut launched = false;
+        let result = match request.get("command").as_str().unwrap_or_default() {
+            "initialize" => Ok(capabilities()),
+            "launch" => {
+                let path = arguments.get("program").as_str().unwrap_or_default();
+                let result = match files.as_mut() {
+                    Some(backend) => launch(&mut context, backend.as_mut(), path),
+                    None => Err("The script is already launched".to_string()),
+                };
+                if result.is_ok() {
+                    let name = Path::new(path)
+                        .file_name()
+                        .map_or(path.to_string(), |name| name.to_string_lossy().to_string());
+                    debugger.source = Json::object([("name", name.into()), ("path", path.into())]);
+                    debugger.entry = arguments.get("stopOnEntry").as_bool() == Some(true);
+                    debugger.mode = if debugger.entry {
+                        Mode::Step
+                    } else {
+                        Mode::Continue
+                    };
+                    let io = DapIo {
+                        connection: connection.clone(),
+                        files: files.take().expect("The files are available until launch"),
+                    };
+                    context.set_io(Box::new(io));
+                    launched = true;
+                }
+                result.map(|_| Json::object([]))
+            }
+            "configurationDone" if files.is_some() => Err("The script isn't launched".to_string()),
+            "configurationDone" => {
+                connection
+                    .borrow_mut()
+                    .respond(&request, Ok(Json::object([])))?;
+                break;
+            }
+            "disconnect" => {
+                return connection
+                    .borrow_mut()
+                    .respond(&request, Ok(Json::object([])));
+            }
+            command => debugger.request(&mut context, command, arguments),
+        };
+        let mut connection = connection.borrow_mut();
+        connection.respond(&request, result)?;
+        if launched {
+            // Ready to accept the breakpoints
+            connection.event("initialized", Json::object([]))?;
+        }
+    }
+    context.set_debugger(Box::new(debugger));
+    let result = context.run();
+    let mut connection = connection.borrow_mut();
+    if connection.disconnected {
+        return Ok(());
+    }
+    match &result {
+        Ok(()) | Err(RuntimeError::Break) => {}
+        Err(err) => connection.output("stderr", &format!("Runtime error: {err}\n"))?,
+    }
+    let exit_code = if result.is_ok() { 0 } else { 1 };
+    connection.event("exited", Json::object([("exitCode", exit_code.into())]))?;
+    connection.event("terminated", Json::object([]))?;
+    // The client may still send requests before it disconnects
+    while let Some(request) = connection.read()? {
+        if request.get("command").as_str() == Some("disconnect") {
+            return connection.respond(&request, Ok(Json::object([])));
+        }
+        connection.respond(&request, Err("The script has finished".to_string()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::io::MemoryIo;
+
+    use super::*;
+
+    /// Output of the server that remains accessible after the run
+    #[derive(Clone, Default)]
+    pub(crate) struct SharedOutput(pub(crate) Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn request(command: &str, arguments: Json) -> Json {
+        Json::object([
+            ("type", "request".into()),
+            ("command", command.into()),
+            ("arguments", arguments),
+        ])
+    }
+
+    /// Plays the requests of the client against the server with the
+    /// script in `test.bas` and returns the messages it sent back
+    fn session(script: &str, requests: Vec<Json>) -> Vec<Json> {
+        let mut input = Vec::new();
+        for (seq, mut request) in requests.into_iter().enumerate() {
+            request.set("seq", (seq + 1).into());
+            write_message(&mut input, &request).unwrap();
+        }
+        let files = MemoryIo::default();
+        files.put_file("test.bas", script);
+        let output = SharedOutput::default();
+        let input = Box::new(io::Cursor::new(input));
+        run_dap_server(
+            input,
+            Box::new(output.clone()),
+            Box::new(files),
+            &Config::default(),
+        )
+        .unwrap();
+        let output = output.0.borrow().clone();
+        let mut output = output.as_slice();
+        let mut messages = Vec::new();
+        while let Some(message) = read_message(&mut output).unwrap() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// Short description of the message, e.g. `response next` or `event
+    /// stopped`, failed responses are marked with `!`
+    fn summary(message: &Json) -> String {
+        match message.get("type").as_str() {
+            Some("event") => format!("event {}", message.get("event").as_str().unwrap()),
+            _ => {
+                let mark = if message.get("success") == &Json::Bool(true) {
+                    ""
+                } else {
+                    "!"
+                };
+                format!(
+                    "response {}{mark}",
+                    message.get("command").as_str().unwrap()
+                )
+            }
+        }
+    }
+
+    fn find<'a>(messages: &'a [Json], kind: &str) -> Vec<&'a Json> {
+        messages
+            .iter()
+            .filter(|message| summary(message).trim_end_matches('!') == kind)
+            .collect()
+    }
+
+    fn launch_request(stop_on_entry: bool) -> Json {
+        let arguments = Json::object([
+            ("program", "test.bas".into()),
+            ("stopOnEntry", stop_on_entry.into()),
+        ]);
+        request("launch", arguments)
+    }
+
+    fn no_arguments(command: &str) -> Json {
+        request(command, Json::object([]))
+    }
+
+    #[test]
+    fn dap_breakpoints_and_variables() {
+        let script = "x = 1\nGOSUB sub\nPRINT x\nGOTO done\nsub:\nx = x + 1\nRETURN\ndone:";
+        let line = |line: usize| Json::object([("line", line.into())]);
+        let mut conditional = line(3);
+        conditional.set("condition", "x >".into());
+        let breakpoints = Json::object([
+            ("source", Json::object([("path", "test.bas".into())])),
+            ("breakpoints", vec![line(5), line(100), conditional].into()),
+        ]);
+        let requests = vec![
+            no_arguments("initialize"),
+            launch_request(false),
+            request("setBreakpoints", breakpoints),
+            no_arguments("configurationDone"),
+            no_arguments("threads"),
+            no_arguments("stackTrace"),
+            request("scopes", Json::object([("frameId", 0usize.into())])),
+            request(
+                "variables",
+                Json::object([("variablesReference", GLOBALS_REFERENCE.into())]),
+            ),
+            request(
+                "evaluate",
+                Json::object([("expression", "x * 10 + LEN(\"ab\")".into())]),
+            ),
+            request(
+                "setVariable",
+                Json::object([("name", "x".into()), ("value", "x + 4".into())]),
+            ),
+            request("evaluate", Json::object([("expression", "(".into())])),
+            no_arguments("stepOut"),
+            no_arguments("stackTrace"),
+            no_arguments("continue"),
+            no_arguments("threads"),
+            no_arguments("disconnect"),
+        ];
+        let messages = session(script, requests);
+        let summaries = messages.iter().map(summary).collect::<Vec<_>>();
+        let expected = [
+            "response initialize",
+            "response launch",
+            "event initialized",
+            "response setBreakpoints",
+            "response configurationDone",
+            "event stopped",
+            "response threads",
+            "response stackTrace",
+            "response scopes",
+            "response variables",
+            "response evaluate",
+            "response setVariable",
+            "response evaluate!",
+            "response stepOut",
+            "event stopped",
+            "response stackTrace",
+            "response continue",
+            "event output",
+            "event output",
+            "event exited",
+            "event terminated",
+            "response threads!",
+            "response disconnect",
+        ];
+        assert_eq!(summaries, expected);
+
+        let breakpoints = find(&messages, "response setBreakpoints")[0]
+            .get("body")
+            .get("breakpoints")
+            .as_array();
+        assert_eq!(breakpoints[0].get("verified"), &Json::Bool(true));
+        assert_eq!(breakpoints[0].get("line").as_usize(), Some(6));
+        assert_eq!(breakpoints[1].get("verified"), &Json::Bool(false));
+        assert_eq!(
+            breakpoints[2].get("message").as_str(),
+            Some("Expected expression, found end of input")
+        );
+
+        let stops = find(&messages, "event stopped");
+        assert_eq!(
+            stops[0].get("body").get("reason").as_str(),
+            Some("breakpoint")
+        );
+        assert_eq!(
+            stops[0].get("body").get("hitBreakpointIds"),
+            &Json::Array(vec![1usize.into()])
+        );
+        assert_eq!(stops[1].get("body").get("reason").as_str(), Some("step"));
+
+        let traces = find(&messages, "response stackTrace");
+        let frames = traces[0].get("body").get("stackFrames").as_array();
+        let frame = |frame: &Json| {
+            let name = frame.get("name").as_str().unwrap().to_string();
+            (name, frame.get("line").as_usize().unwrap())
+        };
+        assert_eq!(
+            frames.iter().map(frame).collect::<Vec<_>>(),
+            [("GOSUB at line 2".to_string(), 6), ("main".to_string(), 2)]
+        );
+        assert_eq!(
+            frames[0].get("source").get("name").as_str(),
+            Some("test.bas")
+        );
+        let frames = traces[1].get("body").get("stackFrames").as_array();
+        assert_eq!(
+            frames.iter().map(frame).collect::<Vec<_>>(),
+            [("main".to_string(), 3)]
+        );
+
+        let variables = find(&messages, "response variables")[0]
+            .get("body")
+            .get("variables")
+            .as_array();
+        assert_eq!(variables.len(), 1);
+        assert_eq!(variables[0].get("name").as_str(), Some("x"));
+        assert_eq!(variables[0].get("value").as_str(), Some("1"));
+        let evaluated = find(&messages, "response evaluate");
+        assert_eq!(evaluated[0].get("body").get("result").as_str(), Some("12"));
+        let assigned = find(&messages, "response setVariable")[0];
+        assert_eq!(assigned.get("body").get("value").as_str(), Some("5"));
+
+        // The assigned value is incremented by the subroutine
+        let output = find(&messages, "event output")
+            .iter()
+            .map(|event| {
+                let body = event.get("body");
+                assert_eq!(body.get("category").as_str(), Some("stdout"));
+                body.get("output").as_str().unwrap().to_string()
+            })
+            .collect::<String>();
+        assert_eq!(output, "6\n");
+        let exited = find(&messages, "event exited")[0];
+        assert_eq!(exited.get("body").get("exitCode").as_usize(), Some(0));
+    }
+
+    #[test]
+    fn dap_stop_on_entry_and_errors() {
+        let script = "PRINT \"a\";\nx = \"s\" - 1\nPRINT \"b\"";
+        let requests = vec![
+            no_arguments("initialize"),
+            no_arguments("configurationDone"),
+            launch_request(true),
+            launch_request(true),
+            no_arguments("configurationDone"),
+            no_arguments("next"),
+            no_arguments("continue"),
+        ];
+        let messages = session(script, requests);
+        let summaries = messages.iter().map(summary).collect::<Vec<_>>();
+        let expected = [
+            "response initialize",
+            "response configurationDone!",
+            "response launch",
+            "event initialized",
+            "response launch!",
+            "response configurationDone",
+            "event stopped",
+            "response next",
+            "event output",
+            "event stopped",
+            "response continue",
+            "event output",
+            "event exited",
+            "event terminated",
+        ];
+        assert_eq!(summaries, expected);
+        let stops = find(&messages, "event stopped");
+        assert_eq!(stops[0].get("body").get("reason").as_str(), Some("entry"));
+        assert_eq!(stops[1].get("body").get("reason").as_str(), Some("step"));
+        let output = find(&messages, "event output")[1].get("body");
+        assert_eq!(output.get("category").as_str(), Some("stderr"));
+        assert_eq!(
+            output.get("output").as_str(),
+            Some("Runtime error: Type mismatch\n")
+        );
+        let exited = find(&messages, "event exited")[0];
+        assert_eq!(exited.get("body").get("exitCode").as_usize(), Some(1));
+    }
+
+    #[test]
+    fn dap_launch_errors_and_disconnect() {
+        let missing = request("launch", Json::object([("program", "missing.bas".into())]));
+        let messages = session("", vec![missing, no_arguments("disconnect")]);
+        let summaries = messages.iter().map(summary).collect::<Vec<_>>();
+        assert_eq!(summaries, ["response launch!", "response disconnect"]);
+
+        let messages = session("PRINT (", vec![launch_request(false)]);
+        assert_eq!(
+            messages[0].get("message").as_str(),
+            Some("Parse error: Line 1: Expected expression, found end of input")
+        );
+
+        let requests = vec![
+            launch_request(true),
+            no_arguments("configurationDone"),
+            no_arguments("disconnect"),
+            no_arguments("threads"),
+        ];
+        let messages = session("PRINT 1", requests);
+        let summaries = messages.iter().map(summary).collect::<Vec<_>>();
+        let expected = [
+            "response launch",
+            "event initialized",
+            "response configurationDone",
+            "event stopped",
+            "response disconnect",
+        ];
+        assert_eq!(summaries, expected);
+    }
+}
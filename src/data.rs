@@ -0,0 +1,68 @@
+use crate::error::RuntimeError;
+
+#[derive(Clone)]
+struct DataItem {
+    /// Index of the DATA statement the value belongs to
+    position: usize,
+    text: String,
+}
+
+/// Values of all DATA statements of the program in the source order
+/// and the pointer of the next value READ takes
+#[derive(Clone, Default)]
+pub struct DataPool {
+    items: Vec<DataItem>,
+    pointer: usize,
+}
+
+impl DataPool {
+    pub fn push(&mut self, position: usize, text: String) {
+        self.items.push(DataItem { position, text });
+    }
+
+    pub fn read(&mut self) -> Result<&str, RuntimeError> {
+        let item = self
+            .items
+            .get(self.pointer)
+            .ok_or(RuntimeError::OutOfData)?;
+        self.pointer += 1;
+        Ok(&item.text)
+    }
+
+    /// Moves the pointer to the first value of DATA statements located
+    /// at or after the given statement position
+    pub fn restore(&mut self, position: usize) {
+        self.pointer = self.items.partition_point(|item| item.position < position);
+    }
+
+    /// Moves the values to the new positions of their DATA statements,
+    /// the new position of each old one is at its index
+    pub fn remap(&mut self, positions: &[usize]) {
+        for item in &mut self.items {
+            item.position = positions[item.position];
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn data_pool_read_restore() {
+        let mut pool = DataPool::default();
+        pool.push(0, "1".to_string());
+        pool.push(0, "a".to_string());
+        pool.push(3, "b".to_string());
+        assert_eq!(pool.read(), Ok("1"));
+        assert_eq!(pool.read(), Ok("a"));
+        assert_eq!(pool.read(), Ok("b"));
+        assert_eq!(pool.read(), Err(RuntimeError::OutOfData));
+        pool.restore(1);
+        assert_eq!(pool.read(), Ok("b"));
+        pool.restore(0);
+        assert_eq!(pool.read(), Ok("1"));
+        pool.restore(4);
+        assert_eq!(pool.read(), Err(RuntimeError::OutOfData));
+    }
+}
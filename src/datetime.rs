@@ -0,0 +1,424 @@
+//! Calendar arithmetic for the date functions. Dates are proleptic
+//! Gregorian in UTC, scripts pass them around as "YYYY-MM-DD HH:MM:SS"
+//! strings because a single precision number can't hold a timestamp.
+
+use std::fmt::Display;
+
+const SECONDS_PER_MINUTE: i64 = 60;
+const SECONDS_PER_HOUR: i64 = 60 * SECONDS_PER_MINUTE;
+const SECONDS_PER_DAY: i64 = 24 * SECONDS_PER_HOUR;
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Unit of DATEADD and DATEDIFF functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interval {
+    Year,
+    Quarter,
+    Month,
+    Week,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl Interval {
+    /// Parses interval names used by Visual Basic: yyyy, q, m, ww, d, h, n, s
+    pub fn parse(text: &str) -> Option<Self> {
+        use Interval::*;
+        let interval = match text.to_lowercase().as_str() {
+            "yyyy" => Year,
+            "q" => Quarter,
+            "m" => Month,
+            "ww" => Week,
+            "d" => Day,
+            "h" => Hour,
+            "n" => Minute,
+            "s" => Second,
+            _ => return None,
+        };
+        Some(interval)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    year: i64,
+    month: i64,
+    day: i64,
+    /// Seconds since midnight
+    time: i64,
+}
+
+impl DateTime {
+    pub fn from_timestamp(seconds: i64) -> Self {
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        Self {
+            year,
+            month,
+            day,
+            time: seconds.rem_euclid(SECONDS_PER_DAY),
+        }
+    }
+
+    /// Seconds since UNIX epoch
+    pub fn timestamp(&self) -> i64 {
+        self.days() * SECONDS_PER_DAY + self.time
+    }
+
+    /// Accepts "YYYY-MM-DD" or "MM-DD-YYYY" dates with '-' or '/' separators
+    /// optionally followed by "HH:MM[:SS]" time separated with space or 'T'
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (date, time) = match text.split_once([' ', 'T']) {
+            Some((date, time)) => (date, Some(time.trim())),
+            None => (text, None),
+        };
+        let parts = date.split(['-', '/']).collect::<Vec<_>>();
+        let [first, second, third] = parts[..] else {
+            return None;
+        };
+        let (year, month, day) = if first.len() == 4 {
+            (first, second, third)
+        } else if third.len() == 4 {
+            (third, first, second)
+        } else {
+            return None;
+        };
+        let year = year.parse::<i64>().ok()?;
+        let month = month.parse::<i64>().ok()?;
+        let day = day.parse::<i64>().ok()?;
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+        let time = match time {
+            Some(time) => parse_time(time)?,
+            None => 0,
+        };
+        Some(Self {
+            year,
+            month,
+            day,
+            time,
+        })
+    }
+
+    /// Returns None if the result falls outside of the years 0 to 9999,
+    /// which are the ones the date strings can hold
+    pub fn add(&self, interval: Interval, count: i64) -> Option<Self> {
+        use Interval::*;
+        let date = match interval {
+            Year => self.add_months(count.checked_mul(12)?),
+            Quarter => self.add_months(count.checked_mul(3)?),
+            Month => self.add_months(count),
+            Week => self.add_seconds(count.checked_mul(7 * SECONDS_PER_DAY)?),
+            Day => self.add_seconds(count.checked_mul(SECONDS_PER_DAY)?),
+            Hour => self.add_seconds(count.checked_mul(SECONDS_PER_HOUR)?),
+            Minute => self.add_seconds(count.checked_mul(SECONDS_PER_MINUTE)?),
+            Second => self.add_seconds(count),
+        }?;
+        (0..=9999).contains(&date.year).then_some(date)
+    }
+
+    /// Number of interval boundaries between this and other date,
+    /// negative if the other date is earlier
+    pub fn diff(&self, other: &DateTime, interval: Interval) -> i64 {
+        use Interval::*;
+        let boundaries =
+            |unit: i64| other.timestamp().div_euclid(unit) - self.timestamp().div_euclid(unit);
+        match interval {
+            Year => other.year - self.year,
+            Quarter => {
+                let quarter = |date: &DateTime| date.year * 4 + (date.month - 1) / 3;
+                quarter(other) - quarter(self)
+            }
+            Month => other.month_index() - self.month_index(),
+            Week => (other.days() - self.days()) / 7,
+            Day => other.days() - self.days(),
+            Hour => boundaries(SECONDS_PER_HOUR),
+            Minute => boundaries(SECONDS_PER_MINUTE),
+            Second => other.timestamp() - self.timestamp(),
+        }
+    }
+
+    /// Replaces yyyy, yy, mmmm, mmm, mm, dd, hh, nn and ss placeholders
+    /// (case insensitive) with date components, other characters are kept
+    pub fn format(&self, pattern: &str) -> String {
+        let chars = pattern.chars().collect::<Vec<_>>();
+        let mut result = String::new();
+        let mut position = 0;
+        while position < chars.len() {
+            let rest = chars[position..]
+                .iter()
+                .take(4)
+                .collect::<String>()
+                .to_lowercase();
+            let (length, text) = if rest.starts_with("yyyy") {
+                (4, format!("{:04}", self.year))
+            } else if rest.starts_with("yy") {
+                (2, format!("{:02}", self.year.rem_euclid(100)))
+            } else if rest.starts_with("mmmm") {
+                (4, self.month_name().to_string())
+            } else if rest.starts_with("mmm") {
+                (3, self.month_name()[..3].to_string())
+            } else if rest.starts_with("mm") {
+                (2, format!("{:02}", self.month))
+            } else if rest.starts_with("dd") {
+                (2, format!("{:02}", self.day))
+            } else if rest.starts_with("hh") {
+                (2, format!("{:02}", self.hour()))
+            } else if rest.starts_with("nn") {
+                (2, format!("{:02}", self.minute()))
+            } else if rest.starts_with("ss") {
+                (2, format!("{:02}", self.second()))
+            } else {
+                (1, chars[position].to_string())
+            };
+            result.push_str(&text);
+            position += length;
+        }
+        result
+    }
+
+    pub fn hour(&self) -> i64 {
+        self.time / SECONDS_PER_HOUR
+    }
+
+    pub fn minute(&self) -> i64 {
+        self.time % SECONDS_PER_HOUR / SECONDS_PER_MINUTE
+    }
+
+    pub fn second(&self) -> i64 {
+        self.time % SECONDS_PER_MINUTE
+    }
+
+    fn month_name(&self) -> &'static str {
+        MONTH_NAMES[self.month as usize - 1]
+    }
+
+    fn days(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day)
+    }
+
+    fn month_index(&self) -> i64 {
+        self.year * 12 + self.month - 1
+    }
+
+    fn add_seconds(&self, seconds: i64) -> Option<Self> {
+        let timestamp = self.timestamp().checked_add(seconds)?;
+        Some(Self::from_timestamp(timestamp))
+    }
+
+    /// Adds months keeping the day unless the target month is shorter
+    fn add_months(&self, months: i64) -> Option<Self> {
+        let index = self.month_index().checked_add(months)?;
+        let year = index.div_euclid(12);
+        let month = index.rem_euclid(12) + 1;
+        Some(Self {
+            year,
+            month,
+            day: self.day.min(days_in_month(year, month)),
+            time: self.time,
+        })
+    }
+}
+
+impl Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year,
+            self.month,
+            self.day,
+            self.hour(),
+            self.minute(),
+            self.second()
+        )
+    }
+}
+
+fn parse_time(text: &str) -> Option<i64> {
+    let parts = text
+        .split(':')
+        .map(|part| part.parse::<i64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let (hour, minute, second) = match parts[..] {
+        [hour, minute] => (hour, minute, 0),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return None,
+    };
+    if !(0..24).contains(&hour) || !(0..60).contains(&minute) || !(0..60).contains(&second) {
+        return None;
+    }
+    Some(hour * SECONDS_PER_HOUR + minute * SECONDS_PER_MINUTE + second)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Inverse of `days_from_civil`
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn date(text: &str) -> DateTime {
+        DateTime::parse(text).unwrap()
+    }
+
+    #[test]
+    fn datetime_timestamp_roundtrip() {
+        for timestamp in [0, 951782400, 1700000000, -86401, 4102444800] {
+            let date = DateTime::from_timestamp(timestamp);
+            assert_eq!(date.timestamp(), timestamp);
+        }
+        let date = DateTime::from_timestamp(951782400);
+        assert_eq!(date.to_string(), "2000-02-29 00:00:00");
+    }
+
+    #[test]
+    fn datetime_parse() {
+        assert_eq!(date("2024-03-05").to_string(), "2024-03-05 00:00:00");
+        assert_eq!(date("03-05-2024").to_string(), "2024-03-05 00:00:00");
+        assert_eq!(date("2024/3/5 7:08").to_string(), "2024-03-05 07:08:00");
+        assert_eq!(
+            date("2024-03-05T23:59:59").to_string(),
+            "2024-03-05 23:59:59"
+        );
+        assert!(DateTime::parse("2023-02-29").is_none());
+        assert!(DateTime::parse("2024-13-01").is_none());
+        assert!(DateTime::parse("2024-01-01 24:00").is_none());
+        assert!(DateTime::parse("yesterday").is_none());
+    }
+
+    #[test]
+    fn datetime_add() {
+        let start = date("2024-01-31 12:00:00");
+        assert_eq!(
+            start.add(Interval::Month, 1).unwrap().to_string(),
+            "2024-02-29 12:00:00"
+        );
+        assert_eq!(
+            start.add(Interval::Year, -1).unwrap().to_string(),
+            "2023-01-31 12:00:00"
+        );
+        assert_eq!(
+            start.add(Interval::Quarter, 1).unwrap().to_string(),
+            "2024-04-30 12:00:00"
+        );
+        assert_eq!(
+            start.add(Interval::Day, 1).unwrap().to_string(),
+            "2024-02-01 12:00:00"
+        );
+        assert_eq!(
+            start.add(Interval::Week, -5).unwrap().to_string(),
+            "2023-12-27 12:00:00"
+        );
+        assert_eq!(
+            start.add(Interval::Hour, 12).unwrap().to_string(),
+            "2024-02-01 00:00:00"
+        );
+        assert_eq!(
+            start.add(Interval::Minute, -1).unwrap().to_string(),
+            "2024-01-31 11:59:00"
+        );
+        assert_eq!(
+            start.add(Interval::Second, 90).unwrap().to_string(),
+            "2024-01-31 12:01:30"
+        );
+    }
+
+    #[test]
+    fn datetime_add_out_of_range() {
+        let start = date("2024-01-31 12:00:00");
+        for interval in [Interval::Year, Interval::Week, Interval::Second] {
+            assert!(start.add(interval, i64::MAX).is_none(), "{interval:?}");
+            assert!(start.add(interval, i64::MIN).is_none(), "{interval:?}");
+        }
+        assert!(start.add(Interval::Month, i64::MAX).is_none());
+        assert!(start.add(Interval::Year, 7976).is_none());
+        assert!(start.add(Interval::Year, -2025).is_none());
+        assert_eq!(
+            start.add(Interval::Year, 7975).unwrap().to_string(),
+            "9999-01-31 12:00:00"
+        );
+    }
+
+    #[test]
+    fn datetime_diff() {
+        let first = date("2023-12-31 23:59:00");
+        let second = date("2024-01-01 00:01:00");
+        assert_eq!(first.diff(&second, Interval::Year), 1);
+        assert_eq!(first.diff(&second, Interval::Quarter), 1);
+        assert_eq!(first.diff(&second, Interval::Month), 1);
+        assert_eq!(first.diff(&second, Interval::Day), 1);
+        assert_eq!(first.diff(&second, Interval::Hour), 1);
+        assert_eq!(first.diff(&second, Interval::Minute), 2);
+        assert_eq!(first.diff(&second, Interval::Second), 120);
+        assert_eq!(second.diff(&first, Interval::Second), -120);
+        let later = date("2024-01-21");
+        assert_eq!(first.diff(&later, Interval::Week), 3);
+    }
+
+    #[test]
+    fn datetime_format() {
+        let date = date("2024-03-05 07:08:09");
+        assert_eq!(date.format("dd.mm.yyyy"), "05.03.2024");
+        assert_eq!(date.format("hh:nn:ss"), "07:08:09");
+        assert_eq!(date.format("MMMM d, YY"), "March d, 24");
+        assert_eq!(date.format("mmm"), "Mar");
+    }
+}
@@ -0,0 +1,398 @@
+use super::{assign, describe, evaluate, variables, Breakpoint, Condition, Debugger, Mode};
+use crate::{
+    error::RuntimeError,
+    interpreter::InterpreterContext,
+    tokenizer::{TokenType, Tokenizer},
+};
+
+const PROMPT: &str = "(debug) ";
+
+const HELP: &str = "Commands:
+  step, s                     execute the next statement
+  next, n                     execute the next statement, GOSUB runs entirely
+  continue, c                 run until a breakpoint
+  break, b <line|label> [IF <condition>]
+                              stop before the line or the label
+  delete, d [number]          remove the breakpoint or all of them
+  breakpoints                 list the breakpoints
+  print, p <expression>       show the value of the expression
+  set <name> = <expression>   change the variable
+  vars                        show the variables
+  stack, bt                   show the GOSUB calls
+  where                       show the current line
+  quit, q                     stop the script
+";
+
+/// Result of a debugger command
+enum Reply {
+    /// Continue the execution
+    Resume,
+    /// Show the message and wait for the next command
+    Show(String),
+}
+
+/// Console debugger that stops the script before the statements and lets
+/// the user inspect and change its state
+pub struct ConsoleDebugger {
+    source: Vec<String>,
+    /// Breakpoints with the lines or labels they were set at
+    breakpoints: Vec<(String, Breakpoint)>,
+    mode: Mode,
+    /// Index of the statement the execution is paused before
+    index: usize,
+}
+
+impl Debugger for ConsoleDebugger {
+    /// Reads the commands from the console if the execution has to stop
+    /// before the statement
+    fn pause(
+        &mut self,
+        context: &mut InterpreterContext,
+        index: usize,
+    ) -> Result<(), RuntimeError> {
+        if !self.should_pause(context, index)? {
+            return Ok(());
+        }
+        self.index = index;
+        let location = self.location(context);
+        self.show(context, &format!("Stopped at {location}"))?;
+        loop {
+            context.write(PROMPT)?;
+            let line = match context.read_line() {
+                Ok(line) => line,
+                Err(RuntimeError::InputPastEnd) => {
+                    // Nobody to control the execution, so the script runs
+                    // to the end
+                    self.mode = Mode::Continue;
+                    self.breakpoints.clear();
+                    return Ok(());
+                }
+                Err(err) => return Err(err),
+            };
+            match self.command(context, &line)? {
+                Reply::Resume => return Ok(()),
+                Reply::Show(message) => self.show(context, &message)?,
+            }
+        }
+    }
+}
+
+impl ConsoleDebugger {
+    /// The script is paused before its first statement
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.lines().map(|line| line.trim().to_string()).collect(),
+            breakpoints: Vec::new(),
+            mode: Mode::Step,
+            index: 0,
+        }
+    }
+
+    fn should_pause(
+        &mut self,
+        context: &mut InterpreterContext,
+        index: usize,
+    ) -> Result<bool, RuntimeError> {
+        if self.mode.stops_at(context.call_stack().len()) {
+            return Ok(true);
+        }
+        for (_, breakpoint) in &self.breakpoints {
+            match breakpoint.is_hit(context, index) {
+                Ok(false) => {}
+                Ok(true) => return Ok(true),
+                Err(message) => {
+                    self.show(context, &message)?;
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn command(
+        &mut self,
+        context: &mut InterpreterContext,
+        line: &str,
+    ) -> Result<Reply, RuntimeError> {
+        let line = line.trim();
+        let (name, argument) = match line.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (line, ""),
+        };
+        let reply = match name.to_lowercase().as_str() {
+            "" => Reply::Show(String::new()),
+            "step" | "s" => {
+                self.mode = Mode::Step;
+                Reply::Resume
+            }
+            "next" | "n" => {
+                self.mode = Mode::Next {
+                    depth: context.call_stack().len(),
+                };
+                Reply::Resume
+            }
+            "continue" | "c" => {
+                self.mode = Mode::Continue;
+                Reply::Resume
+            }
+            "quit" | "q" => return Err(RuntimeError::Break),
+            "break" | "b" => Reply::Show(self.add_breakpoint(context, argument)),
+            "delete" | "d" => Reply::Show(self.delete_breakpoint(argument)),
+            "breakpoints" => Reply::Show(self.breakpoints()),
+            "print" | "p" => Reply::Show(match evaluate(context, argument) {
+                Ok(value) => describe(&value),
+                Err(message) => message,
+            }),
+            "set" => Reply::Show(set_variable(context, argument)),
+            "vars" => Reply::Show(show_variables(context)),
+            "stack" | "bt" => Reply::Show(self.stack(context)),
+            "where" => Reply::Show(self.location(context)),
+            "help" | "h" => Reply::Show(HELP.to_string()),
+            _ => Reply::Show(format!(
+                "Unknown command '{name}', enter HELP to see the commands"
+            )),
+        };
+        Ok(reply)
+    }
+
+    /// Parses `<line|label> [IF <condition>]`
+    fn add_breakpoint(&mut self, context: &mut InterpreterContext, argument: &str) -> String {
+        let tokens = Tokenizer::new().tokenize(argument);
+        let Some(location) = tokens.first() else {
+            return "Expected a line number or a label".to_string();
+        };
+        // Labels take precedence, so the line numbers of a numbered program
+        // refer to the program lines rather than to the source lines
+        let index = context
+            .label(&location.text)
+            .or_else(|| {
+                match location.t_type {
+                    TokenType::Number => location.text.parse().ok(),
+                    _ => None,
+                }
+                .and_then(|line| context.line_statement(line))
+            })
+            // A label at the end of the script isn't followed by a statement
+            .filter(|index| context.statement_line(*index).is_some());
+        let Some(index) = index else {
+            return format!("No statements at '{}'", location.text);
+        };
+        let condition = match tokens.get(1) {
+            None => None,
+            Some(token) if token.text.eq_ignore_ascii_case("if") => {
+                let text = argument.chars().skip(token.column + 2).collect::<String>();
+                match Condition::parse(context, &text) {
+                    Ok(condition) => Some(condition),
+                    Err(message) => return message,
+                }
+            }
+            Some(token) => return format!("Expected IF, found '{}'", token.text),
+        };
+        let breakpoint = Breakpoint { index, condition };
+        self.breakpoints.push((location.text.clone(), breakpoint));
+        format!("Breakpoint {} at {}", self.breakpoints.len(), location.text)
+    }
+
+    fn delete_breakpoint(&mut self, argument: &str) -> String {
+        if argument.is_empty() {
+            self.breakpoints.clear();
+            return "All breakpoints deleted".to_string();
+        }
+        match argument.parse::<usize>() {
+            Ok(number) if (1..=self.breakpoints.len()).contains(&number) => {
+                self.breakpoints.remove(number - 1);
+                format!("Breakpoint {number} deleted")
+            }
+            _ => format!("No breakpoint {argument}"),
+        }
+    }
+
+    fn breakpoints(&self) -> String {
+        if self.breakpoints.is_empty() {
+            return "No breakpoints".to_string();
+        }
+        let lines = self
+            .breakpoints
+            .iter()
+            .enumerate()
+            .map(|(number, (location, breakpoint))| {
+                let mut line = format!("{} at {location}", number + 1);
+                if let Some(condition) = &breakpoint.condition {
+                    line.push_str(&format!(" if {}", condition.text));
+                }
+                line
+            })
+            .collect::<Vec<_>>();
+        lines.join("\n")
+    }
+
+    /// The current statement followed by the GOSUB calls leading to it
+    fn stack(&self, context: &InterpreterContext) -> String {
+        let mut lines = vec![format!("#0 {}", self.describe_line(context, self.index))];
+        for (depth, position) in context.call_stack().iter().rev().enumerate() {
+            // The GOSUB statement precedes its return position
+            let line = self.describe_line(context, position.saturating_sub(1));
+            lines.push(format!("#{} {line}", depth + 1));
+        }
+        lines.join("\n")
+    }
+
+    fn location(&self, context: &InterpreterContext) -> String {
+        self.describe_line(context, self.index)
+    }
+
+    fn describe_line(&self, context: &InterpreterContext, index: usize) -> String {
+        let Some(line) = context.statement_line(index) else {
+            return "the end of the script".to_string();
+        };
+        let text = self.source.get(line - 1).map_or("", |text| text.as_str());
+        format!("line {line}: {text}")
+    }
+
+    /// Outputs the message on its own line
+    fn show(&self, context: &mut InterpreterContext, message: &str) -> Result<(), RuntimeError> {
+        if context.column() > 0 {
+            context.write("\n")?;
+        }
+        if message.is_empty() {
+            return Ok(());
+        }
+        context.write(message)?;
+        context.write("\n")
+    }
+}
+
+/// Parses and executes `<name> = <expression>`
+fn set_variable(context: &mut InterpreterContext, argument: &str) -> String {
+    let Some((name, expression)) = argument.split_once('=') else {
+        return "Expected <name> = <expression>".to_string();
+    };
+    let name = name.trim();
+    match assign(context, name, expression) {
+        Ok(value) => format!("{name} = {}", describe(&value)),
+        Err(message) => message,
+    }
+}
+
+fn show_variables(context: &InterpreterContext) -> String {
+    let variables = variables(context);
+    if variables.is_empty() {
+        return "No variables".to_string();
+    }
+    variables
+        .into_iter()
+        .map(|(name, value)| format!("{name} = {}", describe(value)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{io::MemoryIo, parser::Parser};
+
+    use super::*;
+
+    /// Runs the script in the debugger controlled by the input, returns the
+    /// result and the console output
+    fn debug(source: &str, input: &str) -> (Result<(), RuntimeError>, String) {
+        let io = MemoryIo::new(input);
+        let mut context = InterpreterContext::default();
+        context.set_io(Box::new(io.clone()));
+        let tokens = Tokenizer::new().tokenize(source);
+        Parser::new(tokens, &mut context)
+            .parse()
+            .expect("Failed to parse the script");
+        context.set_debugger(Box::new(ConsoleDebugger::new(source)));
+        let result = context.run();
+        (result, io.output())
+    }
+
+    #[test]
+    fn debugger_step_and_breakpoints() {
+        let source = "x = 1\n\
+            GOSUB sub: PRINT x\n\
+            PRINT \"end\"\n\
+            GOTO done\n\
+            sub:\n\
+            x = x + 1\n\
+            RETURN\n\
+            done:";
+        let input = "next\nstep\nstep\nbreak 3\nb done\nbreakpoints\nc\nc\n";
+        let (result, output) = debug(source, input);
+        assert_eq!(result, Ok(()));
+        let expected = "Stopped at line 1: x = 1\n\
+            (debug) Stopped at line 2: GOSUB sub: PRINT x\n\
+            (debug) Stopped at line 6: x = x + 1\n\
+            (debug) Stopped at line 7: RETURN\n\
+            (debug) Breakpoint 1 at 3\n\
+            (debug) No statements at 'done'\n\
+            (debug) 1 at 3\n\
+            (debug) 2\n\
+            Stopped at line 3: PRINT \"end\"\n\
+            (debug) end\n";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn debugger_next_over_gosub() {
+        let source = "GOSUB sub\nPRINT \"back\"\nGOTO done\nsub: PRINT \"in\": RETURN\ndone:";
+        let (_, output) = debug(source, "n\nn\nq\n");
+        let expected = "Stopped at line 1: GOSUB sub\n\
+            (debug) in\n\
+            Stopped at line 2: PRINT \"back\"\n\
+            (debug) back\n\
+            Stopped at line 3: GOTO done\n\
+            (debug) ";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn debugger_inspect_state() {
+        let source = "a$ = \"text\"\nGOSUB sub\nsub: n = 2\nPRINT n\nRETURN";
+        let input =
+            "b 4\nc\nvars\np n * 10 + LEN(a$)\np (1\nset n = n + 1\nset 1 = 2\nbt\nwhere\nquit\n";
+        let (result, output) = debug(source, input);
+        assert_eq!(result, Err(RuntimeError::Break));
+        let expected = "Stopped at line 1: a$ = \"text\"\n\
+            (debug) Breakpoint 1 at 4\n\
+            (debug) Stopped at line 4: PRINT n\n\
+            (debug) a$ = \"text\"\n\
+            n = 2\n\
+            (debug) 24\n\
+            (debug) Expected CloseParenthesis, found end of input\n\
+            (debug) n = 3\n\
+            (debug) Invalid variable name '1'\n\
+            (debug) #0 line 4: PRINT n\n\
+            #1 line 2: GOSUB sub\n\
+            (debug) line 4: PRINT n\n\
+            (debug) ";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn debugger_conditional_breakpoint() {
+        let source = "i = 0\nloop: i = i + 1\nIF i < 5 THEN loop\nPRINT i";
+        let input = "b loop if i = 3\nb 3 IF x$\nd 2\nc\np i\nd\nc\n";
+        let (result, output) = debug(source, input);
+        assert_eq!(result, Ok(()));
+        let expected = "Stopped at line 1: i = 0\n\
+            (debug) Breakpoint 1 at loop\n\
+            (debug) Breakpoint 2 at 3\n\
+            (debug) Breakpoint 2 deleted\n\
+            (debug) Stopped at line 2: loop: i = i + 1\n\
+            (debug) 3\n\
+            (debug) All breakpoints deleted\n\
+            (debug) 5\n";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn debugger_end_of_input_continues() {
+        let (result, output) = debug("PRINT 1\nPRINT 2", "s\n");
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            output,
+            "Stopped at line 1: PRINT 1\n(debug) 1\nStopped at line 2: PRINT 2\n(debug) 2\n"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+mod console;
+
+pub use console::ConsoleDebugger;
+
+use crate::{
+    ast::{value::Value, Expression},
+    error::RuntimeError,
+    interpreter::InterpreterContext,
+    parser::Parser,
+    tokenizer::{TokenType, Tokenizer},
+};
+
+/// Receives the control before each statement of the script is executed
+pub trait Debugger {
+    fn pause(&mut self, context: &mut InterpreterContext, index: usize)
+        -> Result<(), RuntimeError>;
+}
+
+/// When the execution should stop besides the breakpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Before the next statement
+    Step,
+    /// Before the next statement outside of the subroutines called deeper
+    /// than the depth
+    Next {
+        depth: usize,
+    },
+    /// Before the next statement after the subroutine at the depth returns
+    #[allow(dead_code)] // Only the debug adapter of the binary steps out
+    Out {
+        depth: usize,
+    },
+    Continue,
+}
+
+impl Mode {
+    /// Checks if the statement at the GOSUB depth should be stopped at
+    pub fn stops_at(self, depth: usize) -> bool {
+        match self {
+            Mode::Step => true,
+            Mode::Next { depth: limit } => depth <= limit,
+            Mode::Out { depth: limit } => depth < limit,
+            Mode::Continue => false,
+        }
+    }
+}
+
+pub struct Condition {
+    /// Source of the expression as it was entered
+    pub text: String,
+    expression: Box<dyn Expression>,
+}
+
+impl Condition {
+    pub fn parse(context: &mut InterpreterContext, text: &str) -> Result<Self, String> {
+        let expression = parse_expression(context, text)?;
+        Ok(Self {
+            text: text.trim().to_string(),
+            expression,
+        })
+    }
+}
+
+pub struct Breakpoint {
+    /// Index of the statement to stop before
+    pub index: usize,
+    pub condition: Option<Condition>,
+}
+
+impl Breakpoint {
+    /// Checks if the execution should stop before the statement. The
+    /// condition which fails to evaluate stops it as well, the error is
+    /// returned then.
+    pub fn is_hit(&self, context: &mut InterpreterContext, index: usize) -> Result<bool, String> {
+        if self.index != index {
+            return Ok(false);
+        }
+        let Some(condition) = &self.condition else {
+            return Ok(true);
+        };
+        match condition
+            .expression
+            .evaluate(context)
+            .and_then(|value| value.try_number())
+        {
+            Ok(value) => Ok(value != 0.0),
+            Err(err) => Err(format!("Failed to evaluate '{}': {err}", condition.text)),
+        }
+    }
+}
+
+pub fn parse_expression(
+    context: &mut InterpreterContext,
+    text: &str,
+) -> Result<Box<dyn Expression>, String> {
+    let tokens = Tokenizer::new().tokenize(text);
+    Parser::new(tokens, context)
+        .parse_expression()
+        .map_err(|err| err.message)
+}
+
+pub fn evaluate(context: &mut InterpreterContext, text: &str) -> Result<Value, String> {
+    let expression = parse_expression(context, text)?;
+    expression.evaluate(context).map_err(|err| err.to_string())
+}
+
+/// Evaluates the expression and assigns it to the variable
+pub fn assign(
+    context: &mut InterpreterContext,
+    name: &str,
+    expression: &str,
+) -> Result<Value, String> {
+    let tokens = Tokenizer::new().tokenize(name);
+    if !matches!(tokens.as_slice(), [token] if token.t_type == TokenType::Word) {
+        return Err(format!("Invalid variable name '{name}'"));
+    }
+    let value = evaluate(context, expression)?;
+    context.put_variable(name.to_string(), value.clone());
+    Ok(value)
+}
+
+/// Variables sorted by their names
+pub fn variables(context: &InterpreterContext) -> Vec<(&String, &Value)> {
+    let mut variables = context.variables().collect::<Vec<_>>();
+    variables.sort_by(|a, b| a.0.cmp(b.0));
+    variables
+}
+
+/// Text of the value, the strings are quoted
+pub fn describe(value: &Value) -> String {
+    match value {
+        Value::Number(_) => value.to_text(),
+        Value::Str(text) => format!("\"{text}\""),
+        Value::Array(items) => {
+            let items = items.iter().map(describe).collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::fmt::Display;
+
+use crate::{
+    ast::value::Value,
+    clock::Clock,
+    debugger::ConsoleDebugger,
+    error::{ParseError, RuntimeError},
+    interpreter::{Config, InterpreterContext, ParsedProgram},
+    io::IoBackend,
+    parser::Parser,
+    tokenizer::Tokenizer,
+};
+
+/// Failure of a script compiled or run by the host
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The source can't be parsed
+    Parse(ParseError),
+    /// The script has failed on the source line
+    Runtime {
+        error: RuntimeError,
+        line: Option<usize>,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Parse(err) => write!(f, "Parse error: {err}"),
+            Error::Runtime {
+                error,
+                line: Some(line),
+            } => write!(f, "Runtime error: Line {line}: {error}"),
+            Error::Runtime { error, line: None } => write!(f, "Runtime error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Parsed source, any number of interpreters may run it. Each run binds
+/// a copy of the statements to the variables of the interpreter.
+pub struct Program {
+    source: String,
+    parsed: ParsedProgram,
+}
+
+impl Program {
+    pub fn compile(source: &str) -> Result<Self, Error> {
+        let tokens = Tokenizer::new().tokenize(source);
+        let mut context = InterpreterContext::default();
+        Parser::new(tokens, &mut context)
+            .parse()
+            .map_err(Error::Parse)?;
+        Ok(Self {
+            source: source.to_string(),
+            parsed: context.take_program(),
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+/// Runs the programs for the host. The variables are kept between the
+/// runs, so the host may set the inputs and read the results.
+pub struct Interpreter {
+    context: InterpreterContext,
+    /// Run the programs in the console debugger
+    debug: bool,
+}
+
+impl Interpreter {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            context: InterpreterContext::new(config),
+            debug: config.debug,
+        }
+    }
+
+    /// Console and file system of the scripts, the standard streams and
+    /// the local files by default
+    pub fn set_io(&mut self, io: Box<dyn IoBackend>) {
+        self.context.set_io(io);
+    }
+
+    /// Time source of the date and time functions and RANDOMIZE, the
+    /// system clock by default
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.context.set_clock(clock);
+    }
+
+    /// Runs the program, the files it has opened are closed afterwards
+    pub fn run(&mut self, program: &Program) -> Result<(), Error> {
+        self.context.load_program(&program.parsed);
+        if self.debug {
+            let debugger = ConsoleDebugger::new(&program.source);
+            self.context.set_debugger(Box::new(debugger));
+        }
+        self.context.run().map_err(|error| Error::Runtime {
+            error,
+            line: self.context.last_line(),
+        })
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&Value> {
+        self.context.variable(name)
+    }
+
+    /// Assigns the variable as the script would, e.g. before it runs
+    pub fn set_variable(&mut self, name: &str, value: Value) {
+        self.context.put_variable(name.to_string(), value);
+    }
+
+    /// Assigned variables in the order they are first used
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.context
+            .variables()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn clear_variables(&mut self) {
+        self.context.clear_variables();
+    }
+
+    /// Time spent in the statements and the labeled blocks if the
+    /// profiling is enabled
+    pub fn profile_report(&self) -> Option<String> {
+        let profiler = self.context.profiler()?;
+        Some(profiler.report(&self.context))
+    }
+
+    /// Profile in the collapsed stacks format of the flame graph tools
+    pub fn profile_stacks(&self) -> Option<String> {
+        Some(self.context.profiler()?.collapsed_stacks())
+    }
+
+    /// Lines and IF branches of the program which have run if the
+    /// coverage is enabled
+    pub fn coverage_report(&self, program: &Program) -> Option<String> {
+        let coverage = self.context.coverage()?;
+        Some(coverage.report(&self.context, &program.source))
+    }
+
+    /// Coverage in the LCOV format, the source file is the name it
+    /// refers to the program by
+    pub fn coverage_lcov(&self, source_file: &str) -> Option<String> {
+        let coverage = self.context.coverage()?;
+        Some(coverage.lcov(&self.context, source_file))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::{clock::FakeClock, interpreter::Backend, io::MemoryIo};
+
+    use super::*;
+
+    const BACKENDS: [Backend; 2] = [Backend::Ast, Backend::Bytecode];
+
+    fn interpreter(backend: Backend, io: &MemoryIo) -> Interpreter {
+        let mut interpreter = Interpreter::new(&Config {
+            backend,
+            ..Default::default()
+        });
+        interpreter.set_io(Box::new(io.clone()));
+        interpreter
+    }
+
+    #[test]
+    fn embed_compile_error() {
+        let Err(Error::Parse(err)) = Program::compile("PRINT 1\nPRINT (") else {
+            panic!("Parse error expected");
+        };
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn embed_host_variables() {
+        let program =
+            Program::compile("INPUT name$\nPRINT \"Hi \"; name$\ntotal = total + n").unwrap();
+        for backend in BACKENDS {
+            let io = MemoryIo::new("Ann\nBob\n");
+            let mut interpreter = interpreter(backend, &io);
+            interpreter.set_variable("n", Value::number(2.0));
+            interpreter.run(&program).unwrap();
+            interpreter.set_variable("n", Value::number(3.0));
+            interpreter.run(&program).unwrap();
+            assert_eq!(io.output(), "? Hi Ann\n? Hi Bob\n", "{backend:?}");
+            assert_eq!(
+                interpreter.variable("total"),
+                Some(&Value::number(5.0)),
+                "{backend:?}"
+            );
+            let names = interpreter
+                .variables()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["n", "name$", "total"], "{backend:?}");
+            interpreter.clear_variables();
+            assert_eq!(interpreter.variables().count(), 0, "{backend:?}");
+        }
+    }
+
+    #[test]
+    fn embed_programs_share_variables() {
+        let first = Program::compile("a = 1\nb$ = \"x\"\nc = SPLIT(\"p,q\", \",\")").unwrap();
+        let source = "d = a + 1\nb$ = b$ + c(1)\nREAD e\nPRINT b$; d; e\nDATA 7";
+        let second = Program::compile(source).unwrap();
+        for backend in BACKENDS {
+            let io = MemoryIo::default();
+            let mut interpreter = interpreter(backend, &io);
+            interpreter.set_variable("f", Value::number(5.0));
+            interpreter.run(&first).unwrap();
+            interpreter.run(&second).unwrap();
+            interpreter.run(&second).unwrap();
+            assert_eq!(io.output(), "xq27\nxqq27\n", "{backend:?}");
+            assert_eq!(interpreter.variable("e"), Some(&Value::number(7.0)));
+        }
+    }
+
+    #[test]
+    fn embed_fake_clock() {
+        let program = Program::compile("SLEEP 30\nPRINT TIME$\nPRINT TIMER").unwrap();
+        for backend in BACKENDS {
+            let io = MemoryIo::default();
+            let mut interpreter = interpreter(backend, &io);
+            interpreter.set_clock(Box::new(FakeClock::new(Duration::from_secs(3600))));
+            interpreter.run(&program).unwrap();
+            assert_eq!(io.output(), "01:00:30\n3630\n", "{backend:?}");
+        }
+    }
+
+    #[test]
+    fn embed_runtime_error_line() {
+        let program =
+            Program::compile("x = 1\nGOSUB fail\nPRINT x\nfail: x = \"a\" * 2\nRETURN").unwrap();
+        for backend in BACKENDS {
+            let io = MemoryIo::default();
+            let err = interpreter(backend, &io).run(&program).unwrap_err();
+            assert_eq!(
+                err,
+                Error::Runtime {
+                    error: RuntimeError::TypeMismatch,
+                    line: Some(4)
+                },
+                "{backend:?}"
+            );
+            assert_eq!(err.to_string(), "Runtime error: Line 4: Type mismatch");
+        }
+    }
+}
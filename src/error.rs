@@ -0,0 +1,80 @@
+use std::{fmt::Display, io};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+    /// An argument is outside of the range accepted by a function
+    IllegalFunctionCall(String),
+    /// A value of the wrong type was passed where another one was expected
+    TypeMismatch,
+    /// An array was indexed outside of its bounds
+    SubscriptOutOfRange,
+    /// Input statement reached the end of the input stream
+    InputPastEnd,
+    /// Reading or writing failed on the system level
+    DeviceIo(String),
+    /// File number is out of range or the file isn't open
+    BadFileNumber,
+    /// The file was opened in the mode that doesn't allow the operation
+    BadFileMode,
+    /// The file number is already in use
+    FileAlreadyOpen,
+    /// The file to open for input doesn't exist
+    FileNotFound(String),
+    /// The sandbox policy doesn't allow the file access
+    PermissionDenied(String),
+    /// READ has no more DATA values to take
+    OutOfData,
+    /// The label referenced by the statement doesn't exist
+    UndefinedLabel(String),
+    /// RETURN was executed outside of a subroutine
+    ReturnWithoutGosub,
+    /// The execution was stopped by the user
+    Break,
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use RuntimeError::*;
+        match self {
+            IllegalFunctionCall(name) => write!(f, "Illegal function call in {name}"),
+            TypeMismatch => write!(f, "Type mismatch"),
+            SubscriptOutOfRange => write!(f, "Subscript out of range"),
+            InputPastEnd => write!(f, "Input past end"),
+            DeviceIo(message) => write!(f, "Device I/O error: {message}"),
+            BadFileNumber => write!(f, "Bad file number"),
+            BadFileMode => write!(f, "Bad file mode"),
+            FileAlreadyOpen => write!(f, "File already open"),
+            FileNotFound(path) => write!(f, "File not found: {path}"),
+            PermissionDenied(path) => write!(f, "Permission denied: {path}"),
+            OutOfData => write!(f, "Out of DATA"),
+            UndefinedLabel(label) => write!(f, "Undefined label {label}"),
+            ReturnWithoutGosub => write!(f, "RETURN without GOSUB"),
+            Break => write!(f, "Break"),
+        }
+    }
+}
+
+/// Source code can't be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// 1-based source line of the error
+    pub line: usize,
+    /// 0-based position of the token where the error was found
+    pub column: usize,
+    pub message: String,
+    /// The source ended before the statement did, so it may be completed
+    /// by more input
+    pub incomplete: bool,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl From<io::Error> for RuntimeError {
+    fn from(err: io::Error) -> Self {
+        RuntimeError::DeviceIo(err.to_string())
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    error::RuntimeError,
+    io::{advance_column, split_fields, FileStream},
+};
+
+pub type FileNumber = i64;
+
+const MAX_FILE_NUMBER: FileNumber = 255;
+
+struct OpenFile {
+    path: String,
+    stream: FileStream,
+    /// Number of bytes that still may be written to the file
+    limit: Option<u64>,
+    /// 0-based column of the output cursor
+    column: usize,
+    /// Fields of the last read line not consumed by INPUT # yet
+    pending: VecDeque<String>,
+}
+
+/// Files opened by the script indexed by their numbers
+#[derive(Default)]
+pub struct FileTable {
+    files: HashMap<FileNumber, OpenFile>,
+}
+
+impl FileTable {
+    pub fn insert(
+        &mut self,
+        number: FileNumber,
+        path: &str,
+        stream: FileStream,
+        limit: Option<u64>,
+    ) -> Result<(), RuntimeError> {
+        Self::check_number(number)?;
+        if self.files.contains_key(&number) {
+            return Err(RuntimeError::FileAlreadyOpen);
+        }
+        let file = OpenFile {
+            path: path.to_string(),
+            stream,
+            limit,
+            column: 0,
+            pending: VecDeque::new(),
+        };
+        self.files.insert(number, file);
+        Ok(())
+    }
+
+    pub fn check_number(number: FileNumber) -> Result<(), RuntimeError> {
+        if !(1..=MAX_FILE_NUMBER).contains(&number) {
+            return Err(RuntimeError::BadFileNumber);
+        }
+        Ok(())
+    }
+
+    /// Closing a file that isn't open does nothing
+    pub fn close(&mut self, number: FileNumber) -> Result<(), RuntimeError> {
+        Self::check_number(number)?;
+        if let Some(mut file) = self.files.remove(&number) {
+            if let FileStream::Writer(writer) = &mut file.stream {
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn close_all(&mut self) -> Result<(), RuntimeError> {
+        let numbers = self.files.keys().copied().collect::<Vec<_>>();
+        for number in numbers {
+            self.close(number)?;
+        }
+        Ok(())
+    }
+
+    /// The smallest number not used by open files
+    pub fn free_number(&self) -> Option<FileNumber> {
+        (1..=MAX_FILE_NUMBER).find(|number| !self.files.contains_key(number))
+    }
+
+    pub fn write(&mut self, number: FileNumber, text: &str) -> Result<(), RuntimeError> {
+        let file = self.file(number)?;
+        let FileStream::Writer(writer) = &mut file.stream else {
+            return Err(RuntimeError::BadFileMode);
+        };
+        if let Some(limit) = &mut file.limit {
+            let size = text.len() as u64;
+            if size > *limit {
+                return Err(RuntimeError::PermissionDenied(file.path.clone()));
+            }
+            *limit -= size;
+        }
+        writer.write_all(text.as_bytes())?;
+        file.column = advance_column(file.column, text);
+        Ok(())
+    }
+
+    pub fn column(&mut self, number: FileNumber) -> Result<usize, RuntimeError> {
+        Ok(self.file(number)?.column)
+    }
+
+    /// Reads a line without the line terminator
+    pub fn read_line(&mut self, number: FileNumber) -> Result<String, RuntimeError> {
+        let file = self.file(number)?;
+        file.pending.clear();
+        Self::next_line(file)
+    }
+
+    /// Reads the next comma separated field, lines are treated as separators
+    pub fn read_field(&mut self, number: FileNumber) -> Result<String, RuntimeError> {
+        let file = self.file(number)?;
+        if file.pending.is_empty() {
+            let line = Self::next_line(file)?;
+            file.pending.extend(split_fields(&line));
+        }
+        Ok(file.pending.pop_front().unwrap_or_default())
+    }
+
+    pub fn is_eof(&mut self, number: FileNumber) -> Result<bool, RuntimeError> {
+        let file = self.file(number)?;
+        match &mut file.stream {
+            FileStream::Reader(reader) => {
+                Ok(file.pending.is_empty() && reader.fill_buf()?.is_empty())
+            }
+            FileStream::Writer(_) => Ok(true),
+        }
+    }
+
+    fn file(&mut self, number: FileNumber) -> Result<&mut OpenFile, RuntimeError> {
+        Self::check_number(number)?;
+        self.files
+            .get_mut(&number)
+            .ok_or(RuntimeError::BadFileNumber)
+    }
+
+    fn next_line(file: &mut OpenFile) -> Result<String, RuntimeError> {
+        let FileStream::Reader(reader) = &mut file.stream else {
+            return Err(RuntimeError::BadFileMode);
+        };
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(RuntimeError::InputPastEnd);
+        }
+        let length = line.trim_end_matches(['\r', '\n']).len();
+        line.truncate(length);
+        Ok(line)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn reader(content: &str) -> FileStream {
+        FileStream::Reader(Box::new(Cursor::new(content.as_bytes().to_vec())))
+    }
+
+    #[test]
+    fn file_table_numbers() {
+        let mut table = FileTable::default();
+        assert_eq!(table.free_number(), Some(1));
+        table.insert(1, "a.txt", reader(""), None).unwrap();
+        assert_eq!(table.free_number(), Some(2));
+        assert_eq!(
+            table.insert(1, "a.txt", reader(""), None),
+            Err(RuntimeError::FileAlreadyOpen)
+        );
+        assert_eq!(
+            table.insert(0, "a.txt", reader(""), None),
+            Err(RuntimeError::BadFileNumber)
+        );
+        assert_eq!(
+            table.insert(256, "a.txt", reader(""), None),
+            Err(RuntimeError::BadFileNumber)
+        );
+        table.close(1).unwrap();
+        assert_eq!(table.free_number(), Some(1));
+        assert_eq!(table.read_line(1), Err(RuntimeError::BadFileNumber));
+    }
+
+    #[test]
+    fn file_table_read_fields() {
+        let mut table = FileTable::default();
+        table
+            .insert(1, "a.txt", reader("1, \"a, b\"\r\n3\n"), None)
+            .unwrap();
+        assert_eq!(table.read_field(1).unwrap(), "1");
+        assert!(!table.is_eof(1).unwrap());
+        assert_eq!(table.read_field(1).unwrap(), "a, b");
+        assert_eq!(table.read_field(1).unwrap(), "3");
+        assert!(table.is_eof(1).unwrap());
+        assert_eq!(table.read_field(1), Err(RuntimeError::InputPastEnd));
+    }
+
+    #[test]
+    fn file_table_write_to_reader_fails() {
+        let mut table = FileTable::default();
+        table.insert(1, "a.txt", reader(""), None).unwrap();
+        assert_eq!(table.write(1, "text"), Err(RuntimeError::BadFileMode));
+    }
+
+    #[test]
+    fn file_table_write_limit() {
+        let mut table = FileTable::default();
+        let writer = FileStream::Writer(Box::new(Vec::new()));
+        table.insert(1, "a.txt", writer, Some(5)).unwrap();
+        table.write(1, "abc").unwrap();
+        table.write(1, "de").unwrap();
+        assert_eq!(
+            table.write(1, "f"),
+            Err(RuntimeError::PermissionDenied("a.txt".to_string()))
+        );
+    }
+}
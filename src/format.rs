@@ -0,0 +1,442 @@
+//! Templates of PRINT USING statement and FORMAT$ function.
+//!
+//! Numeric fields consist of `#` digit positions with an optional `.`
+//! decimal point, `,` before the decimal point enables thousands separators,
+//! `+` at the start or end prints the sign there, `-` at the end prints
+//! the minus after negative numbers, `$` or `$$` prefix adds a floating
+//! dollar sign, `**` prefix fills the leading space with asterisks and
+//! `^^^^` at the end switches to the exponent notation. A number that
+//! doesn't fit the field, infinity included, is printed in full prefixed
+//! with `%`.
+//!
+//! String fields are `!` for the first character, `&` for the whole string
+//! and `\  \` for a fixed width of two plus the number of spaces between
+//! the backslashes. `_` outputs the next character literally.
+
+use crate::{ast::value::Value, error::RuntimeError};
+
+#[derive(Debug, PartialEq)]
+enum Sign {
+    /// Minus is printed in front of negative numbers only
+    Default,
+    /// + or - in front of the number
+    Leading,
+    /// + or - after the number
+    Trailing,
+    /// - after negative numbers, space after positive ones
+    TrailingMinus,
+}
+
+#[derive(Debug, PartialEq)]
+struct NumericField {
+    /// Total number of characters
+    width: usize,
+    /// Digit positions before the decimal point
+    integer_digits: usize,
+    decimals: Option<usize>,
+    thousands: bool,
+    sign: Sign,
+    dollar: bool,
+    asterisk: bool,
+    /// Number of digits of the exponent
+    exponent: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Numeric(NumericField),
+    /// Fixed width string, None for & field
+    Text(Option<usize>),
+}
+
+/// Formats the values one after another with the pattern repeating it
+/// from the beginning if there are more values than fields
+pub fn format_using(pattern: &str, values: &[Value]) -> Result<String, RuntimeError> {
+    let segments = parse(pattern);
+    if segments
+        .iter()
+        .all(|segment| matches!(segment, Segment::Literal(_)))
+    {
+        return Err(RuntimeError::IllegalFunctionCall("USING".to_string()));
+    }
+    let mut result = String::new();
+    let mut values = values.iter().peekable();
+    'pattern: loop {
+        for segment in &segments {
+            match segment {
+                Segment::Literal(text) => result.push_str(text),
+                // Stop at the next field once all values are printed
+                _ if values.peek().is_none() => break 'pattern,
+                Segment::Numeric(field) => {
+                    let Some(Value::Number(val)) = values.next() else {
+                        return Err(RuntimeError::TypeMismatch);
+                    };
+                    result.push_str(&format_number(*val as f64, field));
+                }
+                Segment::Text(width) => {
+                    let Some(Value::Str(text)) = values.next() else {
+                        return Err(RuntimeError::TypeMismatch);
+                    };
+                    result.push_str(&format_text(text, *width));
+                }
+            }
+        }
+        if values.peek().is_none() {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+fn parse(pattern: &str) -> Vec<Segment> {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut position = 0;
+    while position < chars.len() {
+        let ch = chars[position];
+        let segment = match ch {
+            '_' => {
+                if let Some(next) = chars.get(position + 1) {
+                    literal.push(*next);
+                }
+                position += 2;
+                continue;
+            }
+            '!' => {
+                position += 1;
+                Segment::Text(Some(1))
+            }
+            '&' => {
+                position += 1;
+                Segment::Text(None)
+            }
+            '\\' => {
+                let Some(length) = chars[position + 1..]
+                    .iter()
+                    .position(|ch| *ch != ' ')
+                    .filter(|length| chars[position + 1 + length] == '\\')
+                else {
+                    literal.push(ch);
+                    position += 1;
+                    continue;
+                };
+                position += length + 2;
+                Segment::Text(Some(length + 2))
+            }
+            _ => {
+                let Some((field, length)) = parse_numeric(&chars[position..]) else {
+                    literal.push(ch);
+                    position += 1;
+                    continue;
+                };
+                position += length;
+                Segment::Numeric(field)
+            }
+        };
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(std::mem::take(&mut literal)));
+        }
+        segments.push(segment);
+    }
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    segments
+}
+
+/// Parses the numeric field at the beginning of the slice and returns
+/// it with the number of characters consumed
+fn parse_numeric(chars: &[char]) -> Option<(NumericField, usize)> {
+    let mut field = NumericField {
+        width: 0,
+        integer_digits: 0,
+        decimals: None,
+        thousands: false,
+        sign: Sign::Default,
+        dollar: false,
+        asterisk: false,
+        exponent: None,
+    };
+    let mut position = 0;
+    if chars.first() == Some(&'+') {
+        field.sign = Sign::Leading;
+        position += 1;
+    }
+    let rest = &chars[position..];
+    let prefix = rest.iter().take(3).collect::<String>();
+    if prefix.starts_with("**$") {
+        field.asterisk = true;
+        field.dollar = true;
+        field.integer_digits += 2;
+        position += 3;
+    } else if prefix.starts_with("**") {
+        field.asterisk = true;
+        field.integer_digits += 2;
+        position += 2;
+    } else if prefix.starts_with("$$") {
+        field.dollar = true;
+        field.integer_digits += 1;
+        position += 2;
+    } else if prefix.starts_with("$#") {
+        field.dollar = true;
+        position += 1;
+    }
+    let body_start = position;
+    while let Some(ch) = chars.get(position) {
+        match ch {
+            '#' if field.decimals.is_none() => field.integer_digits += 1,
+            ',' if field.decimals.is_none() && position > body_start => field.thousands = true,
+            '.' if field.decimals.is_none() && chars.get(position + 1) == Some(&'#') => {
+                field.decimals = Some(0)
+            }
+            '#' => field.decimals = field.decimals.map(|count| count + 1),
+            _ => break,
+        }
+        position += 1;
+    }
+    let has_digits = field.integer_digits > 0 || field.decimals.is_some_and(|count| count > 0);
+    if !has_digits {
+        return None;
+    }
+    let carets = chars[position..]
+        .iter()
+        .take_while(|ch| **ch == '^')
+        .count();
+    if carets >= 4 {
+        let carets = carets.min(5);
+        field.exponent = Some(carets - 2);
+        position += carets;
+    }
+    if field.sign == Sign::Default {
+        match chars.get(position) {
+            Some('+') => {
+                field.sign = Sign::Trailing;
+                position += 1;
+            }
+            Some('-') => {
+                field.sign = Sign::TrailingMinus;
+                position += 1;
+            }
+            _ => {}
+        }
+    }
+    field.width = position;
+    Some((field, position))
+}
+
+fn format_number(val: f64, field: &NumericField) -> String {
+    let negative = val < 0.0;
+    if !val.is_finite() {
+        return format!("%{}", decorate(&val.abs().to_string(), negative, field));
+    }
+    let digits = match field.exponent {
+        Some(exponent_digits) => format_exponent(val.abs(), field, exponent_digits),
+        None => format_fixed(val.abs(), field),
+    };
+    let mut text = decorate(&digits, negative, field);
+    if text.chars().count() > field.width {
+        // Leading zero is the first thing to drop when there is no room
+        if let Some(fraction) = digits.strip_prefix("0.") {
+            text = decorate(&format!(".{fraction}"), negative, field);
+        }
+    }
+    let length = text.chars().count();
+    if length > field.width {
+        return format!("%{text}");
+    }
+    let fill = if field.asterisk { "*" } else { " " };
+    fill.repeat(field.width - length) + &text
+}
+
+fn format_fixed(val: f64, field: &NumericField) -> String {
+    let text = format!("{:.*}", field.decimals.unwrap_or(0), val);
+    let (integer, fraction) = text.split_once('.').unwrap_or((&text, ""));
+    let mut integer = if field.thousands {
+        group_thousands(integer)
+    } else {
+        integer.to_string()
+    };
+    if integer == "0" && field.integer_digits == 0 {
+        integer.clear();
+    }
+    if field.decimals.is_some() {
+        format!("{integer}.{fraction}")
+    } else {
+        integer
+    }
+}
+
+/// Adds the dollar and the sign to the digits
+fn decorate(digits: &str, negative: bool, field: &NumericField) -> String {
+    let mut text = digits.to_string();
+    if field.dollar {
+        text.insert(0, '$');
+    }
+    let sign = if negative { '-' } else { '+' };
+    match field.sign {
+        Sign::Default if negative => text.insert(0, sign),
+        Sign::Leading => text.insert(0, sign),
+        Sign::Trailing => text.push(sign),
+        Sign::TrailingMinus => text.push(if negative { '-' } else { ' ' }),
+        _ => {}
+    }
+    text
+}
+
+fn format_exponent(val: f64, field: &NumericField, exponent_digits: usize) -> String {
+    let decimals = field.decimals.unwrap_or(0);
+    // One of the digit positions is reserved for the sign unless it's
+    // printed elsewhere
+    let mut integer_digits = field.integer_digits;
+    if field.sign == Sign::Default && integer_digits > 1 {
+        integer_digits -= 1;
+    }
+    let integer_digits = integer_digits as i32;
+    let mut exponent = if val == 0.0 {
+        0
+    } else {
+        val.log10().floor() as i32 - (integer_digits - 1).max(0)
+    };
+    let mut mantissa = format!("{:.*}", decimals, val / 10f64.powi(exponent));
+    // Rounding may add one more digit, e.g. 9.99 -> 10.0
+    let integer_length = mantissa.split('.').next().unwrap_or_default().len() as i32;
+    if val != 0.0 && integer_length > integer_digits.max(1) {
+        exponent += 1;
+        mantissa = format!("{:.*}", decimals, val / 10f64.powi(exponent));
+    }
+    if integer_digits == 0 && mantissa.starts_with('0') {
+        mantissa.remove(0);
+    }
+    let exponent_sign = if exponent < 0 { '-' } else { '+' };
+    format!(
+        "{mantissa}E{exponent_sign}{:0width$}",
+        exponent.abs(),
+        width = exponent_digits
+    )
+}
+
+fn group_thousands(integer: &str) -> String {
+    let length = integer.len();
+    integer
+        .chars()
+        .enumerate()
+        .fold(String::new(), |mut acc, (index, ch)| {
+            if index > 0 && (length - index).is_multiple_of(3) {
+                acc.push(',');
+            }
+            acc.push(ch);
+            acc
+        })
+}
+
+fn format_text(text: &str, width: Option<usize>) -> String {
+    let Some(width) = width else {
+        return text.to_string();
+    };
+    let mut result = text.chars().take(width).collect::<String>();
+    let length = result.chars().count();
+    result.push_str(&" ".repeat(width - length));
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn number(pattern: &str, val: f32) -> String {
+        format_using(pattern, &[Value::number(val)]).unwrap()
+    }
+
+    fn text(pattern: &str, val: &str) -> String {
+        format_using(pattern, &[Value::string(val.to_string())]).unwrap()
+    }
+
+    #[test]
+    fn format_digits() {
+        assert_eq!(number("###", 5.0), "  5");
+        assert_eq!(number("###", 12.6), " 13");
+        assert_eq!(number("###.##", 2.34567), "  2.35");
+        assert_eq!(number("###.##", 0.3), "  0.30");
+        assert_eq!(number("#.##", -0.5), "-.50");
+        assert_eq!(number("##.##", -0.5), "-0.50");
+        assert_eq!(number(".##", 0.25), ".25");
+        assert_eq!(number("##", 123.0), "%123");
+    }
+
+    #[test]
+    fn format_thousands() {
+        assert_eq!(number("##,######", 1234567.0), "1,234,567");
+        assert_eq!(number("##,###.##", 1234.5), " 1,234.50");
+        assert_eq!(number("##,###", 12.0), "    12");
+    }
+
+    #[test]
+    fn format_signs() {
+        assert_eq!(number("+###", 5.0), "  +5");
+        assert_eq!(number("+###", -5.0), "  -5");
+        assert_eq!(number("###+", 5.0), "  5+");
+        assert_eq!(number("###-", -5.0), "  5-");
+        assert_eq!(number("###-", 5.0), "  5 ");
+    }
+
+    #[test]
+    fn format_dollar_asterisk() {
+        assert_eq!(number("$$###.##", 45.5), "  $45.50");
+        assert_eq!(number("$###.##", 45.5), " $45.50");
+        assert_eq!(number("**###", 45.0), "***45");
+        assert_eq!(number("**$##.##", 4.5), "***$4.50");
+    }
+
+    #[test]
+    fn format_exponent() {
+        assert_eq!(number("##.##^^^^", 234.56), " 2.35E+02");
+        assert_eq!(number("+#.##^^^^", 0.00123), "+1.23E-03");
+        assert_eq!(number("#.#^^^^^", 9.99), "1.0E+001");
+        assert_eq!(number("##.##^^^^", 0.0), " 0.00E+00");
+    }
+
+    #[test]
+    fn format_not_finite() {
+        assert_eq!(number("#.##^^^^", f32::INFINITY), "%inf");
+        assert_eq!(number("#.##^^^^", f32::NEG_INFINITY), "%-inf");
+        assert_eq!(number("#.##^^^^", f32::NAN), "%NaN");
+        assert_eq!(number("$###.##", f32::INFINITY), "%$inf");
+    }
+
+    #[test]
+    fn format_strings() {
+        assert_eq!(text("!", "hello"), "h");
+        assert_eq!(text("&!", "hi"), "hi");
+        assert_eq!(text("\\  \\|", "hello"), "hell|");
+        assert_eq!(text("\\  \\|", "ж"), "ж   |");
+        assert_eq!(text("[&]", "мир"), "[мир]");
+    }
+
+    #[test]
+    fn format_literals() {
+        assert_eq!(number("Total: ### items", 7.0), "Total:   7 items");
+        assert_eq!(number("_#### _!", 7.0), "#  7 !");
+    }
+
+    #[test]
+    fn format_repeats_pattern() {
+        let values = [1.0, 22.0, 333.0].map(Value::number);
+        let result = format_using("[###]", &values).unwrap();
+        assert_eq!(result, "[  1][ 22][333]");
+        let values = [Value::string("a".to_string()), Value::number(2.0)];
+        let result = format_using("& = ##;", &values).unwrap();
+        assert_eq!(result, "a =  2;");
+    }
+
+    #[test]
+    fn format_errors() {
+        let result = format_using("###", &[Value::string("a".to_string())]);
+        assert_eq!(result, Err(RuntimeError::TypeMismatch));
+        let result = format_using("&", &[Value::number(1.0)]);
+        assert_eq!(result, Err(RuntimeError::TypeMismatch));
+        let result = format_using("no fields", &[Value::number(1.0)]);
+        assert!(result.is_err());
+    }
+}
@@ -1,107 +1,1700 @@
-use std::{collections::HashMap, io};
+use std::{collections::HashMap, io, mem, time::Duration};
 
 use crate::{
     ast::{
-        statement::Statement,
+        statement::{PrintItem, Statement},
         value::{Double, Value},
+        Expression,
     },
-    parser::Parser,
-    tokenizer::Tokenizer,
+    builtins::string::mid_replace,
+    clock::{Clock, SystemClock},
+    coverage::{Coverage, CoverageConfig},
+    data::DataPool,
+    debugger::Debugger,
+    error::RuntimeError,
+    files::{FileNumber, FileTable},
+    format::format_using,
+    io::{advance_column, split_fields, FileMode, IoBackend, StdIo},
+    optimizer,
+    profile::{ProfileConfig, Profiler},
+    random::Random,
+    sandbox::Sandbox,
+    trace::{TraceConfig, Tracer},
+    variables::{Slot, Variable, Variables},
+    vm::{compiler::Compiler, Vm},
 };
 
-pub fn interpret(source: &str) {
-    // tokenize
-    let mut tokenizer = Tokenizer::new();
-    let tokens = tokenizer.tokenize(source);
+/// Width of the print zone the comma separator in PRINT moves to
+const PRINT_ZONE_WIDTH: usize = 14;
 
-    let mut context = InterpreterContext::default();
-    // parse
-    let mut parser = Parser::new(tokens, &mut context);
-    parser.parse();
+/// Largest column of TAB and count of SPC, as in GW-BASIC
+const MAX_PRINT_SPACES: Double = 255.0;
 
-    // interpret
-    context.run()
+/// How the parsed statements are executed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Walks the statements and evaluates the expression trees
+    Ast,
+    /// Compiles the statements to bytecode and runs it on the stack machine
+    #[default]
+    Bytecode,
 }
 
+/// Options of the interpreter
 #[derive(Default)]
+pub struct Config {
+    /// Fixed seed of the random numbers generator
+    pub seed: Option<u64>,
+    /// Restrictions on the file access of the script
+    pub sandbox: Sandbox,
+    /// Run the script in the interactive debugger
+    pub debug: bool,
+    pub trace: TraceConfig,
+    pub profile: ProfileConfig,
+    pub coverage: CoverageConfig,
+    pub backend: Backend,
+    /// Run the statements as they are parsed, without the optimization
+    pub no_optimize: bool,
+}
+
+/// Statements parsed with their labels, DATA values and the names of the
+/// variables in the slots they refer to, so they may be loaded into any
+/// context
+#[derive(Clone)]
+pub struct ParsedProgram {
+    statements: Vec<Statement>,
+    statement_lines: Vec<usize>,
+    labels: HashMap<String, usize>,
+    data: DataPool,
+    variables: Vec<String>,
+}
+
+/// Hooks state of the running statement
+pub struct Step {
+    index: usize,
+    /// The statement has passed the trace filter
+    traced: bool,
+    /// Clock time the statement has started at if it's profiled
+    started: Option<Duration>,
+}
+
+impl Step {
+    /// Position of the statement
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
 pub struct InterpreterContext {
     labels: HashMap<String, usize>,
-    variables: HashMap<String, Value>,
+    variables: Variables,
     statements: Vec<Statement>,
+    /// Source line of each statement
+    statement_lines: Vec<usize>,
     statement_index: usize,
+    random: Random,
+    /// When set, RANDOMIZE always uses this seed instead of its argument
+    fixed_seed: Option<u64>,
+    clock: Box<dyn Clock>,
+    io: Box<dyn IoBackend>,
+    /// 0-based column of the output cursor
+    column: usize,
+    files: FileTable,
+    sandbox: Sandbox,
+    data: DataPool,
+    /// Return positions of the active GOSUB calls
+    call_stack: Vec<usize>,
+    debugger: Option<Box<dyn Debugger>>,
+    tracer: Tracer,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+    backend: Backend,
+    /// Simplify the statements before they run
+    optimize: bool,
+}
+
+impl Default for InterpreterContext {
+    fn default() -> Self {
+        Self {
+            labels: Default::default(),
+            variables: Default::default(),
+            statements: Default::default(),
+            statement_lines: Default::default(),
+            statement_index: 0,
+            random: Default::default(),
+            fixed_seed: None,
+            clock: Box::new(SystemClock),
+            io: Box::new(StdIo),
+            column: 0,
+            files: Default::default(),
+            sandbox: Default::default(),
+            data: Default::default(),
+            call_stack: Vec::new(),
+            debugger: None,
+            tracer: Tracer::default(),
+            profiler: None,
+            coverage: None,
+            backend: Backend::default(),
+            optimize: true,
+        }
+    }
 }
 
 impl InterpreterContext {
+    pub fn new(config: &Config) -> Self {
+        let mut context = Self::default();
+        if let Some(seed) = config.seed {
+            context.fix_seed(seed);
+        }
+        context.set_sandbox(config.sandbox.clone());
+        context.tracer = Tracer::new(&config.trace);
+        if config.profile.enabled {
+            context.profiler = Some(Profiler::default());
+        }
+        if config.coverage.enabled {
+            context.coverage = Some(Coverage::default());
+        }
+        context.backend = config.backend;
+        context.optimize = !config.no_optimize;
+        context
+    }
+
+    /// Value of the variable by its name, the statements use the slots
     pub fn variable(&self, name: &str) -> Option<&Value> {
         self.variables.get(name)
     }
 
+    pub fn variable_at(&self, slot: Slot) -> Option<&Value> {
+        self.variables.at(slot)
+    }
+
+    /// Value of the variable in the slot, the variables which aren't
+    /// assigned yet are zero
+    pub fn value_at(&self, slot: Slot) -> Value {
+        self.variable_at(slot)
+            .cloned()
+            .unwrap_or(Value::Number(0.0))
+    }
+
+    /// Variable with the slot the statements access it by
+    pub fn resolve_variable(&mut self, name: String) -> Variable {
+        let slot = self.variables.resolve(&name);
+        Variable { name, slot }
+    }
+
     pub fn label(&self, name: &str) -> Option<usize> {
         self.labels.get(name).copied()
     }
 
+    pub fn variables(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.variables.iter()
+    }
+
     pub fn put_variable(&mut self, name: String, value: Value) {
-        self.variables.insert(name, value);
+        self.variables.set(&name, value);
+    }
+
+    pub fn put_at(&mut self, slot: Slot, value: Value) {
+        self.variables.set_at(slot, value);
     }
 
     pub fn put_label(&mut self, label: String, position: usize) {
         self.labels.insert(label, position);
     }
 
-    pub fn put_statement(&mut self, statement: Statement) {
-        self.statements.push(statement)
+    pub fn put_statement(&mut self, statement: Statement, line: usize) {
+        self.statements.push(statement);
+        self.statement_lines.push(line);
+    }
+
+    /// Source line of the statement, available while the program runs
+    pub fn statement_line(&self, index: usize) -> Option<usize> {
+        self.statement_lines.get(index).copied()
+    }
+
+    /// Source line of the statement which has run last, e.g. the one
+    /// which has failed
+    pub fn last_line(&self) -> Option<usize> {
+        let index = self.statement_index.checked_sub(1)?;
+        self.statement_line(index)
+    }
+
+    /// Index of the first statement on the source line or on the nearest
+    /// line after it if the line has no statements
+    pub fn line_statement(&self, line: usize) -> Option<usize> {
+        self.statement_lines.iter().position(|value| *value >= line)
+    }
+
+    /// Closest label before the statement
+    pub fn section(&self, index: usize) -> Option<&str> {
+        section(&self.labels, index)
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// Return positions of the active GOSUB calls, the innermost is last
+    pub fn call_stack(&self) -> &[usize] {
+        &self.call_stack
+    }
+
+    /// Pauses the execution in the debugger before the statements
+    pub fn set_debugger(&mut self, debugger: Box<dyn Debugger>) {
+        self.debugger = Some(debugger);
+    }
+
+    pub fn statement(&self, index: usize) -> Option<&Statement> {
+        self.statements.get(index)
+    }
+
+    /// Gives access to a parsed statement, e.g. to set a jump position
+    /// that wasn't known when the statement was put
+    pub fn statement_mut(&mut self, index: usize) -> Option<&mut Statement> {
+        self.statements.get_mut(index)
     }
 
     pub fn statements_count(&self) -> usize {
         self.statements.len()
     }
 
-    fn run(&mut self) {
+    pub fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    /// Makes the random numbers sequence reproducible regardless of
+    /// RANDOMIZE statements in the script
+    pub fn fix_seed(&mut self, seed: u64) {
+        self.fixed_seed = Some(seed);
+        self.random.reseed(seed);
+    }
+
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Replaces the time source, e.g. with a fake clock in tests
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Replaces the console, e.g. to capture the output in tests
+    pub fn set_io(&mut self, io: Box<dyn IoBackend>) {
+        self.io = io;
+    }
+
+    /// Captures the trace instead of writing it to the configured file
+    #[cfg(test)]
+    pub fn set_trace_output(&mut self, output: Box<dyn std::io::Write>) {
+        self.tracer.set_output(output);
+    }
+
+    /// Every file operation of the script is checked against the policy
+    pub fn set_sandbox(&mut self, sandbox: Sandbox) {
+        self.sandbox = sandbox;
+    }
+
+    pub fn data(&mut self) -> &mut DataPool {
+        &mut self.data
+    }
+
+    pub fn files(&mut self) -> &mut FileTable {
+        &mut self.files
+    }
+
+    /// Opens the file through the I/O backend and assigns the number to it
+    pub fn open_file(
+        &mut self,
+        path: &str,
+        mode: FileMode,
+        number: FileNumber,
+    ) -> Result<(), RuntimeError> {
+        FileTable::check_number(number)?;
+        let resolved = self.sandbox.check(path, mode)?;
+        let map_err = |err: io::Error| match err.kind() {
+            io::ErrorKind::NotFound => RuntimeError::FileNotFound(path.to_string()),
+            io::ErrorKind::PermissionDenied => RuntimeError::PermissionDenied(path.to_string()),
+            _ => RuntimeError::from(err),
+        };
+        let limit = match (mode, self.sandbox.max_file_size) {
+            (_, None) => None,
+            (FileMode::Input, Some(_)) => {
+                let size = self.io.file_size(&resolved).map_err(map_err)?;
+                self.sandbox.check_size(path, size)?;
+                None
+            }
+            (FileMode::Output, Some(_)) => self.sandbox.write_limit(0),
+            (FileMode::Append, Some(_)) => {
+                let size = match self.io.file_size(&resolved) {
+                    Ok(size) => size,
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+                    Err(err) => return Err(map_err(err)),
+                };
+                self.sandbox.write_limit(size)
+            }
+        };
+        let stream = self.io.open(&resolved, mode).map_err(map_err)?;
+        self.files.insert(number, path, stream, limit)
+    }
+
+    /// Reads the whole file, the sandbox policy applies as for OPEN
+    pub fn load_file(&mut self, path: &str) -> Result<String, RuntimeError> {
+        let number = self
+            .files
+            .free_number()
+            .ok_or(RuntimeError::BadFileNumber)?;
+        self.open_file(path, FileMode::Input, number)?;
+        let mut content = String::new();
+        let result = loop {
+            match self.files.is_eof(number) {
+                Ok(false) => {}
+                Ok(true) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+            match self.files.read_line(number) {
+                Ok(line) => {
+                    content.push_str(&line);
+                    content.push('\n');
+                }
+                Err(err) => break Err(err),
+            }
+        };
+        let closed = self.files.close(number);
+        result.and(closed).map(|_| content)
+    }
+
+    /// Replaces the file content, the sandbox policy applies as for OPEN
+    pub fn save_file(&mut self, path: &str, content: &str) -> Result<(), RuntimeError> {
+        let number = self
+            .files
+            .free_number()
+            .ok_or(RuntimeError::BadFileNumber)?;
+        self.open_file(path, FileMode::Output, number)?;
+        let result = self.files.write(number, content);
+        let closed = self.files.close(number);
+        result.and(closed)
+    }
+
+    /// Evaluates the file number of an I/O statement
+    fn file_number(&mut self, expression: &dyn Expression) -> Result<FileNumber, RuntimeError> {
+        file_number(&expression.evaluate(self)?)
+    }
+
+    fn channel(
+        &mut self,
+        channel: &Option<Box<dyn Expression>>,
+    ) -> Result<Option<FileNumber>, RuntimeError> {
+        match channel {
+            Some(expression) => self.file_number(expression.as_ref()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 0-based column of the console cursor
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Removes the parsed statements, labels and DATA values keeping the
+    /// variables and open files, so the next source can be parsed and run
+    /// in the same state
+    pub fn clear_program(&mut self) {
+        self.statements.clear();
+        self.statement_lines.clear();
+        self.labels.clear();
+        self.data = Default::default();
+        self.call_stack.clear();
+    }
+
+    /// Moves the parsed program out with the jumps resolved
+    pub fn take_program(&mut self) -> ParsedProgram {
+        self.resolve_jumps();
+        ParsedProgram {
+            statements: mem::take(&mut self.statements),
+            statement_lines: mem::take(&mut self.statement_lines),
+            labels: mem::take(&mut self.labels),
+            data: mem::take(&mut self.data),
+            variables: self.variables.names().to_vec(),
+        }
+    }
+
+    /// Replaces the program with a copy of the parsed one, its variables
+    /// are moved to the slots they have in this context
+    pub fn load_program(&mut self, program: &ParsedProgram) {
+        self.clear_program();
+        let slots = program
+            .variables
+            .iter()
+            .map(|name| self.variables.resolve(name))
+            .collect::<Vec<_>>();
+        self.statements = program.statements.clone();
+        if slots.iter().enumerate().any(|(old, new)| old != *new) {
+            for statement in &mut self.statements {
+                statement.rebind(&slots);
+            }
+        }
+        self.statement_lines = program.statement_lines.clone();
+        self.labels = program.labels.clone();
+        self.data = program.data.clone();
+    }
+
+    pub fn clear_variables(&mut self) {
+        self.variables.clear();
+    }
+
+    /// Outputs the text keeping track of the cursor column
+    pub fn write(&mut self, text: &str) -> Result<(), RuntimeError> {
+        self.column = advance_column(self.column, text);
+        self.io.write(text).map_err(RuntimeError::from)
+    }
+
+    /// Outputs the text to the file or to the console if there is no
+    /// file number
+    pub fn write_to(
+        &mut self,
+        channel: Option<FileNumber>,
+        text: &str,
+    ) -> Result<(), RuntimeError> {
+        match channel {
+            Some(number) => self.files.write(number, text),
+            None => self.write(text),
+        }
+    }
+
+    fn column_of(&mut self, channel: Option<FileNumber>) -> Result<usize, RuntimeError> {
+        match channel {
+            Some(number) => self.files.column(number),
+            None => Ok(self.column),
+        }
+    }
+
+    fn print(
+        &mut self,
+        channel: Option<FileNumber>,
+        items: &[PrintItem],
+        newline: bool,
+    ) -> Result<(), RuntimeError> {
+        for item in items {
+            match item {
+                PrintItem::Expression(expression) => {
+                    let eval = expression.evaluate(self)?.to_text();
+                    self.write_to(channel, &eval)?;
+                }
+                PrintItem::Tab(expression) => {
+                    let column = expression.evaluate(self)?;
+                    self.print_tab(channel, &column)?;
+                }
+                PrintItem::Spc(expression) => {
+                    let count = expression.evaluate(self)?;
+                    self.print_spc(channel, &count)?;
+                }
+                PrintItem::Zone => self.print_zone(channel)?,
+            }
+        }
+        if newline {
+            self.write_to(channel, "\n")?;
+        }
+        Ok(())
+    }
+
+    /// Moves to the 1-based column, to the next line if the cursor is
+    /// past it
+    pub fn print_tab(
+        &mut self,
+        channel: Option<FileNumber>,
+        column: &Value,
+    ) -> Result<(), RuntimeError> {
+        let column = column.try_number()?.round();
+        if column > MAX_PRINT_SPACES {
+            return Err(RuntimeError::IllegalFunctionCall("TAB".to_string()));
+        }
+        let column = column.max(1.0) as usize - 1;
+        let mut current = self.column_of(channel)?;
+        if current > column {
+            self.write_to(channel, "\n")?;
+            current = 0;
+        }
+        self.write_to(channel, &" ".repeat(column - current))
+    }
+
+    pub fn print_spc(
+        &mut self,
+        channel: Option<FileNumber>,
+        count: &Value,
+    ) -> Result<(), RuntimeError> {
+        let count = count.try_number()?.round();
+        if count > MAX_PRINT_SPACES {
+            return Err(RuntimeError::IllegalFunctionCall("SPC".to_string()));
+        }
+        self.write_to(channel, &" ".repeat(count.max(0.0) as usize))
+    }
+
+    /// Moves to the start of the next print zone
+    pub fn print_zone(&mut self, channel: Option<FileNumber>) -> Result<(), RuntimeError> {
+        let count = PRINT_ZONE_WIDTH - self.column_of(channel)? % PRINT_ZONE_WIDTH;
+        self.write_to(channel, &" ".repeat(count))
+    }
+
+    /// WRITE separates the values with commas and puts strings in quotes,
+    /// so INPUT # could read them back
+    pub fn write_values(
+        &mut self,
+        channel: Option<FileNumber>,
+        values: &[Value],
+    ) -> Result<(), RuntimeError> {
+        let mut fields = Vec::with_capacity(values.len());
+        for value in values {
+            let field = match value {
+                Value::Str(text) => format!("\"{text}\""),
+                Value::Number(_) => value.to_text(),
+                Value::Array(_) => return Err(RuntimeError::TypeMismatch),
+            };
+            fields.push(field);
+        }
+        let mut text = fields.join(",");
+        text.push('\n');
+        self.write_to(channel, &text)
+    }
+
+    /// Reads the fields from the file into the variables
+    pub fn input_file(
+        &mut self,
+        number: FileNumber,
+        variables: &[Variable],
+    ) -> Result<(), RuntimeError> {
+        for variable in variables {
+            let field = self.files.read_field(number)?;
+            let value = typed_value(&variable.name, field).ok_or(RuntimeError::TypeMismatch)?;
+            self.put_at(variable.slot, value);
+        }
+        Ok(())
+    }
+
+    /// Reads a line of the console input without the line terminator
+    pub fn read_line(&mut self) -> Result<String, RuntimeError> {
+        let Some(mut buffer) = self.io.read_line()? else {
+            return Err(RuntimeError::InputPastEnd);
+        };
+        // The user has pressed Enter, so the cursor is on a new line
+        self.column = 0;
+        let length = buffer.trim_end_matches(['\r', '\n']).len();
+        buffer.truncate(length);
+        Ok(buffer)
+    }
+
+    pub fn input(&mut self, prompt: &str, variables: &[Variable]) -> Result<(), RuntimeError> {
+        loop {
+            self.write(prompt)?;
+            let line = self.read_line()?;
+            if let Some(values) = parse_input(&line, variables) {
+                for (variable, value) in variables.iter().zip(values) {
+                    self.put_at(variable.slot, value);
+                }
+                return Ok(());
+            }
+            self.write("?Redo from start\n")?;
+        }
+    }
+
+    /// Runs the parsed statements and closes the files afterwards
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        let result = self.run_immediate();
+        // Unsaved output is flushed even if the script has failed
+        let closed = self.files.close_all();
+        let flushed = self.tracer.flush().map_err(RuntimeError::from);
+        result.and(closed).and(flushed)
+    }
+
+    /// Runs the parsed statements leaving the files open, they may be used
+    /// by the statements entered next
+    pub fn run_immediate(&mut self) -> Result<(), RuntimeError> {
+        // Statements are moved out while running, so the expressions
+        // could borrow the context mutably
+        self.resolve_jumps();
+        self.optimize();
+        let statements = mem::take(&mut self.statements);
         self.statement_index = 0;
+        self.call_stack.clear();
+        let result = match self.backend {
+            Backend::Ast => self.execute(&statements),
+            Backend::Bytecode => {
+                let chunk = Compiler::default().compile(&statements);
+                let mut vm = Vm::default();
+                let result = vm.run(self, &chunk, &statements);
+                // The tree walker leaves the position after the last
+                // statement as well
+                self.statement_index = vm.statement() + 1;
+                result
+            }
+        };
+        self.statements = statements;
+        result
+    }
+
+    /// Resolves the labels the statements jump to, the labels may be
+    /// defined after the statements using them
+    pub fn resolve_jumps(&mut self) {
+        for statement in &mut self.statements {
+            statement.resolve_jump(|label| self.labels.get(label).copied());
+        }
+    }
+
+    /// Folds the constants, threads the jumps and removes the statements
+    /// which can't run. The debugger and the coverage need the statements
+    /// to match the source, so they are left as they are.
+    fn optimize(&mut self) {
+        if !self.optimize || self.debugger.is_some() || self.coverage.is_some() {
+            return;
+        }
+        optimizer::fold_constants(&mut self.statements);
+        optimizer::thread_jumps(&mut self.statements);
+        let labels = self.labels.values().copied();
+        let removed = optimizer::unreachable(&self.statements, labels);
+        if !removed.contains(&true) {
+            return;
+        }
+        let positions = optimizer::positions(&removed);
+        optimizer::retain(&mut self.statements, &removed);
+        optimizer::retain(&mut self.statement_lines, &removed);
+        for statement in &mut self.statements {
+            statement.remap(&positions);
+        }
+        for position in self.labels.values_mut() {
+            *position = positions[*position];
+        }
+        self.data.remap(&positions);
+    }
+
+    fn execute(&mut self, statements: &[Statement]) -> Result<(), RuntimeError> {
         loop {
             let index = self.statement_index;
             self.statement_index += 1;
-            let Some(statement) = self.statements.get(index) else {
+            let Some(statement) = statements.get(index) else {
                 break;
             };
+            let step = self.begin_statement(index)?;
             use Statement::*;
             match statement {
-                Assign { name, value } => {
-                    let eval = value.evaluate(self);
-                    self.put_variable(name.clone(), eval);
+                Assign { variable, value } => {
+                    let eval = value.evaluate(self)?;
+                    self.put_at(variable.slot, eval);
                 }
-                Goto { label } => {
-                    if let Some(index) = self.label(label) {
-                        self.statement_index = index;
+                Goto { label, target } => {
+                    self.statement_index =
+                        target.ok_or_else(|| RuntimeError::UndefinedLabel(label.clone()))?;
+                }
+                Gosub { label, target } => {
+                    let target =
+                        target.ok_or_else(|| RuntimeError::UndefinedLabel(label.clone()))?;
+                    self.push_call(self.statement_index);
+                    self.statement_index = target;
+                }
+                Return => self.statement_index = self.return_position()?,
+                If { condition, end } => {
+                    let taken = condition.evaluate(self)?.try_number()? != 0.0;
+                    if !taken {
+                        self.statement_index = *end;
                     }
+                    self.cover_branch(index, taken);
+                }
+                IfThen {
+                    condition,
+                    label,
+                    target,
+                } => {
+                    let taken = condition.evaluate(self)?.try_number()? != 0.0;
+                    self.cover_branch(index, taken);
+                    if taken {
+                        self.statement_index =
+                            target.ok_or_else(|| RuntimeError::UndefinedLabel(label.clone()))?;
+                    }
+                }
+                Print {
+                    channel,
+                    items,
+                    newline,
+                } => {
+                    let channel = self.channel(channel)?;
+                    self.print(channel, items, *newline)?
                 }
-                IfThen { condition, label } => {
-                    if let Some(index) = self.label(label) {
-                        let val = condition.evaluate(self).to_number();
-                        if val != 0.0 {
-                            self.statement_index = index;
+                PrintUsing {
+                    channel,
+                    format,
+                    values,
+                    newline,
+                } => {
+                    let format = format.evaluate(self)?;
+                    let values = values
+                        .iter()
+                        .map(|value| value.evaluate(self))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let text = print_using(&format, &values, *newline)?;
+                    let channel = self.channel(channel)?;
+                    self.write_to(channel, &text)?;
+                }
+                Write { channel, values } => {
+                    let channel = self.channel(channel)?;
+                    let values = values
+                        .iter()
+                        .map(|value| value.evaluate(self))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    self.write_values(channel, &values)?;
+                }
+                Input { prompt, variables } => self.input(prompt, variables)?,
+                LineInput { prompt, variable } => self.line_input(prompt, variable)?,
+                InputFile { channel, variables } => {
+                    let number = self.file_number(channel.as_ref())?;
+                    self.input_file(number, variables)?;
+                }
+                LineInputFile { channel, variable } => {
+                    let number = self.file_number(channel.as_ref())?;
+                    self.line_input_file(number, variable)?;
+                }
+                Data => {}
+                Read { variables } => self.read_data(variables)?,
+                Restore { label, target } => {
+                    let position = match (label, target) {
+                        (None, _) => 0,
+                        (Some(_), Some(target)) => *target,
+                        (Some(label), None) => {
+                            return Err(RuntimeError::UndefinedLabel(label.clone()))
                         }
+                    };
+                    self.data.restore(position);
+                }
+                Open { path, mode, number } => {
+                    let path = path.evaluate(self)?.to_text();
+                    let number = self.file_number(number.as_ref())?;
+                    self.open_file(&path, *mode, number)?;
+                }
+                Close { numbers } => {
+                    if numbers.is_empty() {
+                        self.files.close_all()?;
+                    }
+                    for number in numbers {
+                        let number = self.file_number(number.as_ref())?;
+                        self.files.close(number)?;
                     }
                 }
-                Print { expression } => {
-                    let eval = expression.evaluate(self).to_text();
-                    println!("{eval}")
-                }
-                Input { name } => {
-                    let mut buffer = String::new();
-                    // TODO: replace stdin to local variable
-                    io::stdin()
-                        .read_line(&mut buffer)
-                        .expect("Failed to read from stdin");
-
-                    let value = if let Ok(val) = buffer.trim_end().parse::<Double>() {
-                        Value::number(val)
-                    } else {
-                        Value::string(buffer)
+                Randomize { seed } => {
+                    let seed = match seed {
+                        Some(expr) => Some(expr.evaluate(self)?),
+                        None => None,
+                    };
+                    self.randomize(seed.as_ref())?;
+                }
+                Sleep { seconds } => {
+                    let seconds = seconds.evaluate(self)?;
+                    self.sleep(&seconds)?;
+                }
+                MidAssign {
+                    variable,
+                    start,
+                    length,
+                    value,
+                } => {
+                    let start = start.evaluate(self)?;
+                    let length = match length {
+                        Some(expr) => Some(expr.evaluate(self)?),
+                        None => None,
                     };
-                    self.put_variable(name.clone(), value);
+                    let value = value.evaluate(self)?;
+                    self.mid_assign(variable, &start, length.as_ref(), &value)?;
+                }
+                Tron => self.set_tracing(true),
+                Troff => self.set_tracing(false),
+            }
+            self.end_statement(step, statement)?;
+        }
+        Ok(())
+    }
+
+    /// Checks if the debugger, the trace, the profiler or the coverage
+    /// have to be run for the statements
+    pub fn has_hooks(&self) -> bool {
+        self.debugger.is_some()
+            || self.tracer.enabled
+            || self.profiler.is_some()
+            || self.coverage.is_some()
+    }
+
+    /// Runs the debugger, the trace and the coverage hooks before the
+    /// statement at the index is executed
+    pub fn begin_statement(&mut self, index: usize) -> Result<Step, RuntimeError> {
+        if let Some(mut debugger) = self.debugger.take() {
+            let result = debugger.pause(self, index);
+            self.debugger = Some(debugger);
+            result?;
+        }
+        let traced = self.trace_statement(index)?;
+        let started = self.profiler.is_some().then(|| self.clock.now());
+        if let Some(coverage) = &mut self.coverage {
+            coverage.hit(index);
+        }
+        Ok(Step {
+            index,
+            traced,
+            started,
+        })
+    }
+
+    /// Profiles the statement and traces the values it has assigned
+    pub fn end_statement(&mut self, step: Step, statement: &Statement) -> Result<(), RuntimeError> {
+        if let Some(started) = step.started {
+            self.profile_statement(step.index, statement, started);
+        }
+        if step.traced && self.tracer.traces_values() {
+            for variable in statement.assigned_variables() {
+                if let Some(value) = self.variables.at(variable.slot) {
+                    self.tracer.value(&variable.name, value)?;
                 }
             }
         }
+        Ok(())
+    }
+
+    /// Remembers the position to RETURN to
+    pub fn push_call(&mut self, position: usize) {
+        self.call_stack.push(position);
+    }
+
+    /// Position after the innermost GOSUB
+    pub fn return_position(&mut self) -> Result<usize, RuntimeError> {
+        self.call_stack
+            .pop()
+            .ok_or(RuntimeError::ReturnWithoutGosub)
+    }
+
+    pub fn line_input(&mut self, prompt: &str, variable: &Variable) -> Result<(), RuntimeError> {
+        self.write(prompt)?;
+        let line = self.read_line()?;
+        self.put_at(variable.slot, Value::string(line));
+        Ok(())
+    }
+
+    pub fn line_input_file(
+        &mut self,
+        number: FileNumber,
+        variable: &Variable,
+    ) -> Result<(), RuntimeError> {
+        let line = self.files.read_line(number)?;
+        self.put_at(variable.slot, Value::string(line));
+        Ok(())
+    }
+
+    /// Assigns the next DATA values to the variables
+    pub fn read_data(&mut self, variables: &[Variable]) -> Result<(), RuntimeError> {
+        for variable in variables {
+            let text = self.data.read()?.to_string();
+            let value = typed_value(&variable.name, text).ok_or(RuntimeError::TypeMismatch)?;
+            self.put_at(variable.slot, value);
+        }
+        Ok(())
+    }
+
+    /// Reseeds the generator with the fixed seed if there is one, with the
+    /// time if there is no seed
+    pub fn randomize(&mut self, seed: Option<&Value>) -> Result<(), RuntimeError> {
+        let seed = match (self.fixed_seed, seed) {
+            (Some(seed), _) => seed,
+            (None, Some(value)) => Random::seed_from(value.try_number()?),
+            (None, None) => self.clock.now().as_nanos() as u64,
+        };
+        self.random.reseed(seed);
+        Ok(())
+    }
+
+    pub fn sleep(&mut self, seconds: &Value) -> Result<(), RuntimeError> {
+        let seconds = seconds.try_number()?.max(0.0);
+        let duration = Duration::try_from_secs_f32(seconds)
+            .map_err(|_| RuntimeError::IllegalFunctionCall("SLEEP".to_string()))?;
+        self.clock.sleep(duration);
+        Ok(())
+    }
+
+    /// Replaces the part of the string variable, MID$(name, start[, length])
+    pub fn mid_assign(
+        &mut self,
+        variable: &Variable,
+        start: &Value,
+        length: Option<&Value>,
+        value: &Value,
+    ) -> Result<(), RuntimeError> {
+        let Some(Value::Str(text)) = self.variable_at(variable.slot) else {
+            return Err(RuntimeError::TypeMismatch);
+        };
+        let start = start.try_number()?.round() as i64;
+        let length = match length {
+            Some(length) => Some(length.try_number()?.round() as i64),
+            None => None,
+        };
+        let text = mid_replace(text, start, length, &value.to_text())?;
+        self.put_at(variable.slot, Value::string(text));
+        Ok(())
+    }
+
+    /// Turns the tracing on or off as TRON and TROFF do
+    pub fn set_tracing(&mut self, enabled: bool) {
+        self.tracer.enabled = enabled;
+    }
+
+    /// Records the outcome of the IF at the statement index
+    pub fn cover_branch(&mut self, index: usize, taken: bool) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.branch(index, taken);
+        }
+    }
+
+    /// Adds the time since the statement has started to the profile
+    fn profile_statement(&mut self, index: usize, statement: &Statement, started: Duration) {
+        let time = self.clock.now().saturating_sub(started);
+        let line = self.statement_line(index).unwrap_or_default();
+        let Some(profiler) = &mut self.profiler else {
+            return;
+        };
+        profiler.record(index, line, time);
+        match statement {
+            Statement::Gosub { label, .. } => profiler.enter(label),
+            Statement::Return => profiler.leave(),
+            _ => {}
+        }
+    }
+
+    /// Logs the statement if the tracing is on and the statement passes
+    /// the filter
+    fn trace_statement(&mut self, index: usize) -> Result<bool, RuntimeError> {
+        if !self.tracer.enabled {
+            return Ok(false);
+        }
+        let line = self.statement_line(index).unwrap_or_default();
+        let section = section(&self.labels, index);
+        if !self.tracer.is_traced(line, section) {
+            return Ok(false);
+        }
+        self.tracer.statement(line, section)?;
+        Ok(true)
+    }
+}
+
+/// Number of the file from the value of the expression after #
+pub fn file_number(value: &Value) -> Result<FileNumber, RuntimeError> {
+    if !value.is_numeric() {
+        return Err(RuntimeError::TypeMismatch);
+    }
+    Ok(value.try_number()?.round() as FileNumber)
+}
+
+/// Text of PRINT USING with the values formatted
+pub fn print_using(
+    format: &Value,
+    values: &[Value],
+    newline: bool,
+) -> Result<String, RuntimeError> {
+    let mut text = format_using(&format.to_text(), values)?;
+    if newline {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Closest label before the statement
+fn section(labels: &HashMap<String, usize>, index: usize) -> Option<&str> {
+    labels
+        .iter()
+        .filter(|(_, position)| **position <= index)
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(name, _)| name.as_str())
+}
+
+/// Names of string variables end with $, others hold numbers
+pub fn is_string_variable(name: &str) -> bool {
+    name.ends_with('$')
+}
+
+/// Converts the comma separated input to values of the variables types.
+/// Returns None if the number of fields doesn't match or a field isn't
+/// a number when a numeric variable is expected.
+fn parse_input(line: &str, names: &[impl AsRef<str>]) -> Option<Vec<Value>> {
+    let fields = split_fields(line);
+    if fields.len() != names.len() {
+        return None;
+    }
+    names
+        .iter()
+        .zip(fields)
+        .map(|(name, field)| typed_value(name.as_ref(), field))
+        .collect()
+}
+
+/// Converts the text to the type of the variable, None if the variable
+/// is numeric and the text isn't a number. Empty text is zero.
+fn typed_value(name: &str, text: String) -> Option<Value> {
+    if is_string_variable(name) {
+        Some(Value::string(text))
+    } else if text.is_empty() {
+        Some(Value::number(0.0))
+    } else {
+        text.trim().parse::<Double>().ok().map(Value::number)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        clock::FakeClock,
+        datetime::DateTime,
+        io::{FileStream, MemoryIo},
+        parser::Parser,
+        tokenizer::Tokenizer,
+        trace::TraceFilter,
+    };
+
+    use super::*;
+
+    const BACKENDS: [Backend; 2] = [Backend::Ast, Backend::Bytecode];
+
+    fn context(backend: Backend) -> InterpreterContext {
+        InterpreterContext::new(&Config {
+            backend,
+            ..Default::default()
+        })
+    }
+
+    fn run_script(source: &str, context: &mut InterpreterContext) -> Result<(), RuntimeError> {
+        let tokens = Tokenizer::new().tokenize(source);
+        Parser::new(tokens, context)
+            .parse()
+            .expect("Failed to parse the script");
+        context.run()
+    }
+
+    /// Runs the script with the given input on each backend, checks they
+    /// agree and returns the output
+    fn run_with_input(source: &str, input: &str) -> (Result<(), RuntimeError>, String) {
+        let [ast, bytecode] = BACKENDS.map(|backend| {
+            let io = MemoryIo::new(input);
+            let mut context = context(backend);
+            context.set_io(Box::new(io.clone()));
+            let result = run_script(source, &mut context);
+            (result, io.output())
+        });
+        assert_eq!(ast, bytecode, "{source}");
+        bytecode
+    }
+
+    fn output(source: &str) -> String {
+        let (result, output) = run_with_input(source, "");
+        result.unwrap();
+        output
+    }
+
+    fn text(context: &InterpreterContext, name: &str) -> String {
+        context
+            .variable(name)
+            .expect("Variable not found")
+            .to_text()
+    }
+
+    fn number(context: &InterpreterContext, name: &str) -> Double {
+        context
+            .variable(name)
+            .expect("Variable not found")
+            .try_number()
+            .unwrap()
+    }
+
+    #[test]
+    fn interpret_variables_by_name() {
+        for backend in BACKENDS {
+            let mut context = context(backend);
+            context.put_variable("n".to_string(), Value::number(3.0));
+            let script = "GOSUB double\nm$ = \"done\"\nGOTO end\ndouble: n = n * 2\nRETURN\nend:";
+            run_script(script, &mut context).unwrap();
+            assert_eq!(number(&context, "n"), 6.0, "{backend:?}");
+            assert_eq!(text(&context, "m$"), "done", "{backend:?}");
+            let names = context
+                .variables()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["n", "m$"], "{backend:?}");
+            context.clear_variables();
+            assert_eq!(context.variable("n"), None, "{backend:?}");
+        }
+    }
+
+    #[test]
+    fn interpret_fixed_seed_reproducible() {
+        for backend in BACKENDS {
+            let script = "RANDOMIZE TIMER\na = RND\nb = RND(1)\nc = RND(0)";
+            let mut first = context(backend);
+            first.fix_seed(42);
+            run_script(script, &mut first).unwrap();
+            let mut second = context(backend);
+            second.fix_seed(42);
+            run_script(script, &mut second).unwrap();
+            for name in ["a", "b", "c"] {
+                assert_eq!(number(&first, name), number(&second, name));
+            }
+            assert_ne!(number(&first, "a"), number(&first, "b"));
+            assert_eq!(number(&first, "b"), number(&first, "c"));
+        }
+    }
+
+    #[test]
+    fn interpret_randomize_seed() {
+        for backend in BACKENDS {
+            let script = "RANDOMIZE 7\na = RND\nRANDOMIZE 7\nb = RND\nc = RND";
+            let mut context = context(backend);
+            run_script(script, &mut context).unwrap();
+            assert_eq!(number(&context, "a"), number(&context, "b"));
+            assert_ne!(number(&context, "b"), number(&context, "c"));
+        }
+    }
+
+    #[test]
+    fn interpret_randomize_from_clock() {
+        for backend in BACKENDS {
+            let numbers = [0, 0, 1].map(|seconds| {
+                let mut context = context(backend);
+                context.set_clock(Box::new(FakeClock::new(Duration::from_secs(seconds))));
+                run_script("RANDOMIZE\na = RND", &mut context).unwrap();
+                number(&context, "a")
+            });
+            assert_eq!(numbers[0], numbers[1], "{backend:?}");
+            assert_ne!(numbers[1], numbers[2], "{backend:?}");
+        }
+    }
+
+    #[test]
+    fn interpret_fake_clock() {
+        for backend in BACKENDS {
+            let script = r#"
+t = TIMER
+SLEEP 90
+elapsed = TIMER - t
+n$ = NOW
+d$ = DATE$
+tm$ = TIME$
+later$ = DATEADD("m", 1, NOW)
+days = DATEDIFF("d", NOW, later$)
+f$ = FORMATDATE$(later$, "dd mmm yyyy")
+"#;
+            let now = DateTime::parse("2024-03-05 07:08:09").unwrap().timestamp();
+            let mut context = context(backend);
+            context.set_clock(Box::new(FakeClock::new(Duration::from_secs(now as u64))));
+            run_script(script, &mut context).unwrap();
+            assert_eq!(number(&context, "t"), 25689.0);
+            assert_eq!(number(&context, "elapsed"), 90.0);
+            assert_eq!(text(&context, "n$"), "2024-03-05 07:09:39");
+            assert_eq!(text(&context, "d$"), "03-05-2024");
+            assert_eq!(text(&context, "tm$"), "07:09:39");
+            assert_eq!(text(&context, "later$"), "2024-04-05 07:09:39");
+            assert_eq!(number(&context, "days"), 31.0);
+            assert_eq!(text(&context, "f$"), "05 Apr 2024");
+        }
+    }
+
+    #[test]
+    fn interpret_time_overflow() {
+        for backend in BACKENDS {
+            for script in [
+                "SLEEP 1 / 0",
+                "x$ = DATEADD(\"yyyy\", 1000000 * 1000000 * 1000000, NOW)",
+                "x$ = DATEADD(\"s\", 1000000 * 1000000 * 1000000 * 1000000, NOW)",
+                "x$ = DATEADD(\"d\", 4000000, NOW)",
+            ] {
+                let mut context = context(backend);
+                context.set_clock(Box::new(FakeClock::new(Duration::ZERO)));
+                let err = run_script(script, &mut context).unwrap_err();
+                assert!(
+                    matches!(err, RuntimeError::IllegalFunctionCall(_)),
+                    "{script} {backend:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn interpret_string_functions() {
+        for backend in BACKENDS {
+            let script = r#"
+a$ = "Hello, мир"
+MID$(a$, 1, 2) = "JJJ"
+parts = SPLIT("x,y,z", ",")
+p$ = parts(1)
+j$ = JOIN$(parts, "-")
+n = LEN(a$) + INSTR(a$, "мир")
+"#;
+            let mut context = context(backend);
+            run_script(script, &mut context).unwrap();
+            assert_eq!(text(&context, "a$"), "JJllo, мир");
+            assert_eq!(text(&context, "p$"), "y");
+            assert_eq!(text(&context, "j$"), "x-y-z");
+            assert_eq!(number(&context, "n"), 18.0);
+        }
+    }
+
+    #[test]
+    fn interpret_print_column() {
+        for backend in BACKENDS {
+            let cases = [
+                ("PRINT \"abc\";", 3),
+                ("PRINT \"abc\"", 0),
+                ("PRINT \"a\", ", 14),
+                ("PRINT \"a\", \"b\";", 15),
+                ("PRINT \"abc\"; TAB(10);", 9),
+                ("PRINT \"abc\"; SPC(2); 1;", 6),
+                ("PRINT \"abcdef\"; TAB(3); \"x\";", 3),
+            ];
+            for (script, column) in cases {
+                let mut context = context(backend);
+                run_script(script, &mut context).unwrap();
+                assert_eq!(context.column, column, "{backend:?}: {script}");
+            }
+        }
+    }
+
+    #[test]
+    fn interpret_print_separators() {
+        let script = r#"
+PRINT "a"; "b"; 1
+PRINT "x", "y"
+PRINT "no newline";
+PRINT " - continued"
+PRINT
+PRINT "col"; TAB(10); "t"; SPC(2); "s"
+PRINT "abcdef"; TAB(3); "wrap"
+"#;
+        let expected =
+            "ab1\nx             y\nno newline - continued\n\ncol      t  s\nabcdef\n  wrap\n";
+        assert_eq!(output(script), expected);
+    }
+
+    #[test]
+    fn interpret_print_spaces_limit() {
+        for script in [
+            "PRINT TAB(99999999999)",
+            "PRINT SPC(256)",
+            "PRINT TAB(1 / 0)",
+        ] {
+            let (result, output) = run_with_input(script, "");
+            assert!(
+                matches!(result, Err(RuntimeError::IllegalFunctionCall(_))),
+                "{script}"
+            );
+            assert_eq!(output, "", "{script}");
+        }
+        assert_eq!(
+            output("PRINT SPC(255); \"x\""),
+            format!("{}x\n", " ".repeat(255))
+        );
+    }
+
+    #[test]
+    fn interpret_print_using() {
+        let script = r####"
+PRINT USING "###.##"; 3.14159
+PRINT USING "\   \ $$#,###.##|"; "Apples", 1234.5; "Pears", 7
+PRINT USING "##"; 1;
+PRINT FORMAT$(0.1 + 0.2, "#.##")
+"####;
+        let expected = "  3.14\nApple  $1,234.50|Pears      $7.00|\n 10.30\n";
+        assert_eq!(output(script), expected);
+    }
+
+    #[test]
+    fn interpret_input() {
+        let script = r#"
+INPUT "Name and age"; n$, age
+INPUT "Raw: ", r$
+LINE INPUT l$
+PRINT n$; "|"; age; "|"; r$; "|"; l$
+"#;
+        let input = "Bob, old\n\"Smith, J\", 42\r\nhello\n  a, b \"c\"  \n";
+        let (result, output) = run_with_input(script, input);
+        result.unwrap();
+        let expected =
+            "Name and age? ?Redo from start\nName and age? Raw: Smith, J|42|hello|  a, b \"c\"  \n";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn interpret_input_past_end() {
+        let (result, output) = run_with_input("INPUT a\nINPUT b", "1\n");
+        assert_eq!(result, Err(RuntimeError::InputPastEnd));
+        assert_eq!(output, "? ? ");
+    }
+
+    #[test]
+    fn input_parse_typed() {
+        let names = ["n$".to_string(), "a".to_string()];
+        let values = parse_input("Bob, 42", &names).unwrap();
+        assert_eq!(
+            values,
+            [Value::string("Bob".to_string()), Value::number(42.0)]
+        );
+        let values = parse_input("42, 1.5", &names).unwrap();
+        assert_eq!(
+            values,
+            [Value::string("42".to_string()), Value::number(1.5)]
+        );
+        assert!(parse_input("Bob, many", &names).is_none());
+        assert!(parse_input("Bob", &names).is_none());
+        assert!(parse_input("Bob, 1, 2", &names).is_none());
+    }
+
+    #[test]
+    fn interpret_files() {
+        for backend in BACKENDS {
+            let script = r####"
+OPEN "data.csv" FOR OUTPUT AS #1
+WRITE #1, "Smith, J", 42
+PRINT #1, "Bob"; TAB(6); 7
+CLOSE #1
+OPEN "data.csv" FOR APPEND AS 2
+PRINT #2, USING "##.#"; 1.26
+CLOSE
+n = FREEFILE
+OPEN "data.csv" FOR INPUT AS #n
+INPUT #n, name$, age
+LINE INPUT #n, line$
+count = 0
+loop:
+IF EOF(n) THEN done
+INPUT #n, rest
+count = count + 1
+GOTO loop
+done:
+CLOSE n
+"####;
+            let io = MemoryIo::default();
+            let mut context = context(backend);
+            context.set_io(Box::new(io.clone()));
+            run_script(script, &mut context).unwrap();
+            let expected = "\"Smith, J\",42\nBob  7\n 1.3\n";
+            assert_eq!(io.file("data.csv").as_deref(), Some(expected));
+            assert_eq!(number(&context, "n"), 1.0);
+            assert_eq!(text(&context, "name$"), "Smith, J");
+            assert_eq!(number(&context, "age"), 42.0);
+            assert_eq!(text(&context, "line$"), "Bob  7");
+            assert_eq!(number(&context, "count"), 1.0);
+            assert_eq!(number(&context, "rest"), 1.3);
+        }
+    }
+
+    #[test]
+    fn interpret_file_errors() {
+        for backend in BACKENDS {
+            let cases = [
+                (
+                    "OPEN \"missing.txt\" FOR INPUT AS #1",
+                    RuntimeError::FileNotFound("missing.txt".to_string()),
+                ),
+                ("PRINT #3, 1", RuntimeError::BadFileNumber),
+                (
+                    "OPEN \"a.txt\" FOR OUTPUT AS #0",
+                    RuntimeError::BadFileNumber,
+                ),
+                (
+                    "OPEN \"a.txt\" FOR OUTPUT AS #1\nOPEN \"b.txt\" FOR OUTPUT AS #1",
+                    RuntimeError::FileAlreadyOpen,
+                ),
+                (
+                    "OPEN \"a.txt\" FOR OUTPUT AS #1\nINPUT #1, a",
+                    RuntimeError::BadFileMode,
+                ),
+                (
+                    "OPEN \"in.txt\" FOR INPUT AS #1\nINPUT #1, a",
+                    RuntimeError::TypeMismatch,
+                ),
+                (
+                    "OPEN \"in.txt\" FOR INPUT AS #1\nINPUT #1, a$, b$, c$",
+                    RuntimeError::InputPastEnd,
+                ),
+            ];
+            for (script, error) in cases.clone() {
+                let io = MemoryIo::default();
+                io.put_file("in.txt", "abc, def\n");
+                let mut context = context(backend);
+                context.set_io(Box::new(io));
+                assert_eq!(
+                    run_script(script, &mut context),
+                    Err(error),
+                    "{backend:?}: {script}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn interpret_sandbox() {
+        for backend in BACKENDS {
+            let denied = |path: &str| Err(RuntimeError::PermissionDenied(path.to_string()));
+            let limited = Sandbox {
+                max_file_size: Some(8),
+                ..Default::default()
+            };
+            let cases = [
+                (
+                    Sandbox {
+                        allowed_dirs: vec!["/no-such-root/data".into()],
+                        ..Default::default()
+                    },
+                    "OPEN \"/no-such-root/data/../in.txt\" FOR INPUT AS #1",
+                    denied("/no-such-root/data/../in.txt"),
+                ),
+                (
+                    Sandbox {
+                        read_only: true,
+                        ..Default::default()
+                    },
+                    "OPEN \"in.txt\" FOR INPUT AS #1\nOPEN \"out.txt\" FOR OUTPUT AS #2",
+                    denied("out.txt"),
+                ),
+                (
+                    limited.clone(),
+                    "OPEN \"out.txt\" FOR OUTPUT AS #1\nPRINT #1, \"1234567\"",
+                    Ok(()),
+                ),
+                (
+                    limited.clone(),
+                    "OPEN \"out.txt\" FOR OUTPUT AS #1\nPRINT #1, \"12345678\"",
+                    denied("out.txt"),
+                ),
+                (
+                    limited.clone(),
+                    "OPEN \"in.txt\" FOR APPEND AS #1\nPRINT #1, \"x\"",
+                    denied("in.txt"),
+                ),
+                (
+                    Sandbox {
+                        max_file_size: Some(4),
+                        ..Default::default()
+                    },
+                    "OPEN \"in.txt\" FOR INPUT AS #1",
+                    denied("in.txt"),
+                ),
+            ];
+            for (sandbox, script, result) in cases.clone() {
+                let io = MemoryIo::default();
+                io.put_file("in.txt", "abc, def");
+                let mut context = context(backend);
+                context.set_io(Box::new(io));
+                context.set_sandbox(sandbox);
+                assert_eq!(
+                    run_script(script, &mut context),
+                    result,
+                    "{backend:?}: {script}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn interpret_read_data() {
+        for backend in BACKENDS {
+            let script = r#"
+READ a, b$, c$
+PRINT a; b$; c$
+DATA 1, "Smith, J"
+RESTORE second
+READ d$, e
+DATA skipped
+second:
+DATA New York, -2.5
+RESTORE
+READ f$
+"#;
+            let mut context = context(backend);
+            run_script(script, &mut context).unwrap();
+            assert_eq!(number(&context, "a"), 1.0);
+            assert_eq!(text(&context, "b$"), "Smith, J");
+            assert_eq!(text(&context, "c$"), "skipped");
+            assert_eq!(text(&context, "d$"), "New York");
+            assert_eq!(number(&context, "e"), -2.5);
+            assert_eq!(text(&context, "f$"), "1");
+        }
+    }
+
+    #[test]
+    fn interpret_read_errors() {
+        for backend in BACKENDS {
+            let cases = [
+                ("DATA 1\nREAD a, b", RuntimeError::OutOfData),
+                ("DATA abc\nREAD a", RuntimeError::TypeMismatch),
+                (
+                    "RESTORE nowhere",
+                    RuntimeError::UndefinedLabel("nowhere".to_string()),
+                ),
+            ];
+            for (script, error) in cases.clone() {
+                let mut context = context(backend);
+                assert_eq!(
+                    run_script(script, &mut context),
+                    Err(error),
+                    "{backend:?}: {script}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn interpret_line_numbers() {
+        let script = r#"
+10 i = 0
+20 GOSUB 100
+30 IF i < 3 THEN 20
+40 GOTO done
+50 PRINT "skipped"
+done:
+60 GOSUB sub
+70 RESTORE 210
+80 READ d
+90 GOTO 999
+100 i = i + 1
+110 PRINT i;
+120 RETURN
+sub:
+PRINT "sub"
+RETURN
+200 DATA 1
+210 DATA 2
+999 PRINT d
+"#;
+        assert_eq!(output(script), "123sub\n2\n");
+    }
+
+    #[test]
+    fn interpret_jump_errors() {
+        for backend in BACKENDS {
+            let cases = [
+                ("GOSUB 10", RuntimeError::UndefinedLabel("10".to_string())),
+                ("GOTO 999", RuntimeError::UndefinedLabel("999".to_string())),
+                (
+                    "x = 1\nIF x THEN 999",
+                    RuntimeError::UndefinedLabel("999".to_string()),
+                ),
+                (
+                    "GOTO nowhere",
+                    RuntimeError::UndefinedLabel("nowhere".to_string()),
+                ),
+                ("10 RETURN", RuntimeError::ReturnWithoutGosub),
+            ];
+            for (script, error) in cases.clone() {
+                let mut context = context(backend);
+                assert_eq!(
+                    run_script(script, &mut context),
+                    Err(error),
+                    "{backend:?}: {script}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn interpret_colon_separators() {
+        let script = r#"
+x = 1: y = 2: PRINT x + y
+IF x = 1 THEN PRINT "a";: PRINT "b"
+IF x = 2 THEN PRINT "skipped": PRINT "skipped"
+PRINT "c"
+IF y = 2 THEN 10: PRINT "skipped"
+PRINT "skipped"
+10 GOSUB sub: PRINT "e"
+IF x THEN done
+sub: PRINT "d";
+RETURN: PRINT "skipped"
+done:
+"#;
+        assert_eq!(output(script), "3\nab\nc\nde\n");
+    }
+
+    const TRACE_FILE: &str = "trace.txt";
+
+    /// Writes the trace to a file of the in-memory backend
+    fn capture_trace(context: &mut InterpreterContext, io: &MemoryIo) {
+        let Ok(FileStream::Writer(output)) = io.clone().open(TRACE_FILE, FileMode::Output) else {
+            panic!("Trace file can't be created");
+        };
+        context.set_trace_output(output);
+    }
+
+    #[test]
+    fn interpret_tron_troff() {
+        for backend in BACKENDS {
+            let script = r#"x = 1
+TRON
+y$ = "a": READ n
+GOSUB sub
+TROFF
+PRINT y$
+GOTO done
+sub:
+z = n + 1
+RETURN
+done:
+DATA 5"#;
+            let config = Config {
+                trace: TraceConfig {
+                    values: true,
+                    ..Default::default()
+                },
+                backend,
+                ..Default::default()
+            };
+            let io = MemoryIo::default();
+            let mut context = InterpreterContext::new(&config);
+            context.set_io(Box::new(io.clone()));
+            capture_trace(&mut context, &io);
+            run_script(script, &mut context).unwrap();
+            assert_eq!(io.output(), "a\n");
+            let expected = r#"[line 3]
+  y$ = "a"
+[line 3]
+  n = 5
+[line 4]
+[line 9, sub]
+  z = 6
+[line 10, sub]
+[line 5]
+"#;
+            assert_eq!(io.file(TRACE_FILE).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn interpret_trace_filter() {
+        for backend in BACKENDS {
+            let script = "i = 0\nloop: i = i + 1\nIF i < 2 THEN loop\nGOSUB sub\nGOTO done\nsub: PRINT i\nRETURN\ndone:";
+            let config = Config {
+                trace: TraceConfig {
+                    enabled: true,
+                    filter: TraceFilter::parse_list("sub,3").unwrap(),
+                    ..Default::default()
+                },
+                backend,
+                ..Default::default()
+            };
+            let io = MemoryIo::default();
+            let mut context = InterpreterContext::new(&config);
+            context.set_io(Box::new(io.clone()));
+            capture_trace(&mut context, &io);
+            run_script(script, &mut context).unwrap();
+            let expected = "[line 3, loop]\n[line 3, loop]\n[line 6, sub]\n[line 7, sub]\n";
+            assert_eq!(io.file(TRACE_FILE).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn interpret_function_error() {
+        for backend in BACKENDS {
+            let mut context = context(backend);
+            let result = run_script("a = SPLIT(\"x\", \",\")\nb = a(3)", &mut context);
+            assert_eq!(result, Err(RuntimeError::SubscriptOutOfRange));
+        }
     }
 }
@@ -0,0 +1,279 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Cursor, Write},
+    rc::Rc,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileMode {
+    Input,
+    Output,
+    Append,
+}
+
+/// Opened file, either for reading or for writing depending on the mode
+pub enum FileStream {
+    Reader(Box<dyn BufRead>),
+    Writer(Box<dyn Write>),
+}
+
+/// Console and file system of the interpreter, all input and output of
+/// the script goes through it
+pub trait IoBackend {
+    /// Outputs the text as is
+    fn write(&mut self, text: &str) -> io::Result<()>;
+
+    /// Reads a line including its terminator or returns None at the end
+    /// of input
+    fn read_line(&mut self) -> io::Result<Option<String>>;
+
+    fn open(&mut self, path: &str, mode: FileMode) -> io::Result<FileStream>;
+
+    /// Size of the file in bytes
+    fn file_size(&mut self, path: &str) -> io::Result<u64>;
+}
+
+/// Standard output and input of the process
+#[derive(Default)]
+pub struct StdIo;
+
+impl IoBackend for StdIo {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        io::stdout().write_all(text.as_bytes())
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        // The prompt must be visible before waiting for the input
+        io::stdout().flush()?;
+        let mut buffer = String::new();
+        let count = io::stdin().lock().read_line(&mut buffer)?;
+        Ok((count > 0).then_some(buffer))
+    }
+
+    fn open(&mut self, path: &str, mode: FileMode) -> io::Result<FileStream> {
+        let stream = match mode {
+            FileMode::Input => FileStream::Reader(Box::new(BufReader::new(File::open(path)?))),
+            FileMode::Output => FileStream::Writer(Box::new(BufWriter::new(File::create(path)?))),
+            FileMode::Append => {
+                let file = OpenOptions::new().append(true).create(true).open(path)?;
+                FileStream::Writer(Box::new(BufWriter::new(file)))
+            }
+        };
+        Ok(stream)
+    }
+
+    fn file_size(&mut self, path: &str) -> io::Result<u64> {
+        Ok(fs::metadata(path)?.len())
+    }
+}
+
+/// Column of the cursor after the text is output
+pub fn advance_column(column: usize, text: &str) -> usize {
+    match text.rfind('\n') {
+        Some(position) => text[position + 1..].chars().count(),
+        None => column + text.chars().count(),
+    }
+}
+
+/// Splits the line by commas, fields in double quotes may contain commas.
+/// Spaces around the fields are removed.
+pub fn split_fields(line: &str) -> Vec<String> {
+    let finish = |field: &mut String, quoted: bool| {
+        let field = std::mem::take(field);
+        if quoted {
+            field
+        } else {
+            field.trim().to_string()
+        }
+    };
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut quoted = false;
+    for ch in line.chars() {
+        match ch {
+            '"' if in_quotes => in_quotes = false,
+            '"' if !quoted && field.trim().is_empty() => {
+                field.clear();
+                in_quotes = true;
+                quoted = true;
+            }
+            ',' if !in_quotes => {
+                fields.push(finish(&mut field, quoted));
+                quoted = false;
+            }
+            // Anything between the closing quote and the comma is ignored
+            _ if quoted && !in_quotes => {}
+            _ => field.push(ch),
+        }
+    }
+    fields.push(finish(&mut field, quoted));
+    fields
+}
+
+type SharedFile = Rc<RefCell<Vec<u8>>>;
+
+#[derive(Default)]
+struct MemoryBuffers {
+    input: VecDeque<String>,
+    output: String,
+    files: HashMap<String, SharedFile>,
+}
+
+/// Appends the written bytes to an in-memory file
+struct MemoryFileWriter(SharedFile);
+
+impl Write for MemoryFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads the input from a string and collects the output in memory,
+/// files are kept in memory as well. Clones share the same buffers, so
+/// the output remains accessible after the backend is passed to the
+/// interpreter.
+#[derive(Clone, Default)]
+pub struct MemoryIo {
+    buffers: Rc<RefCell<MemoryBuffers>>,
+}
+
+impl MemoryIo {
+    pub fn new(input: &str) -> Self {
+        let io = Self::default();
+        io.push_input(input);
+        io
+    }
+
+    /// Appends lines to the unread input
+    pub fn push_input(&self, input: &str) {
+        let mut buffers = self.buffers.borrow_mut();
+        buffers
+            .input
+            .extend(input.split_inclusive('\n').map(|line| line.to_string()));
+    }
+
+    /// Everything written so far
+    pub fn output(&self) -> String {
+        self.buffers.borrow().output.clone()
+    }
+
+    /// Returns the output written so far and clears it
+    pub fn take_output(&self) -> String {
+        std::mem::take(&mut self.buffers.borrow_mut().output)
+    }
+
+    /// Creates or replaces a file
+    pub fn put_file(&self, path: &str, content: &str) {
+        let file = Rc::new(RefCell::new(content.as_bytes().to_vec()));
+        self.buffers
+            .borrow_mut()
+            .files
+            .insert(path.to_string(), file);
+    }
+
+    pub fn file(&self, path: &str) -> Option<String> {
+        let buffers = self.buffers.borrow();
+        let file = buffers.files.get(path)?.borrow();
+        Some(String::from_utf8_lossy(&file).to_string())
+    }
+}
+
+impl IoBackend for MemoryIo {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.buffers.borrow_mut().output.push_str(text);
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        Ok(self.buffers.borrow_mut().input.pop_front())
+    }
+
+    fn open(&mut self, path: &str, mode: FileMode) -> io::Result<FileStream> {
+        let mut buffers = self.buffers.borrow_mut();
+        if mode == FileMode::Input {
+            let Some(file) = buffers.files.get(path) else {
+                return Err(io::ErrorKind::NotFound.into());
+            };
+            let content = file.borrow().clone();
+            return Ok(FileStream::Reader(Box::new(Cursor::new(content))));
+        }
+        let file = buffers.files.entry(path.to_string()).or_default();
+        if mode == FileMode::Output {
+            file.borrow_mut().clear();
+        }
+        Ok(FileStream::Writer(Box::new(MemoryFileWriter(file.clone()))))
+    }
+
+    fn file_size(&mut self, path: &str) -> io::Result<u64> {
+        let buffers = self.buffers.borrow();
+        match buffers.files.get(path) {
+            Some(file) => Ok(file.borrow().len() as u64),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn memory_io_shared_buffers() {
+        let io = MemoryIo::new("first\nsecond");
+        let mut backend: Box<dyn IoBackend> = Box::new(io.clone());
+        assert_eq!(backend.read_line().unwrap().as_deref(), Some("first\n"));
+        assert_eq!(backend.read_line().unwrap().as_deref(), Some("second"));
+        assert_eq!(backend.read_line().unwrap(), None);
+        backend.write("hello ").unwrap();
+        backend.write("world").unwrap();
+        assert_eq!(io.output(), "hello world");
+        assert_eq!(io.take_output(), "hello world");
+        assert_eq!(io.output(), "");
+    }
+
+    #[test]
+    fn memory_io_files() {
+        let mut io = MemoryIo::default();
+        assert!(io.open("missing.txt", FileMode::Input).is_err());
+        let FileStream::Writer(mut writer) = io.open("a.txt", FileMode::Output).unwrap() else {
+            panic!("Writer expected")
+        };
+        writer.write_all(b"one\n").unwrap();
+        let FileStream::Writer(mut writer) = io.open("a.txt", FileMode::Append).unwrap() else {
+            panic!("Writer expected")
+        };
+        writer.write_all(b"two\n").unwrap();
+        assert_eq!(io.file("a.txt").as_deref(), Some("one\ntwo\n"));
+        let FileStream::Reader(mut reader) = io.open("a.txt", FileMode::Input).unwrap() else {
+            panic!("Reader expected")
+        };
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "one\n");
+    }
+
+    #[test]
+    fn io_split_fields() {
+        assert_eq!(split_fields("1, 2 ,3"), ["1", "2", "3"]);
+        assert_eq!(split_fields("  hello world  "), ["hello world"]);
+        assert_eq!(split_fields("\"Smith, J\" , 42"), ["Smith, J", "42"]);
+        assert_eq!(split_fields("\" padded \""), [" padded "]);
+        assert_eq!(split_fields("a,,b,"), ["a", "", "b", ""]);
+        assert_eq!(split_fields(""), [""]);
+    }
+
+    #[test]
+    fn io_advance_column() {
+        assert_eq!(advance_column(3, "ab"), 5);
+        assert_eq!(advance_column(3, "ab\nмир"), 3);
+        assert_eq!(advance_column(3, "\n"), 0);
+    }
+}
@@ -0,0 +1,376 @@
+use std::{fmt::Display, iter::Peekable, str::Chars};
+
+/// JSON value used by the editor protocols. Objects keep the order of
+/// their keys.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object<const N: usize>(entries: [(&str, Json); N]) -> Self {
+        let entries = entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect();
+        Self::Object(entries)
+    }
+
+    /// Value of the object key, Null if there is no such key
+    pub fn get(&self, key: &str) -> &Json {
+        match self {
+            Json::Object(entries) => entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map_or(&Json::Null, |(_, value)| value),
+            _ => &Json::Null,
+        }
+    }
+
+    /// Adds or replaces the object key
+    pub fn set(&mut self, key: &str, value: Json) {
+        let Json::Object(entries) = self else {
+            return;
+        };
+        match entries.iter_mut().find(|(name, _)| name == key) {
+            Some(entry) => entry.1 = value,
+            None => entries.push((key.to_string(), value)),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|value| *value >= 0.0 && value.fract() == 0.0)
+            .map(|value| value as usize)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> &[Json] {
+        match self {
+            Json::Array(items) => items,
+            _ => &[],
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars, 0)?;
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => Ok(value),
+            Some(ch) => Err(format!("Unexpected '{ch}' after the value")),
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Self {
+        Json::Number(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(value: Vec<Json>) -> Self {
+        Json::Array(value)
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{value}"),
+            Json::Number(value) if value.is_finite() => write!(f, "{value}"),
+            // JSON has no infinities and NaN
+            Json::Number(_) => write!(f, "null"),
+            Json::String(text) => write_string(f, text),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(entries) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, text: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for ch in text.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{ch}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+type Input<'a> = Peekable<Chars<'a>>;
+
+/// Nesting of the arrays and objects the parser accepts, so a malicious
+/// message can't overflow the stack
+const MAX_DEPTH: usize = 128;
+
+fn skip_whitespace(chars: &mut Input) {
+    while chars.next_if(|ch| ch.is_ascii_whitespace()).is_some() {}
+}
+
+fn parse_value(chars: &mut Input, depth: usize) -> Result<Json, String> {
+    skip_whitespace(chars);
+    if depth >= MAX_DEPTH && matches!(chars.peek(), Some('{' | '[')) {
+        return Err("JSON is nested too deeply".to_string());
+    }
+    match chars.peek() {
+        Some('{') => parse_object(chars, depth + 1),
+        Some('[') => parse_array(chars, depth + 1),
+        Some('"') => parse_string(chars).map(Json::String),
+        Some('-' | '0'..='9') => parse_number(chars),
+        Some(_) => parse_literal(chars),
+        None => Err("Unexpected end of JSON".to_string()),
+    }
+}
+
+fn expect(chars: &mut Input, expected: char) -> Result<(), String> {
+    skip_whitespace(chars);
+    match chars.next() {
+        Some(ch) if ch == expected => Ok(()),
+        Some(ch) => Err(format!("Expected '{expected}', found '{ch}'")),
+        None => Err(format!("Expected '{expected}', found end of JSON")),
+    }
+}
+
+fn parse_object(chars: &mut Input, depth: usize) -> Result<Json, String> {
+    expect(chars, '{')?;
+    let mut entries = Vec::new();
+    skip_whitespace(chars);
+    if chars.next_if_eq(&'}').is_some() {
+        return Ok(Json::Object(entries));
+    }
+    loop {
+        skip_whitespace(chars);
+        let key = parse_string(chars)?;
+        expect(chars, ':')?;
+        let value = parse_value(chars, depth)?;
+        entries.push((key, value));
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some('}') => return Ok(Json::Object(entries)),
+            _ => return Err("Expected ',' or '}' in the object".to_string()),
+        }
+    }
+}
+
+fn parse_array(chars: &mut Input, depth: usize) -> Result<Json, String> {
+    expect(chars, '[')?;
+    let mut items = Vec::new();
+    skip_whitespace(chars);
+    if chars.next_if_eq(&']').is_some() {
+        return Ok(Json::Array(items));
+    }
+    loop {
+        items.push(parse_value(chars, depth)?);
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some(']') => return Ok(Json::Array(items)),
+            _ => return Err("Expected ',' or ']' in the array".to_string()),
+        }
+    }
+}
+
+fn parse_string(chars: &mut Input) -> Result<String, String> {
+    expect(chars, '"')?;
+    let mut text = String::new();
+    loop {
+        let ch = chars.next().ok_or("Unterminated string")?;
+        match ch {
+            '"' => return Ok(text),
+            '\\' => {
+                let escaped = match chars.next().ok_or("Unterminated string")? {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'u' => parse_unicode(chars)?,
+                    ch @ ('"' | '\\' | '/') => ch,
+                    ch => return Err(format!("Invalid escape '\\{ch}'")),
+                };
+                text.push(escaped);
+            }
+            ch => text.push(ch),
+        }
+    }
+}
+
+/// Parses the hex digits after `\u`, including the surrogate pairs
+fn parse_unicode(chars: &mut Input) -> Result<char, String> {
+    let mut code = hex_code(chars)?;
+    if (0xd800..0xdc00).contains(&code) {
+        if chars.next() != Some('\\') || chars.next() != Some('u') {
+            return Err("Unpaired surrogate in string".to_string());
+        }
+        let low = hex_code(chars)?;
+        code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+    }
+    char::from_u32(code).ok_or("Invalid character code".to_string())
+}
+
+fn hex_code(chars: &mut Input) -> Result<u32, String> {
+    let digits = chars.take(4).collect::<String>();
+    u32::from_str_radix(&digits, 16).map_err(|_| format!("Invalid character code '{digits}'"))
+}
+
+fn parse_number(chars: &mut Input) -> Result<Json, String> {
+    let mut text = String::new();
+    while let Some(ch) = chars.next_if(|ch| matches!(ch, '-' | '+' | '.' | 'e' | 'E' | '0'..='9')) {
+        text.push(ch);
+    }
+    text.parse::<f64>()
+        .map(Json::Number)
+        .map_err(|_| format!("Invalid number '{text}'"))
+}
+
+fn parse_literal(chars: &mut Input) -> Result<Json, String> {
+    let mut text = String::new();
+    while let Some(ch) = chars.next_if(|ch| ch.is_ascii_alphabetic()) {
+        text.push(ch);
+    }
+    match text.as_str() {
+        "null" => Ok(Json::Null),
+        "true" => Ok(Json::Bool(true)),
+        "false" => Ok(Json::Bool(false)),
+        _ => Err(format!("Unexpected '{text}'")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn json_parse() {
+        let text =
+            r#" {"a": [1, -2.5e1, true, null], "b": {"c": "x\"\\\n\u00e9\ud83d\ude00"}, "d": {}} "#;
+        let json = Json::parse(text).unwrap();
+        assert_eq!(
+            json.get("a"),
+            &Json::Array(vec![
+                Json::Number(1.0),
+                Json::Number(-25.0),
+                Json::Bool(true),
+                Json::Null
+            ])
+        );
+        assert_eq!(json.get("b").get("c").as_str(), Some("x\"\\\né😀"));
+        assert_eq!(json.get("d"), &Json::Object(vec![]));
+        assert_eq!(json.get("missing"), &Json::Null);
+        assert_eq!(json.get("a").as_array()[0].as_usize(), Some(1));
+    }
+
+    #[test]
+    fn json_parse_errors() {
+        for text in [
+            "",
+            "{",
+            "[1,]",
+            "{\"a\" 1}",
+            "tru",
+            "\"abc",
+            "1 2",
+            "\"\\x\"",
+        ] {
+            assert!(Json::parse(text).is_err(), "{text}");
+        }
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(Json::parse(&nested(MAX_DEPTH + 1)).is_err());
+        assert!(Json::parse(&"{\"a\":[".repeat(100_000)).is_err());
+    }
+
+    #[test]
+    fn json_serialize() {
+        let mut json = Json::object([
+            ("text", "a\"b\n\u{1}".into()),
+            ("number", 1.5.into()),
+            ("count", 3usize.into()),
+            ("items", vec![Json::Null, false.into()].into()),
+        ]);
+        json.set("count", 4usize.into());
+        json.set("extra", Json::object([]));
+        let expected =
+            r#"{"text":"a\"b\n\u0001","number":1.5,"count":4,"items":[null,false],"extra":{}}"#;
+        assert_eq!(json.to_string(), expected);
+        assert_eq!(Json::parse(expected).unwrap(), json);
+    }
+}
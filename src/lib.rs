@@ -0,0 +1,51 @@
+//! Interpreter of the BASIC-like language for embedding in the host
+//! programs. The host compiles the source into a [`Program`] and runs it
+//! with an [`Interpreter`]:
+//!
+//! ```
+//! use basic_like::{Config, Interpreter, MemoryIo, Program, Value};
+//!
+//! let program = Program::compile("total = price * count\nPRINT total").unwrap();
+//! let io = MemoryIo::default();
+//! let mut interpreter = Interpreter::new(&Config::default());
+//! interpreter.set_io(Box::new(io.clone()));
+//! interpreter.set_variable("price", Value::number(2.5));
+//! interpreter.set_variable("count", Value::number(4.0));
+//! interpreter.run(&program).unwrap();
+//! assert_eq!(interpreter.variable("total"), Some(&Value::number(10.0)));
+//! assert_eq!(io.output(), "10\n");
+//! ```
+
+pub use ast::value::{Double, Value};
+pub use clock::{Clock, FakeClock, SystemClock};
+pub use coverage::CoverageConfig;
+pub use embed::{Error, Interpreter, Program};
+pub use error::{ParseError, RuntimeError};
+pub use interpreter::{Backend, Config};
+pub use io::{FileMode, FileStream, IoBackend, MemoryIo, StdIo};
+pub use profile::ProfileConfig;
+pub use sandbox::Sandbox;
+pub use trace::{TraceConfig, TraceFilter};
+
+mod ast;
+mod builtins;
+mod clock;
+mod coverage;
+mod data;
+mod datetime;
+mod debugger;
+mod embed;
+mod error;
+mod files;
+mod format;
+mod interpreter;
+mod io;
+mod optimizer;
+mod parser;
+mod profile;
+mod random;
+mod sandbox;
+mod tokenizer;
+mod trace;
+mod variables;
+mod vm;
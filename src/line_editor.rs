@@ -0,0 +1,359 @@
+use std::{
+    io::{self, BufRead, IsTerminal, Read, Write},
+    process::{Command, Stdio},
+};
+
+/// Key press decoded from the terminal input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    /// Ctrl+U removes the text before the cursor
+    KillLine,
+    /// Ctrl+C drops the line being edited
+    Interrupt,
+    /// Ctrl+D on an empty line ends the input
+    EndOfInput,
+    Unknown,
+}
+
+/// What the editor does after a key press
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    Continue,
+    Accept(String),
+    Cancel,
+    EndOfInput,
+}
+
+/// Line editor with history for the interactive mode. On a terminal the
+/// input is read key by key, otherwise it's read line by line.
+#[derive(Default)]
+pub struct LineEditor {
+    history: Vec<String>,
+    line: Vec<char>,
+    cursor: usize,
+    /// Position in the history of the line being shown, equals to the
+    /// history length for the new line
+    history_index: usize,
+    /// New line being edited while browsing the history
+    draft: Vec<char>,
+}
+
+impl LineEditor {
+    /// Reads a line without the terminator, None at the end of input
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let mut stdout = io::stdout();
+        write!(stdout, "{prompt}")?;
+        stdout.flush()?;
+        if !io::stdin().is_terminal() {
+            let mut buffer = String::new();
+            if io::stdin().lock().read_line(&mut buffer)? == 0 {
+                return Ok(None);
+            }
+            let length = buffer.trim_end_matches(['\r', '\n']).len();
+            buffer.truncate(length);
+            return Ok(Some(buffer));
+        }
+        let _raw_mode = RawMode::enable()?;
+        self.start();
+        loop {
+            let key = read_key(&mut io::stdin().lock())?;
+            let edit = self.press(key);
+            match edit {
+                Edit::Continue => self.redraw(&mut stdout, prompt)?,
+                Edit::Accept(line) => {
+                    write!(stdout, "\r\n")?;
+                    return Ok(Some(line));
+                }
+                Edit::Cancel => {
+                    write!(stdout, "^C\r\n")?;
+                    return Ok(Some(String::new()));
+                }
+                Edit::EndOfInput => {
+                    write!(stdout, "\r\n")?;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Resets the state before editing a new line
+    fn start(&mut self) {
+        self.line.clear();
+        self.cursor = 0;
+        self.history_index = self.history.len();
+        self.draft.clear();
+    }
+
+    /// Applies the key to the line being edited
+    pub fn press(&mut self, key: Key) -> Edit {
+        match key {
+            Key::Char(ch) => {
+                self.line.insert(self.cursor, ch);
+                self.cursor += 1;
+            }
+            Key::Enter => {
+                let line = self.line.iter().collect::<String>();
+                if !line.trim().is_empty() && self.history.last() != Some(&line) {
+                    self.history.push(line.clone());
+                }
+                self.start();
+                return Edit::Accept(line);
+            }
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.line.remove(self.cursor);
+            }
+            Key::Delete if self.cursor < self.line.len() => {
+                self.line.remove(self.cursor);
+            }
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(self.line.len()),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.line.len(),
+            Key::Up if self.history_index > 0 => {
+                if self.history_index == self.history.len() {
+                    self.draft = self.line.clone();
+                }
+                self.history_index -= 1;
+                self.show(self.history[self.history_index].chars().collect());
+            }
+            Key::Down if self.history_index < self.history.len() => {
+                self.history_index += 1;
+                let line = match self.history.get(self.history_index) {
+                    Some(line) => line.chars().collect(),
+                    None => self.draft.clone(),
+                };
+                self.show(line);
+            }
+            Key::KillLine => {
+                self.line.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            Key::Interrupt => {
+                self.start();
+                return Edit::Cancel;
+            }
+            Key::EndOfInput if self.line.is_empty() => return Edit::EndOfInput,
+            _ => {}
+        }
+        Edit::Continue
+    }
+
+    /// Replaces the line with the cursor at its end
+    fn show(&mut self, line: Vec<char>) {
+        self.line = line;
+        self.cursor = self.line.len();
+    }
+
+    fn redraw(&self, output: &mut impl Write, prompt: &str) -> io::Result<()> {
+        let line = self.line.iter().collect::<String>();
+        // Return to the line start, print the line and erase the rest
+        write!(output, "\r{prompt}{line}\x1b[K")?;
+        let back = self.line.len() - self.cursor;
+        if back > 0 {
+            write!(output, "\x1b[{back}D")?;
+        }
+        output.flush()
+    }
+}
+
+/// Decodes a single key press, escape sequences are used for the arrows
+/// and the editing keys
+fn read_key(input: &mut impl Read) -> io::Result<Key> {
+    let byte = read_byte(input)?;
+    let key = match byte {
+        None | Some(0x04) => Key::EndOfInput,
+        Some(b'\r' | b'\n') => Key::Enter,
+        Some(0x7f | 0x08) => Key::Backspace,
+        Some(0x01) => Key::Home,
+        Some(0x05) => Key::End,
+        Some(0x03) => Key::Interrupt,
+        Some(0x15) => Key::KillLine,
+        Some(0x1b) => read_escape(input)?,
+        Some(byte) if byte < 0x20 => Key::Unknown,
+        Some(byte) => {
+            // Continuation bytes of UTF-8 sequence
+            let length = match byte {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            let mut bytes = vec![byte];
+            for _ in 1..length {
+                bytes.extend(read_byte(input)?);
+            }
+            match String::from_utf8(bytes).ok().and_then(|s| s.chars().next()) {
+                Some(ch) => Key::Char(ch),
+                None => Key::Unknown,
+            }
+        }
+    };
+    Ok(key)
+}
+
+fn read_escape(input: &mut impl Read) -> io::Result<Key> {
+    if !matches!(read_byte(input)?, Some(b'[' | b'O')) {
+        return Ok(Key::Unknown);
+    }
+    let mut parameter = String::new();
+    loop {
+        let Some(byte) = read_byte(input)? else {
+            return Ok(Key::Unknown);
+        };
+        let key = match byte {
+            b'0'..=b'9' | b';' => {
+                parameter.push(byte as char);
+                continue;
+            }
+            b'A' => Key::Up,
+            b'B' => Key::Down,
+            b'C' => Key::Right,
+            b'D' => Key::Left,
+            b'H' => Key::Home,
+            b'F' => Key::End,
+            b'~' => match parameter.as_str() {
+                "1" | "7" => Key::Home,
+                "4" | "8" => Key::End,
+                "3" => Key::Delete,
+                _ => Key::Unknown,
+            },
+            _ => Key::Unknown,
+        };
+        return Ok(key);
+    }
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut buffer = [0];
+    match input.read(&mut buffer)? {
+        0 => Ok(None),
+        _ => Ok(Some(buffer[0])),
+    }
+}
+
+/// Switches the terminal to the mode without line buffering and echo
+/// while the value is alive
+struct RawMode {
+    /// Terminal settings to restore
+    settings: String,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let output = stty(&["-g"])?;
+        let settings = String::from_utf8_lossy(&output).trim().to_string();
+        stty(&["-icanon", "-echo", "-isig", "-icrnl", "min", "1"])?;
+        Ok(Self { settings })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.settings]);
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("Failed to configure the terminal"));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn type_text(editor: &mut LineEditor, text: &str) {
+        for ch in text.chars() {
+            assert_eq!(editor.press(Key::Char(ch)), Edit::Continue);
+        }
+    }
+
+    #[test]
+    fn line_editor_editing() {
+        let mut editor = LineEditor::default();
+        type_text(&mut editor, "PRNT 1");
+        for key in [Key::Home, Key::Right, Key::Right] {
+            editor.press(key);
+        }
+        type_text(&mut editor, "I");
+        editor.press(Key::End);
+        editor.press(Key::Backspace);
+        type_text(&mut editor, "2");
+        editor.press(Key::Left);
+        editor.press(Key::Delete);
+        assert_eq!(editor.press(Key::Enter), Edit::Accept("PRINT ".to_string()));
+        type_text(&mut editor, "abc");
+        editor.press(Key::Left);
+        editor.press(Key::KillLine);
+        assert_eq!(editor.press(Key::Enter), Edit::Accept("c".to_string()));
+    }
+
+    #[test]
+    fn line_editor_history() {
+        let mut editor = LineEditor::default();
+        for line in ["first", "second", "second", "  "] {
+            type_text(&mut editor, line);
+            editor.press(Key::Enter);
+        }
+        type_text(&mut editor, "draft");
+        editor.press(Key::Up);
+        assert_eq!(editor.press(Key::Enter), Edit::Accept("second".to_string()));
+        editor.press(Key::Up);
+        editor.press(Key::Up);
+        editor.press(Key::Up);
+        editor.press(Key::Up);
+        assert_eq!(editor.press(Key::Enter), Edit::Accept("first".to_string()));
+        type_text(&mut editor, "new");
+        editor.press(Key::Up);
+        editor.press(Key::Down);
+        editor.press(Key::Down);
+        assert_eq!(editor.press(Key::Enter), Edit::Accept("new".to_string()));
+        assert_eq!(editor.history, ["first", "second", "first", "new"]);
+    }
+
+    #[test]
+    fn line_editor_cancel() {
+        let mut editor = LineEditor::default();
+        type_text(&mut editor, "abc");
+        assert_eq!(editor.press(Key::EndOfInput), Edit::Continue);
+        assert_eq!(editor.press(Key::Interrupt), Edit::Cancel);
+        assert_eq!(editor.press(Key::EndOfInput), Edit::EndOfInput);
+    }
+
+    #[test]
+    fn line_editor_read_key() {
+        let input = "a\u{416}\x1b[A\x1b[3~\x1bOH\r\x7f\x03";
+        let mut input = input.as_bytes();
+        let expected = [
+            Key::Char('a'),
+            Key::Char('Ж'),
+            Key::Up,
+            Key::Delete,
+            Key::Home,
+            Key::Enter,
+            Key::Backspace,
+            Key::Interrupt,
+            Key::EndOfInput,
+        ];
+        for key in expected {
+            assert_eq!(read_key(&mut input).unwrap(), key);
+        }
+    }
+}
@@ -0,0 +1,523 @@
+use std::collections::HashMap;
+
+use crate::{
+    builtins::{self, Builtin},
+    interpreter::InterpreterContext,
+    parser::{is_keyword, Parser, KEYWORDS},
+    tokenizer::{Token, TokenType, Tokenizer},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Label,
+    Variable,
+    Function,
+}
+
+/// Name in the source code. The positions are 0-based, the columns are
+/// counted in characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    /// Line numbers are normalized, so `010` and `10` have the same name
+    pub name: String,
+    pub kind: Kind,
+    /// Label declaration or assignment of the variable
+    pub definition: bool,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Occurrence {
+    fn contains(&self, line: usize, column: usize) -> bool {
+        self.line == line && self.start <= column && column <= self.end
+    }
+
+    fn is_same(&self, other: &Occurrence) -> bool {
+        self.kind == other.kind && self.name == other.name
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Names and problems found in the source code of a script
+pub struct Analysis {
+    tokens: Vec<Token>,
+    pub occurrences: Vec<Occurrence>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Types of the variables inferred from their first assignments
+    types: HashMap<String, &'static str>,
+}
+
+impl Analysis {
+    pub fn new(source: &str) -> Self {
+        let tokens = Tokenizer::new().tokenize(source);
+        let mut analysis = Self {
+            tokens,
+            occurrences: Vec::new(),
+            diagnostics: Vec::new(),
+            types: HashMap::new(),
+        };
+        analysis.collect_names();
+        analysis.check_labels();
+        analysis.check_syntax();
+        analysis
+    }
+
+    /// Name at the position, the column may be just after the name
+    pub fn occurrence_at(&self, line: usize, column: usize) -> Option<&Occurrence> {
+        self.occurrences
+            .iter()
+            .find(|occurrence| occurrence.contains(line, column))
+    }
+
+    /// Declaration of the label or the first assignment of the variable
+    pub fn definition(&self, occurrence: &Occurrence) -> Option<&Occurrence> {
+        self.occurrences
+            .iter()
+            .find(|item| item.definition && item.is_same(occurrence))
+    }
+
+    pub fn references(&self, occurrence: &Occurrence) -> Vec<&Occurrence> {
+        self.occurrences
+            .iter()
+            .filter(|item| item.is_same(occurrence))
+            .collect()
+    }
+
+    /// Inferred type of the variable: number, string or array
+    pub fn variable_type(&self, name: &str) -> &'static str {
+        if name.ends_with('$') {
+            return "string";
+        }
+        self.types.get(name).copied().unwrap_or("number")
+    }
+
+    /// Distinct names of the kind in the order of their first occurrences
+    pub fn names(&self, kind: Kind) -> Vec<&Occurrence> {
+        let mut names: Vec<&Occurrence> = Vec::new();
+        for occurrence in &self.occurrences {
+            if occurrence.kind == kind && !names.iter().any(|item| item.is_same(occurrence)) {
+                names.push(occurrence);
+            }
+        }
+        names
+    }
+
+    /// Checks if the label is a target of GOSUB
+    pub fn is_subroutine(&self, label: &str) -> bool {
+        self.occurrences.iter().any(|occurrence| {
+            occurrence.kind == Kind::Label
+                && occurrence.name == label
+                && !occurrence.definition
+                && self.previous_word(occurrence).as_deref() == Some("gosub")
+        })
+    }
+
+    /// Checks if a label is expected at the position, i.e. it follows a
+    /// jump keyword
+    pub fn expects_label(&self, line: usize, column: usize) -> bool {
+        let mut before = self
+            .tokens
+            .iter()
+            .filter(|token| token.line == line + 1 && token.column < column);
+        let mut previous = before.next_back();
+        if let Some(token) = previous {
+            let is_name = matches!(token.t_type, TokenType::Word | TokenType::Number);
+            if is_name && token.column + token.text.chars().count() >= column {
+                // The name being typed
+                previous = before.next_back();
+            }
+        }
+        previous.is_some_and(|token| {
+            token.t_type == TokenType::Word && is_jump(&token.text.to_lowercase())
+        })
+    }
+
+    fn previous_word(&self, occurrence: &Occurrence) -> Option<String> {
+        let index = self.tokens.iter().position(|token| {
+            token.line == occurrence.line + 1 && token.column == occurrence.start
+        })?;
+        let token = self.tokens.get(index.checked_sub(1)?)?;
+        Some(token.text.to_lowercase())
+    }
+
+    fn collect_names(&mut self) {
+        let mut statement_start = true;
+        // The statement assigns the variables it lists
+        let mut assigns = false;
+        // The file number of INPUT or LINE INPUT is being read
+        let mut channel = false;
+        // THEN was found on the line, so a label may be a statement
+        let mut after_then = false;
+        let mut index = 0;
+        while index < self.tokens.len() {
+            let token = &self.tokens[index].clone();
+            let next = self.tokens.get(index + 1).map(|token| token.t_type);
+            let is_line_start = index == 0 || self.tokens[index - 1].t_type == TokenType::Line;
+            let lowercase = token.text.to_lowercase();
+            let mut start = false;
+            match token.t_type {
+                TokenType::Line | TokenType::Colon => {
+                    start = true;
+                    assigns = false;
+                    channel = false;
+                    if token.t_type == TokenType::Line {
+                        after_then = false;
+                    }
+                }
+                TokenType::Label if is_keyword(&token.text) => {}
+                TokenType::Label => {
+                    self.push(token.text.clone(), Kind::Label, true, token);
+                    start = true;
+                }
+                TokenType::Number if is_line_start => {
+                    self.push(normalize(&token.text), Kind::Label, true, token);
+                    start = true;
+                }
+                TokenType::Number if statement_start && after_then => {
+                    self.push(normalize(&token.text), Kind::Label, false, token);
+                }
+                TokenType::Word if statement_start && lowercase == "data" => {
+                    // The values are literals, even if they look like names
+                    while self.tokens.get(index + 1).is_some_and(|token| {
+                        !matches!(token.t_type, TokenType::Line | TokenType::Colon)
+                    }) {
+                        index += 1;
+                    }
+                }
+                TokenType::Word if is_jump(&lowercase) => {
+                    start = lowercase == "then";
+                    after_then |= start;
+                    let target = self.tokens.get(index + 1).cloned().filter(|target| {
+                        lowercase != "then" && is_target(target, self.tokens.get(index + 2))
+                    });
+                    if let Some(target) = target {
+                        let name = match target.t_type {
+                            TokenType::Number => normalize(&target.text),
+                            _ => target.text.clone(),
+                        };
+                        self.push(name, Kind::Label, false, &target);
+                        index += 1;
+                    }
+                }
+                TokenType::Word
+                    if statement_start
+                        && after_then
+                        && is_target(token, self.tokens.get(index + 1)) =>
+                {
+                    self.push(token.text.clone(), Kind::Label, false, token);
+                }
+                TokenType::Word
+                    if statement_start
+                        && matches!(lowercase.as_str(), "input" | "read" | "line") =>
+                {
+                    assigns = true;
+                    channel = next == Some(TokenType::Hash);
+                }
+                TokenType::Word if assigns && lowercase == "input" => {
+                    // LINE INPUT
+                    channel = next == Some(TokenType::Hash);
+                }
+                TokenType::Word if is_reserved(&lowercase, statement_start) => {}
+                TokenType::Word if builtins::lookup(&token.text).is_some() => {
+                    self.push(token.text.to_uppercase(), Kind::Function, false, token);
+                }
+                TokenType::Word => {
+                    let assignment = statement_start && next == Some(TokenType::Equals);
+                    let definition = assignment || (assigns && !channel);
+                    if assignment {
+                        let value = self.tokens.get(index + 2).map(value_type);
+                        self.types
+                            .entry(token.text.clone())
+                            .or_insert(value.unwrap_or("number"));
+                    }
+                    self.push(token.text.clone(), Kind::Variable, definition, token);
+                }
+                TokenType::Comma => channel = false,
+                _ => {}
+            }
+            statement_start = start;
+            index += 1;
+        }
+    }
+
+    fn push(&mut self, name: String, kind: Kind, definition: bool, token: &Token) {
+        self.occurrences.push(Occurrence {
+            name,
+            kind,
+            definition,
+            line: token.line - 1,
+            start: token.column,
+            end: token.column + token.text.chars().count(),
+        });
+    }
+
+    fn check_labels(&mut self) {
+        let mut defined: HashMap<&str, &Occurrence> = HashMap::new();
+        let mut diagnostics = Vec::new();
+        let labels = self
+            .occurrences
+            .iter()
+            .filter(|occurrence| occurrence.kind == Kind::Label);
+        for label in labels.clone().filter(|label| label.definition) {
+            if let Some(previous) = defined.get(label.name.as_str()) {
+                let message = format!(
+                    "Label '{}' is already defined at line {}",
+                    label.name,
+                    previous.line + 1
+                );
+                diagnostics.push(Diagnostic::new(Severity::Warning, message, label));
+            } else {
+                defined.insert(&label.name, label);
+            }
+        }
+        for label in labels.filter(|label| !label.definition) {
+            if !defined.contains_key(label.name.as_str()) {
+                let message = format!("Undefined label '{}'", label.name);
+                diagnostics.push(Diagnostic::new(Severity::Error, message, label));
+            }
+        }
+        self.diagnostics.extend(diagnostics);
+    }
+
+    /// Reports the first syntax error found by the parser
+    fn check_syntax(&mut self) {
+        let mut context = InterpreterContext::default();
+        let Err(err) = Parser::new(self.tokens.clone(), &mut context).parse() else {
+            return;
+        };
+        let length = self
+            .tokens
+            .iter()
+            .find(|token| token.line == err.line && token.column == err.column)
+            .map_or(1, |token| token.text.chars().count().max(1));
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            message: err.message,
+            line: err.line.saturating_sub(1),
+            start: err.column,
+            end: err.column + length,
+        });
+    }
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, message: String, occurrence: &Occurrence) -> Self {
+        Self {
+            severity,
+            message,
+            line: occurrence.line,
+            start: occurrence.start,
+            end: occurrence.end,
+        }
+    }
+}
+
+/// Keywords and built-in functions which may be completed
+pub fn completions() -> impl Iterator<Item = (String, Option<&'static Builtin>)> {
+    let keywords = KEYWORDS
+        .iter()
+        .filter(|keyword| builtins::lookup(keyword).is_none())
+        .map(|keyword| (keyword.to_uppercase(), None));
+    let functions = builtins::all()
+        .iter()
+        .map(|builtin| (builtin.name.to_string(), Some(builtin)));
+    keywords.chain(functions)
+}
+
+/// Keywords followed by a label
+fn is_jump(keyword: &str) -> bool {
+    matches!(keyword, "goto" | "gosub" | "restore" | "then")
+}
+
+/// Checks if the word is a keyword at its position. `MID$` starts a
+/// statement, but it's a function inside of the expressions.
+fn is_reserved(word: &str, statement_start: bool) -> bool {
+    KEYWORDS.contains(&word) && (statement_start || builtins::lookup(word).is_none())
+}
+
+/// Checks if the token is a jump target ending the statement, the same
+/// way the parser does
+fn is_target(token: &Token, next: Option<&Token>) -> bool {
+    match token.t_type {
+        TokenType::Number => true,
+        TokenType::Word => {
+            !is_keyword(&token.text)
+                && next.is_none_or(|next| {
+                    matches!(
+                        next.t_type,
+                        TokenType::Line | TokenType::Eof | TokenType::Colon
+                    )
+                })
+        }
+        _ => false,
+    }
+}
+
+/// Type of the value starting with the token
+fn value_type(token: &Token) -> &'static str {
+    match token.t_type {
+        TokenType::String => "string",
+        TokenType::Word if token.text.eq_ignore_ascii_case("split") => "array",
+        TokenType::Word if token.text.ends_with('$') => "string",
+        _ => "number",
+    }
+}
+
+fn normalize(number: &str) -> String {
+    number
+        .parse::<u32>()
+        .map_or(number.to_string(), |number| number.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: &str = "\
+10 INPUT \"Name\"; name$
+count = 0
+loop: count = count + 1
+IF count < 3 THEN loop
+GOSUB greet
+parts = SPLIT(name$, \" \")
+GOTO 99
+greet: PRINT LEFT$(name$, count)
+RETURN
+";
+
+    fn names(analysis: &Analysis, kind: Kind) -> Vec<(String, bool, usize, usize)> {
+        analysis
+            .occurrences
+            .iter()
+            .filter(|occurrence| occurrence.kind == kind)
+            .map(|item| (item.name.clone(), item.definition, item.line, item.start))
+            .collect()
+    }
+
+    #[test]
+    fn analysis_names() {
+        let analysis = Analysis::new(SOURCE);
+        assert_eq!(
+            names(&analysis, Kind::Label),
+            [
+                ("10".to_string(), true, 0, 0),
+                ("loop".to_string(), true, 2, 0),
+                ("loop".to_string(), false, 3, 18),
+                ("greet".to_string(), false, 4, 6),
+                ("99".to_string(), false, 6, 5),
+                ("greet".to_string(), true, 7, 0),
+            ]
+        );
+        assert_eq!(
+            names(&analysis, Kind::Variable),
+            [
+                ("name$".to_string(), true, 0, 17),
+                ("count".to_string(), true, 1, 0),
+                ("count".to_string(), true, 2, 6),
+                ("count".to_string(), false, 2, 14),
+                ("count".to_string(), false, 3, 3),
+                ("parts".to_string(), true, 5, 0),
+                ("name$".to_string(), false, 5, 14),
+                ("name$".to_string(), false, 7, 19),
+                ("count".to_string(), false, 7, 26),
+            ]
+        );
+        assert_eq!(
+            names(&analysis, Kind::Function),
+            [
+                ("SPLIT".to_string(), false, 5, 8),
+                ("LEFT$".to_string(), false, 7, 13)
+            ]
+        );
+        assert_eq!(analysis.variable_type("name$"), "string");
+        assert_eq!(analysis.variable_type("count"), "number");
+        assert_eq!(analysis.variable_type("parts"), "array");
+        assert!(analysis.is_subroutine("greet"));
+        assert!(!analysis.is_subroutine("loop"));
+    }
+
+    #[test]
+    fn analysis_lookup() {
+        let analysis = Analysis::new(SOURCE);
+        let occurrence = analysis.occurrence_at(3, 22).unwrap();
+        assert_eq!(occurrence.name, "loop");
+        let definition = analysis.definition(occurrence).unwrap();
+        assert_eq!((definition.line, definition.start), (2, 0));
+        assert_eq!(analysis.references(occurrence).len(), 2);
+        let occurrence = analysis.occurrence_at(7, 30).unwrap();
+        let definition = analysis.definition(occurrence).unwrap();
+        assert_eq!((definition.line, definition.start), (1, 0));
+        assert_eq!(analysis.references(occurrence).len(), 5);
+        assert!(analysis.occurrence_at(4, 2).is_none());
+        assert_eq!(analysis.names(Kind::Variable).len(), 3);
+        assert!(analysis.expects_label(6, 5));
+        assert!(analysis.expects_label(6, 7));
+        assert!(!analysis.expects_label(5, 8));
+    }
+
+    #[test]
+    fn analysis_skips_data_and_file_numbers() {
+        let source = "DATA one, two\nINPUT #n, a, b$\nLINE INPUT #2, c$\nREAD x: RESTORE\n";
+        let analysis = Analysis::new(source);
+        let variables = names(&analysis, Kind::Variable)
+            .into_iter()
+            .map(|(name, definition, ..)| (name, definition))
+            .collect::<Vec<_>>();
+        let expected = [
+            ("n", false),
+            ("a", true),
+            ("b$", true),
+            ("c$", true),
+            ("x", true),
+        ]
+        .map(|(name, definition)| (name.to_string(), definition));
+        assert_eq!(variables, expected);
+        assert!(names(&analysis, Kind::Label).is_empty());
+        assert!(analysis.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn analysis_diagnostics() {
+        let source = "start: PRINT 1\nstart: GOTO finish\nIF 1 THEN PRINT 2: 010\nPRINT (1\n";
+        let analysis = Analysis::new(source);
+        let diagnostics = analysis
+            .diagnostics
+            .iter()
+            .map(|item| (item.severity, item.message.as_str(), item.line, item.start))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diagnostics,
+            [
+                (
+                    Severity::Warning,
+                    "Label 'start' is already defined at line 1",
+                    1,
+                    0
+                ),
+                (Severity::Error, "Undefined label 'finish'", 1, 12),
+                (Severity::Error, "Undefined label '10'", 2, 19),
+                (
+                    Severity::Error,
+                    "Expected CloseParenthesis, found end of line",
+                    3,
+                    8
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,681 @@
+mod analysis;
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use analysis::{completions, Analysis, Kind, Occurrence, Severity};
+
+use crate::{
+    builtins,
+    json::Json,
+    parser::KEYWORDS,
+    protocol::{read_message, write_message},
+    tokenizer::{TokenType, Tokenizer},
+};
+
+const INVALID_PARAMS: i32 = -32602;
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// Error of the request sent back to the client
+struct ResponseError {
+    code: i32,
+    message: String,
+}
+
+impl ResponseError {
+    fn invalid_params(message: &str) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: message.to_string(),
+        }
+    }
+}
+
+type Response = Result<Json, ResponseError>;
+
+/// Open script with its analysis
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+impl Document {
+    fn new(text: String) -> Self {
+        let analysis = Analysis::new(&text);
+        Self { text, analysis }
+    }
+
+    fn line(&self, line: usize) -> &str {
+        self.text.split('\n').nth(line).unwrap_or_default()
+    }
+
+    /// Converts the position in UTF-16 code units the protocol uses to
+    /// the column in characters
+    fn column(&self, line: usize, character: usize) -> usize {
+        let mut units = 0;
+        for (column, ch) in self.line(line).chars().enumerate() {
+            if units >= character {
+                return column;
+            }
+            units += ch.len_utf16();
+        }
+        self.line(line).chars().count()
+    }
+
+    fn character(&self, line: usize, column: usize) -> usize {
+        self.line(line)
+            .chars()
+            .take(column)
+            .map(char::len_utf16)
+            .sum()
+    }
+
+    fn range(&self, line: usize, start: usize, end: usize) -> Json {
+        let position = |column: usize| {
+            Json::object([
+                ("line", line.into()),
+                ("character", self.character(line, column).into()),
+            ])
+        };
+        Json::object([("start", position(start)), ("end", position(end))])
+    }
+
+    fn occurrence_range(&self, occurrence: &Occurrence) -> Json {
+        self.range(occurrence.line, occurrence.start, occurrence.end)
+    }
+
+    /// Name at the position given by the request
+    fn occurrence(&self, params: &Json) -> Option<&Occurrence> {
+        let position = params.get("position");
+        let line = position.get("line").as_usize()?;
+        let character = position.get("character").as_usize()?;
+        self.analysis
+            .occurrence_at(line, self.column(line, character))
+    }
+}
+
+struct Server {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    /// Open documents by their URIs
+    documents: HashMap<String, Document>,
+}
+
+impl Server {
+    fn run(&mut self) -> io::Result<()> {
+        while let Some(message) = read_message(&mut self.input)? {
+            let method = message.get("method").as_str().unwrap_or_default();
+            let params = message.get("params");
+            let id = message.get("id");
+            if method == "exit" {
+                break;
+            }
+            if id == &Json::Null {
+                self.notification(method, params)?;
+                continue;
+            }
+            if method.is_empty() {
+                // Response of the client, the server sends no requests
+                continue;
+            }
+            let mut response = Json::object([("jsonrpc", "2.0".into()), ("id", id.clone())]);
+            match self.request(method, params) {
+                Ok(result) => response.set("result", result),
+                Err(err) => {
+                    let error = Json::object([
+                        ("code", f64::from(err.code).into()),
+                        ("message", err.message.into()),
+                    ]);
+                    response.set("error", error);
+                }
+            }
+            write_message(&mut self.output, &response)?;
+        }
+        Ok(())
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> io::Result<()> {
+        let uri = params.get("textDocument").get("uri").as_str();
+        let Some(uri) = uri.map(str::to_string) else {
+            return Ok(());
+        };
+        match method {
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").get("text").as_str();
+                let document = Document::new(text.unwrap_or_default().to_string());
+                self.documents.insert(uri.clone(), document);
+            }
+            "textDocument/didChange" => {
+                // The documents are synchronized in full
+                let Some(text) = params
+                    .get("contentChanges")
+                    .as_array()
+                    .last()
+                    .and_then(|change| change.get("text").as_str())
+                else {
+                    return Ok(());
+                };
+                self.documents
+                    .insert(uri.clone(), Document::new(text.to_string()));
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+            }
+            _ => return Ok(()),
+        }
+        self.publish_diagnostics(&uri)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let diagnostics = self.documents.get(uri).map_or(Vec::new(), |document| {
+            document
+                .analysis
+                .diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    let severity = match diagnostic.severity {
+                        Severity::Error => 1,
+                        Severity::Warning => 2,
+                    };
+                    let range = document.range(diagnostic.line, diagnostic.start, diagnostic.end);
+                    Json::object([
+                        ("range", range),
+                        ("severity", severity.into()),
+                        ("source", "basic-like".into()),
+                        ("message", diagnostic.message.as_str().into()),
+                    ])
+                })
+                .collect()
+        });
+        let params = Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())]);
+        let message = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            ("params", params),
+        ]);
+        write_message(&mut self.output, &message)
+    }
+
+    fn request(&self, method: &str, params: &Json) -> Response {
+        if method == "initialize" {
+            return Ok(Json::object([
+                ("capabilities", capabilities()),
+                (
+                    "serverInfo",
+                    Json::object([("name", env!("CARGO_PKG_NAME").into())]),
+                ),
+            ]));
+        }
+        if method == "shutdown" {
+            return Ok(Json::Null);
+        }
+        let uri = params.get("textDocument").get("uri").as_str();
+        let uri = uri.ok_or(ResponseError::invalid_params("Missing document"))?;
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or(ResponseError::invalid_params("Unknown document"))?;
+        match method {
+            "textDocument/definition" => Ok(definition(uri, document, params)),
+            "textDocument/references" => Ok(references(uri, document, params)),
+            "textDocument/hover" => Ok(hover(document, params)),
+            "textDocument/completion" => Ok(completion(document, params)),
+            "textDocument/documentSymbol" => Ok(symbols(document)),
+            "textDocument/rename" => rename(uri, document, params),
+            _ => Err(ResponseError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Unsupported method '{method}'"),
+            }),
+        }
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        ("textDocumentSync", 1usize.into()),
+        ("definitionProvider", true.into()),
+        ("referencesProvider", true.into()),
+        ("hoverProvider", true.into()),
+        ("completionProvider", Json::object([])),
+        ("documentSymbolProvider", true.into()),
+        ("renameProvider", true.into()),
+    ])
+}
+
+fn location(uri: &str, document: &Document, occurrence: &Occurrence) -> Json {
+    Json::object([
+        ("uri", uri.into()),
+        ("range", document.occurrence_range(occurrence)),
+    ])
+}
+
+fn definition(uri: &str, document: &Document, params: &Json) -> Json {
+    document
+        .occurrence(params)
+        .and_then(|occurrence| document.analysis.definition(occurrence))
+        .map_or(Json::Null, |definition| location(uri, document, definition))
+}
+
+fn references(uri: &str, document: &Document, params: &Json) -> Json {
+    let Some(occurrence) = document.occurrence(params) else {
+        return Json::Null;
+    };
+    let declaration = params.get("context").get("includeDeclaration").as_bool() == Some(true);
+    let definition = document.analysis.definition(occurrence);
+    document
+        .analysis
+        .references(occurrence)
+        .into_iter()
+        .filter(|item| declaration || Some(*item) != definition)
+        .map(|item| location(uri, document, item))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn hover(document: &Document, params: &Json) -> Json {
+    let Some(occurrence) = document.occurrence(params) else {
+        return Json::Null;
+    };
+    let analysis = &document.analysis;
+    let text = match occurrence.kind {
+        Kind::Variable => {
+            let variable_type = analysis.variable_type(&occurrence.name);
+            format!("```\n(variable) {}: {variable_type}\n```", occurrence.name)
+        }
+        Kind::Function => {
+            let Some(builtin) = builtins::lookup(&occurrence.name) else {
+                return Json::Null;
+            };
+            format!("```\n(function) {}\n```", builtin.signature())
+        }
+        Kind::Label => {
+            let kind = if analysis.is_subroutine(&occurrence.name) {
+                "subroutine"
+            } else {
+                "label"
+            };
+            let place = match analysis.definition(occurrence) {
+                Some(definition) => format!("defined at line {}", definition.line + 1),
+                None => "not defined".to_string(),
+            };
+            format!("```\n({kind}) {}\n```\n{place}", occurrence.name)
+        }
+    };
+    let contents = Json::object([("kind", "markdown".into()), ("value", text.into())]);
+    Json::object([
+        ("contents", contents),
+        ("range", document.occurrence_range(occurrence)),
+    ])
+}
+
+fn completion(document: &Document, params: &Json) -> Json {
+    let position = params.get("position");
+    let line = position.get("line").as_usize().unwrap_or_default();
+    let character = position.get("character").as_usize().unwrap_or_default();
+    let analysis = &document.analysis;
+    let item = |label: &str, kind: usize, detail: &str| {
+        Json::object([
+            ("label", label.into()),
+            ("kind", kind.into()),
+            ("detail", detail.into()),
+        ])
+    };
+    // The numbers are completion item kinds of the protocol
+    let labels = analysis
+        .names(Kind::Label)
+        .into_iter()
+        .filter(|label| analysis.definition(label).is_some())
+        .map(|label| item(&label.name, 18, "label"));
+    if analysis.expects_label(line, document.column(line, character)) {
+        return labels.collect::<Vec<_>>().into();
+    }
+    let names = completions().map(|(name, builtin)| match builtin {
+        Some(builtin) => item(&name, 3, &builtin.signature()),
+        None => item(&name, 14, "keyword"),
+    });
+    let variables = analysis.names(Kind::Variable).into_iter().map(|variable| {
+        let variable_type = analysis.variable_type(&variable.name);
+        item(&variable.name, 6, variable_type)
+    });
+    names
+        .chain(variables)
+        .chain(labels)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Labels and variables of the document. The line numbers are listed only
+/// if they are jumped to.
+fn symbols(document: &Document) -> Json {
+    let analysis = &document.analysis;
+    let mut symbols = Vec::new();
+    for label in analysis.names(Kind::Label) {
+        let Some(definition) = analysis.definition(label) else {
+            continue;
+        };
+        let is_number = label.name.starts_with(|ch: char| ch.is_ascii_digit());
+        if is_number && analysis.references(label).len() == 1 {
+            continue;
+        }
+        // Symbol kinds of the protocol
+        let (kind, detail) = if analysis.is_subroutine(&label.name) {
+            (12, "subroutine")
+        } else {
+            (20, "label")
+        };
+        symbols.push((definition, kind, detail));
+    }
+    for variable in analysis.names(Kind::Variable) {
+        let definition = analysis.definition(variable).unwrap_or(variable);
+        let detail = analysis.variable_type(&variable.name);
+        let kind = if detail == "array" { 18 } else { 13 };
+        symbols.push((definition, kind, detail));
+    }
+    symbols.sort_by_key(|(occurrence, ..)| (occurrence.line, occurrence.start));
+    symbols
+        .into_iter()
+        .map(|(occurrence, kind, detail)| {
+            let range = document.occurrence_range(occurrence);
+            Json::object([
+                ("name", occurrence.name.as_str().into()),
+                ("detail", detail.into()),
+                ("kind", (kind as usize).into()),
+                ("range", range.clone()),
+                ("selectionRange", range),
+            ])
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn rename(uri: &str, document: &Document, params: &Json) -> Response {
+    let invalid = ResponseError::invalid_params;
+    let occurrence = document
+        .occurrence(params)
+        .ok_or(invalid("There is nothing to rename"))?;
+    let name = params.get("newName").as_str().unwrap_or_default();
+    match occurrence.kind {
+        Kind::Function => return Err(invalid("Built-in functions can't be renamed")),
+        Kind::Label if occurrence.name.starts_with(|ch: char| ch.is_ascii_digit()) => {
+            return Err(invalid("Line numbers can't be renamed"));
+        }
+        _ => {}
+    }
+    let tokens = Tokenizer::new().tokenize(name);
+    if !matches!(tokens.as_slice(), [token] if token.t_type == TokenType::Word && token.text == name)
+    {
+        return Err(invalid(&format!("'{name}' isn't a valid name")));
+    }
+    if KEYWORDS.contains(&name.to_lowercase().as_str()) || builtins::lookup(name).is_some() {
+        return Err(invalid(&format!("'{name}' is a reserved word")));
+    }
+    if name.ends_with('$') != occurrence.name.ends_with('$') {
+        return Err(invalid("The new name must keep the type of the old one"));
+    }
+    let analysis = &document.analysis;
+    let is_used = analysis
+        .names(occurrence.kind)
+        .iter()
+        .any(|item| item.name == name && item.name != occurrence.name);
+    if is_used {
+        return Err(invalid(&format!("'{name}' is already used")));
+    }
+    let edits = analysis
+        .references(occurrence)
+        .into_iter()
+        .map(|item| {
+            Json::object([
+                ("range", document.occurrence_range(item)),
+                ("newText", name.into()),
+            ])
+        })
+        .collect::<Vec<_>>();
+    let mut changes = Json::object([]);
+    changes.set(uri, edits.into());
+    Ok(Json::object([("changes", changes)]))
+}
+
+/// Serves the language server protocol for the scripts the client opens.
+/// The documents are analyzed on each change, the diagnostics are
+/// published back.
+pub fn run_language_server(input: Box<dyn BufRead>, output: Box<dyn Write>) -> io::Result<()> {
+    let mut server = Server {
+        input,
+        output,
+        documents: HashMap::new(),
+    };
+    server.run()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dap::test::SharedOutput;
+
+    use super::*;
+
+    const URI: &str = "file:///test.bas";
+
+    fn message(id: Option<usize>, method: &str, params: Json) -> Json {
+        let mut message = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", method.into()),
+            ("params", params),
+        ]);
+        if let Some(id) = id {
+            message.set("id", id.into());
+        }
+        message
+    }
+
+    /// Request about the position in the test document
+    fn at(id: usize, method: &str, line: usize, character: usize) -> Json {
+        let params = Json::object([
+            ("textDocument", Json::object([("uri", URI.into())])),
+            (
+                "position",
+                Json::object([("line", line.into()), ("character", character.into())]),
+            ),
+        ]);
+        message(Some(id), method, params)
+    }
+
+    fn session(messages: Vec<Json>) -> Vec<Json> {
+        let mut input = Vec::new();
+        for message in messages {
+            write_message(&mut input, &message).unwrap();
+        }
+        let output = SharedOutput::default();
+        let input = Box::new(io::Cursor::new(input));
+        run_language_server(input, Box::new(output.clone())).unwrap();
+        let output = output.0.borrow().clone();
+        let mut output = output.as_slice();
+        let mut messages = Vec::new();
+        while let Some(message) = read_message(&mut output).unwrap() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn open(text: &str) -> Json {
+        let document = Json::object([("uri", URI.into()), ("text", text.into())]);
+        message(
+            None,
+            "textDocument/didOpen",
+            Json::object([("textDocument", document)]),
+        )
+    }
+
+    fn result(messages: &[Json], id: usize) -> &Json {
+        let response = messages
+            .iter()
+            .find(|message| message.get("id").as_usize() == Some(id))
+            .unwrap();
+        response.get("result")
+    }
+
+    fn start(range: &Json) -> (usize, usize) {
+        let start = range.get("start");
+        (
+            start.get("line").as_usize().unwrap(),
+            start.get("character").as_usize().unwrap(),
+        )
+    }
+
+    #[test]
+    fn lsp_diagnostics() {
+        let change = Json::object([
+            ("textDocument", Json::object([("uri", URI.into())])),
+            (
+                "contentChanges",
+                vec![Json::object([("text", "GOTO done\ndone:".into())])].into(),
+            ),
+        ]);
+        let messages = session(vec![
+            message(Some(1), "initialize", Json::object([])),
+            message(None, "initialized", Json::object([])),
+            open("a$ = \"😀\": GOTO done\nPRINT (1\n"),
+            message(None, "textDocument/didChange", change),
+            message(Some(2), "shutdown", Json::Null),
+            message(None, "exit", Json::Null),
+            message(Some(3), "shutdown", Json::Null),
+        ]);
+        assert_eq!(messages.len(), 4);
+        let capabilities = result(&messages, 1).get("capabilities");
+        assert_eq!(capabilities.get("renameProvider"), &Json::Bool(true));
+        let diagnostics = messages[1].get("params").get("diagnostics").as_array();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].get("message").as_str(),
+            Some("Undefined label 'done'")
+        );
+        assert_eq!(start(diagnostics[0].get("range")), (0, 16));
+        assert_eq!(diagnostics[0].get("severity").as_usize(), Some(1));
+        assert_eq!(
+            diagnostics[1].get("message").as_str(),
+            Some("Expected CloseParenthesis, found end of line")
+        );
+        assert_eq!(start(diagnostics[1].get("range")), (1, 8));
+        let diagnostics = messages[2].get("params").get("diagnostics");
+        assert_eq!(diagnostics, &Json::Array(vec![]));
+        assert_eq!(result(&messages, 2), &Json::Null);
+    }
+
+    #[test]
+    fn lsp_navigation() {
+        let source = "name$ = \"😀\": n = LEN(name$)\nGOSUB show\nshow: PRINT name$; n\n";
+        let mut params = at(4, "", 2, 13).get("params").clone();
+        let context = Json::object([("includeDeclaration", false.into())]);
+        params.set("context", context);
+        let references = message(Some(4), "textDocument/references", params);
+        let messages = session(vec![
+            open(source),
+            at(1, "textDocument/definition", 1, 8),
+            at(2, "textDocument/definition", 2, 20),
+            at(3, "textDocument/definition", 0, 19),
+            references,
+            at(5, "textDocument/hover", 0, 1),
+            at(6, "textDocument/hover", 0, 19),
+            at(7, "textDocument/hover", 1, 7),
+            at(8, "textDocument/documentSymbol", 0, 0),
+            at(9, "textDocument/hover", 1, 0),
+        ]);
+        assert_eq!(start(result(&messages, 1).get("range")), (2, 0));
+        assert_eq!(start(result(&messages, 2).get("range")), (0, 14));
+        assert_eq!(result(&messages, 3), &Json::Null);
+        let references = result(&messages, 4)
+            .as_array()
+            .iter()
+            .map(|location| start(location.get("range")))
+            .collect::<Vec<_>>();
+        assert_eq!(references, [(0, 22), (2, 12)]);
+        let hover = |id: usize| {
+            let contents = result(&messages, id).get("contents");
+            contents.get("value").as_str().unwrap().to_string()
+        };
+        assert_eq!(hover(5), "```\n(variable) name$: string\n```");
+        assert_eq!(hover(6), "```\n(function) LEN(text$)\n```");
+        assert_eq!(hover(7), "```\n(subroutine) show\n```\ndefined at line 3");
+        let symbols = result(&messages, 8)
+            .as_array()
+            .iter()
+            .map(|symbol| {
+                let name = symbol.get("name").as_str().unwrap().to_string();
+                (name, symbol.get("kind").as_usize().unwrap())
+            })
+            .collect::<Vec<_>>();
+        let expected =
+            [("name$", 13), ("n", 13), ("show", 12)].map(|(name, kind)| (name.to_string(), kind));
+        assert_eq!(symbols, expected);
+        assert_eq!(result(&messages, 9), &Json::Null);
+    }
+
+    #[test]
+    fn lsp_completion_and_rename() {
+        let source = "count = 1\nloop: count = count + 1\nIF count < 3 THEN \nGOTO loop\n";
+        let rename = |id: usize, line: usize, character: usize, name: &str| {
+            let mut params = at(id, "", line, character).get("params").clone();
+            params.set("newName", name.into());
+            message(Some(id), "textDocument/rename", params)
+        };
+        let messages = session(vec![
+            open(source),
+            at(1, "textDocument/completion", 3, 6),
+            at(2, "textDocument/completion", 2, 18),
+            at(3, "textDocument/completion", 1, 6),
+            rename(4, 1, 8, "total"),
+            rename(5, 1, 0, "again"),
+            rename(6, 1, 8, "name$"),
+            rename(7, 1, 8, "print"),
+            rename(8, 1, 8, "a b"),
+            rename(9, 0, 8, "x"),
+            at(10, "textDocument/unknown", 0, 0),
+        ]);
+        let labels = |id: usize| {
+            result(&messages, id)
+                .as_array()
+                .iter()
+                .map(|item| item.get("label").as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(labels(1), ["loop"]);
+        assert_eq!(labels(2), ["loop"]);
+        let items = labels(3);
+        for label in ["PRINT", "THEN", "LEFT$", "MID$", "count", "loop"] {
+            assert!(items.contains(&label.to_string()), "{label}");
+        }
+        assert_eq!(items.iter().filter(|item| *item == "MID$").count(), 1);
+        let edits = result(&messages, 4).get("changes").get(URI).as_array();
+        assert_eq!(edits.len(), 4);
+        assert!(edits
+            .iter()
+            .all(|edit| edit.get("newText").as_str() == Some("total")));
+        let edits = result(&messages, 5).get("changes").get(URI).as_array();
+        let ranges = edits
+            .iter()
+            .map(|edit| start(edit.get("range")))
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, [(1, 0), (3, 5)]);
+        let error = |id: usize| {
+            let response = messages
+                .iter()
+                .find(|message| message.get("id").as_usize() == Some(id))
+                .unwrap();
+            let error = response.get("error");
+            let code = error.get("code").as_f64().unwrap();
+            (code, error.get("message").as_str().unwrap().to_string())
+        };
+        assert_eq!(
+            error(6),
+            (
+                -32602.0,
+                "The new name must keep the type of the old one".into()
+            )
+        );
+        assert_eq!(error(7), (-32602.0, "'print' is a reserved word".into()));
+        assert_eq!(error(8), (-32602.0, "'a b' isn't a valid name".into()));
+        assert_eq!(error(9), (-32602.0, "There is nothing to rename".into()));
+        assert_eq!(error(10).0, -32601.0);
+    }
+}
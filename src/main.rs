@@ -1,33 +1,177 @@
 use std::env;
-use std::fs::read_to_string;
+use std::fs::{self, read_to_string};
+use std::io::{stdin, stdout};
+use std::process::ExitCode;
 
-use interpreter::interpret;
+use cli::parse_options;
+use dap::run_dap_server;
+use embed::{Interpreter, Program};
+use interpreter::Config;
+use io::StdIo;
+use lsp::run_language_server;
+use repl::run_repl;
 
 mod ast;
+mod builtins;
+mod cli;
+// The parts of the embedding API the binary doesn't use are checked by
+// the library
+#[allow(dead_code)]
+mod clock;
+mod coverage;
+mod dap;
+mod data;
+mod datetime;
+mod debugger;
+#[allow(dead_code)]
+mod embed;
+mod error;
+mod files;
+mod format;
 mod interpreter;
+#[allow(dead_code)]
+mod io;
+mod json;
+mod line_editor;
+mod lsp;
+mod optimizer;
 mod parser;
+mod profile;
+mod program;
+mod protocol;
+mod random;
+mod repl;
+mod sandbox;
 mod tokenizer;
+mod trace;
+mod variables;
+mod vm;
 
-fn main() {
-    let mut args = env::args();
-    let Some(input_file) = args.nth(1) else {
-        show_usage();
-        return;
+fn main() -> ExitCode {
+    let mut options = match parse_options(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}");
+            show_usage();
+            return ExitCode::FAILURE;
+        }
+    };
+    if options.dap {
+        let input = Box::new(stdin().lock());
+        let output = Box::new(stdout());
+        if let Err(err) = run_dap_server(input, output, Box::new(StdIo), &options.config) {
+            eprintln!("Debug adapter failed: {err}");
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+    if options.lsp {
+        let input = Box::new(stdin().lock());
+        let output = Box::new(stdout());
+        if let Err(err) = run_language_server(input, output) {
+            eprintln!("Language server failed: {err}");
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+    let Some(input_file) = options.script else {
+        run_repl(&options.config);
+        return ExitCode::SUCCESS;
     };
-    let result = read_to_string(input_file);
+    let result = read_to_string(&input_file);
+    options.config.coverage.source_file = Some(input_file);
     match result {
         Result::Err(err) => {
-            println!("Failed to load input file with error {err:?}")
+            eprintln!("Failed to load input file with error {err:?}");
+            ExitCode::FAILURE
+        }
+        Result::Ok(source) => run_script(&source, &options.config),
+    }
+}
+
+/// Runs the script printing the errors and the reports the options ask
+/// for to stderr. Fails if the script doesn't parse or stops with an
+/// error.
+fn run_script(source: &str, config: &Config) -> ExitCode {
+    let program = match Program::compile(source) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
         }
-        Result::Ok(source) => interpret(&source),
+    };
+    let mut interpreter = Interpreter::new(config);
+    let result = interpreter.run(&program);
+    if let Err(err) = &result {
+        eprintln!("{err}");
+    }
+    if let Some(report) = interpreter.profile_report() {
+        eprint!("{report}");
+    }
+    if let (Some(path), Some(stacks)) = (&config.profile.stacks_file, interpreter.profile_stacks())
+    {
+        if let Err(err) = fs::write(path, stacks) {
+            eprintln!("Failed to write the profile stacks: {err}");
+        }
+    }
+    if let Some(report) = interpreter.coverage_report(&program) {
+        eprint!("{report}");
+    }
+    let source_file = config.coverage.source_file.as_deref().unwrap_or_default();
+    if let (Some(path), Some(lcov)) = (
+        &config.coverage.lcov_file,
+        interpreter.coverage_lcov(source_file),
+    ) {
+        if let Err(err) = fs::write(path, lcov) {
+            eprintln!("Failed to write the coverage: {err}");
+        }
+    }
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(_) => ExitCode::FAILURE,
     }
 }
 
 fn show_usage() {
-    println!(
+    eprintln!(
         r"Usage:
-        basic-like <script>
+        basic-like [options] <script>
+
+        <script> input file name with source code, the interactive mode
+                 is started if it's omitted
 
-        <script> input file name with source code"
+Options:
+        --seed <integer>         fixed seed of the random numbers generator
+        --allow-dir <dir>        allow file access in the directory only,
+                                 may be repeated
+        --read-only              don't allow scripts to write files
+        --max-file-size <bytes>  size limit of files the scripts access
+        --no-files               don't allow scripts to access files
+        --debug                  run the script in the step debugger
+        --trace                  log the executed statements to stderr as
+                                 if the script started with TRON
+        --trace-values           log the values the statements assign
+        --trace-file <file>      write the trace to the file
+        --trace-filter <items>   trace only the comma separated labels and
+                                 source line ranges, e.g. loop,10-20
+        --profile                print the time spent in the statements and
+                                 the labeled blocks to stderr at the end
+        --profile-stacks <file>  profile and write the collapsed stacks for
+                                 the flame graph tools to the file
+        --coverage               print the lines and the IF branches the
+                                 script has run to stderr at the end
+        --lcov <file>            record the coverage and write it to the
+                                 file in the LCOV format
+        --backend <name>         execute the script with the bytecode VM
+                                 (bytecode, the default) or by walking the
+                                 syntax tree (ast)
+        --no-optimize            run the statements as they are written,
+                                 without folding the constants and
+                                 removing the unreachable ones
+        --dap                    serve the debug adapter protocol on the
+                                 standard input and output, the script is
+                                 given by the launch request
+        --lsp                    serve the language server protocol on the
+                                 standard input and output"
     );
 }
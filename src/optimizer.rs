@@ -0,0 +1,263 @@
+use std::mem;
+
+use crate::ast::{statement::Statement, value::Value, Expression};
+
+/// Folds the constant subexpressions of the statements
+pub fn fold_constants(statements: &mut [Statement]) {
+    for statement in statements {
+        for expression in statement.expressions_mut() {
+            let placeholder: Box<dyn Expression> = Box::new(Value::number(0.0));
+            *expression = mem::replace(expression, placeholder).optimize();
+        }
+    }
+}
+
+/// Jumps to a GOTO go to its target directly
+pub fn thread_jumps(statements: &mut [Statement]) {
+    for index in 0..statements.len() {
+        let Some(target) = statements[index].jump_target_mut().copied() else {
+            continue;
+        };
+        let destination = destination(statements, target);
+        if let Some(target) = statements[index].jump_target_mut() {
+            *target = destination;
+        }
+    }
+}
+
+/// Position the chain of GOTOs starting at the target ends at. A GOTO
+/// loop is followed at most once around.
+fn destination(statements: &[Statement], mut target: usize) -> usize {
+    for _ in 0..statements.len() {
+        match statements.get(target) {
+            Some(Statement::Goto {
+                target: Some(next), ..
+            }) => target = *next,
+            _ => break,
+        }
+    }
+    target
+}
+
+/// Marks the statements after an unconditional GOTO, up to the next one
+/// a label or an IF leads to. Such statements never run, but DATA is kept
+/// since RESTORE positions its values.
+pub fn unreachable(statements: &[Statement], labels: impl Iterator<Item = usize>) -> Vec<bool> {
+    let mut entries = vec![false; statements.len() + 1];
+    let ends = statements.iter().filter_map(|statement| match statement {
+        Statement::If { end, .. } => Some(*end),
+        _ => None,
+    });
+    for position in labels.chain(ends) {
+        if let Some(entry) = entries.get_mut(position) {
+            *entry = true;
+        }
+    }
+    let mut reachable = true;
+    let mut removed = Vec::with_capacity(statements.len());
+    for (statement, entry) in statements.iter().zip(entries) {
+        reachable |= entry;
+        removed.push(!reachable && !matches!(statement, Statement::Data));
+        if matches!(statement, Statement::Goto { .. }) {
+            reachable = false;
+        }
+    }
+    removed
+}
+
+/// New position of each statement when the removed ones are dropped, the
+/// removed ones take the position of the next one left. The last item is
+/// the new end.
+pub fn positions(removed: &[bool]) -> Vec<usize> {
+    let mut positions = Vec::with_capacity(removed.len() + 1);
+    let mut position = 0;
+    for removed in removed {
+        positions.push(position);
+        if !removed {
+            position += 1;
+        }
+    }
+    positions.push(position);
+    positions
+}
+
+/// Drops the items at the removed positions
+pub fn retain<T>(items: &mut Vec<T>, removed: &[bool]) {
+    let mut removed = removed.iter();
+    items.retain(|_| !removed.next().copied().unwrap_or_default());
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        error::RuntimeError,
+        interpreter::{Backend, Config, InterpreterContext},
+        io::MemoryIo,
+        parser::Parser,
+        tokenizer::Tokenizer,
+    };
+
+    use super::*;
+
+    fn parse(source: &str, no_optimize: bool, backend: Backend) -> (InterpreterContext, MemoryIo) {
+        let io = MemoryIo::new("3\n");
+        let mut context = InterpreterContext::new(&Config {
+            seed: Some(1),
+            no_optimize,
+            backend,
+            ..Default::default()
+        });
+        context.set_io(Box::new(io.clone()));
+        let tokens = Tokenizer::new().tokenize(source);
+        Parser::new(tokens, &mut context).parse().unwrap();
+        (context, io)
+    }
+
+    /// Output or the error of the script, checking it's the same with and
+    /// without the optimization on both backends
+    fn run(source: &str) -> String {
+        let mut outputs = Vec::new();
+        for backend in [Backend::Ast, Backend::Bytecode] {
+            for no_optimize in [true, false] {
+                let (mut context, io) = parse(source, no_optimize, backend);
+                let result = context.run();
+                let mut output = io.output();
+                if let Err(err) = result {
+                    output.push_str(&err.to_string());
+                }
+                outputs.push(output);
+            }
+        }
+        for output in &outputs[1..] {
+            assert_eq!(output, &outputs[0], "{source}");
+        }
+        outputs.swap_remove(0)
+    }
+
+    #[test]
+    fn optimize_fold_constants() {
+        let (mut context, _) = parse(
+            "x = 60 * 60 * 24\ny = LEN(\"abc\") + x\nz = (x - 1) * 1\nPRINT z",
+            false,
+            Backend::Ast,
+        );
+        context.run().unwrap();
+        let mut statements = (0..4).filter_map(|index| context.statement(index));
+        let Some(Statement::Assign { value, .. }) = statements.next() else {
+            panic!("Assignment expected");
+        };
+        assert_eq!(value.constant(), Some(&Value::number(86400.0)));
+        let Some(Statement::Assign { value, .. }) = statements.next() else {
+            panic!("Assignment expected");
+        };
+        assert!(value.constant().is_none());
+    }
+
+    #[test]
+    fn optimize_keep_sized_functions() {
+        let source = "IF 0 THEN x$ = SPACE$(1000000 * 1000000 * 1000000)\n\
+                      y$ = STRING$(3, \"a\")\nz$ = LEFT$(\"a\", 0 - 1)\nPRINT y$";
+        let (mut context, _) = parse(source, false, Backend::Ast);
+        let result = context.run();
+        assert_eq!(
+            result,
+            Err(RuntimeError::IllegalFunctionCall("LEFT$".to_string()))
+        );
+        let assignments = (0..5)
+            .filter_map(|index| context.statement(index))
+            .filter_map(|statement| match statement {
+                Statement::Assign { value, .. } => Some(value),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(assignments.len(), 3);
+        assert!(assignments.iter().all(|value| value.constant().is_none()));
+    }
+
+    #[test]
+    fn optimize_output_equivalence() {
+        let scripts = [
+            "x = 60 * 60 * 24\nPRINT x, x + 0, x * 1, 1 * x, x / 1, x - 0",
+            "a$ = \"ab\"\nPRINT a$ + \"\" + LEFT$(\"xyz\", 2) + STR$(2 * 3)",
+            "PRINT \"a\" + 1\nPRINT 1 + \"2\"\nPRINT 0 - 0 * 1 + 0",
+            "x = \"s\"\nPRINT x * 1",
+            "PRINT CHR$(0 - 1)",
+            "PRINT 1 / 0, 0 - 1 / 0",
+            "i = 0\nloop: i = i + 1\nIF i < 5 THEN hop\nPRINT i\nGOTO end\nhop: GOTO loop\nPRINT \"dead\"\nend:",
+            "GOTO a\nPRINT 1\nDATA 1, 2\nb: DATA 3\na: READ x, y\nPRINT x; y\nRESTORE b\nREAD z\nPRINT z",
+            "GOSUB s\nPRINT \"back\"\nGOTO done\ns: GOTO t\nPRINT \"skipped\"\nt: PRINT \"sub\"\nRETURN\ndone:",
+            "INPUT n\nIF n > 2 THEN GOTO big: PRINT \"dead\"\nPRINT \"small\"\nGOTO done\nbig: PRINT \"big\"\ndone:",
+            "GOTO a\nb: GOTO c\na: GOTO b\nc: PRINT \"end\"\nGOTO nowhere\nPRINT \"dead\"",
+        ];
+        let outputs = [
+            "86400         86400         86400         86400         86400         86400\n",
+            "abxy 6\n",
+            "",
+            "Type mismatch",
+            "Illegal function call in CHR$",
+            "inf           -inf\n",
+            "5\n",
+            "12\n3\n",
+            "sub\nback\n",
+            "? big\n",
+            "end\nUndefined label nowhere",
+        ];
+        for (script, expected) in scripts.iter().zip(outputs) {
+            let output = run(script);
+            if !expected.is_empty() {
+                assert_eq!(output, expected, "{script}");
+            }
+        }
+    }
+
+    #[test]
+    fn optimize_thread_jumps_and_remove_unreachable() {
+        let source = "GOTO a\nPRINT 1\nPRINT 2\na: GOTO b\nPRINT 3\nb: IF x THEN a\nGOTO c\nc:";
+        let (mut context, io) = parse(source, false, Backend::Ast);
+        context.run().unwrap();
+        assert_eq!(io.output(), "");
+        assert_eq!(context.statements_count(), 4);
+        assert_eq!(context.label("a"), Some(1));
+        assert_eq!(context.label("b"), Some(2));
+        assert_eq!(context.label("c"), Some(4));
+        assert_eq!(context.statement_line(2), Some(6));
+        assert!(matches!(
+            context.statement(0),
+            Some(Statement::Goto {
+                target: Some(2),
+                ..
+            })
+        ));
+        assert!(matches!(
+            context.statement(2),
+            Some(Statement::IfThen {
+                target: Some(2),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn optimize_thread_jump_loop() {
+        let mut statements = ["a", "b", "a"].map(|label| Statement::goto(label.to_string()));
+        for statement in &mut statements {
+            statement.resolve_jump(|label| Some(if label == "a" { 1 } else { 2 }));
+        }
+        thread_jumps(&mut statements);
+        let targets = statements
+            .iter_mut()
+            .filter_map(|statement| statement.jump_target_mut().copied())
+            .collect::<Vec<_>>();
+        // The jumps stay in the loop, the threading just has to end
+        assert_eq!(targets, [2, 1, 1]);
+    }
+
+    #[test]
+    fn optimize_positions() {
+        let removed = [false, true, true, false, true];
+        assert_eq!(positions(&removed), [0, 1, 1, 1, 2, 2]);
+        let mut items = vec![0, 1, 2, 3, 4];
+        retain(&mut items, &removed);
+        assert_eq!(items, [0, 3]);
+    }
+}
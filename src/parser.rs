@@ -1,25 +1,107 @@
 use crate::{
     ast::{
+        expr_function::FunctionExpression,
+        expr_index::IndexExpression,
         expr_operator::OperatorExpression,
         expr_variable::VariableExpression,
-        statement::Statement,
+        statement::{PrintItem, Statement},
         value::{Double, Value},
         Expression,
     },
+    builtins,
+    error::ParseError,
     interpreter::InterpreterContext,
+    io::FileMode,
     tokenizer::{Token, TokenType},
+    variables::Variable,
 };
 
 const KEYWORD_PRINT: &str = "print";
 const KEYWORD_INPUT: &str = "input";
+const KEYWORD_LINE: &str = "line";
 const KEYWORD_GOTO: &str = "goto";
+const KEYWORD_GOSUB: &str = "gosub";
+const KEYWORD_RETURN: &str = "return";
 const KEYWORD_IF: &str = "if";
 const KEYWORD_THEN: &str = "then";
+const KEYWORD_MID: &str = "mid$";
+const KEYWORD_RANDOMIZE: &str = "randomize";
+const KEYWORD_SLEEP: &str = "sleep";
+const KEYWORD_TAB: &str = "tab";
+const KEYWORD_SPC: &str = "spc";
+const KEYWORD_USING: &str = "using";
+const KEYWORD_WRITE: &str = "write";
+const KEYWORD_OPEN: &str = "open";
+const KEYWORD_FOR: &str = "for";
+const KEYWORD_OUTPUT: &str = "output";
+const KEYWORD_APPEND: &str = "append";
+const KEYWORD_AS: &str = "as";
+const KEYWORD_CLOSE: &str = "close";
+const KEYWORD_DATA: &str = "data";
+const KEYWORD_READ: &str = "read";
+const KEYWORD_RESTORE: &str = "restore";
+const KEYWORD_TRON: &str = "tron";
+const KEYWORD_TROFF: &str = "troff";
+
+/// Keywords starting statements, a keyword followed by colon at the start
+/// of a line is a statement rather than a label
+const STATEMENT_KEYWORDS: &[&str] = &[
+    KEYWORD_PRINT,
+    KEYWORD_INPUT,
+    KEYWORD_LINE,
+    KEYWORD_GOTO,
+    KEYWORD_GOSUB,
+    KEYWORD_RETURN,
+    KEYWORD_IF,
+    KEYWORD_RANDOMIZE,
+    KEYWORD_SLEEP,
+    KEYWORD_WRITE,
+    KEYWORD_OPEN,
+    KEYWORD_CLOSE,
+    KEYWORD_DATA,
+    KEYWORD_READ,
+    KEYWORD_RESTORE,
+    KEYWORD_TRON,
+    KEYWORD_TROFF,
+];
+
+/// All reserved words of the language
+#[allow(dead_code)] // Only the language server of the binary lists them
+pub const KEYWORDS: &[&str] = &[
+    KEYWORD_PRINT,
+    KEYWORD_INPUT,
+    KEYWORD_LINE,
+    KEYWORD_GOTO,
+    KEYWORD_GOSUB,
+    KEYWORD_RETURN,
+    KEYWORD_IF,
+    KEYWORD_THEN,
+    KEYWORD_MID,
+    KEYWORD_RANDOMIZE,
+    KEYWORD_SLEEP,
+    KEYWORD_TAB,
+    KEYWORD_SPC,
+    KEYWORD_USING,
+    KEYWORD_WRITE,
+    KEYWORD_OPEN,
+    KEYWORD_FOR,
+    KEYWORD_OUTPUT,
+    KEYWORD_APPEND,
+    KEYWORD_AS,
+    KEYWORD_CLOSE,
+    KEYWORD_DATA,
+    KEYWORD_READ,
+    KEYWORD_RESTORE,
+    KEYWORD_TRON,
+    KEYWORD_TROFF,
+];
 
 pub struct Parser<'a> {
     tokens: Vec<Token>,
     position: usize,
     context: &'a mut InterpreterContext,
+    /// Source line of the statement being parsed
+    line: usize,
 }
 
 impl<'a> Parser<'a> {
@@ -28,52 +110,386 @@ impl<'a> Parser<'a> {
             tokens,
             position: 0,
             context,
+            line: 0,
         }
     }
 
-    pub fn parse(&mut self) {
+    /// Parses the tokens as a single expression, used to evaluate the
+    /// expressions entered in the debugger
+    pub fn parse_expression(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let expression = self.expression()?;
+        if self.get(0).t_type != TokenType::Eof {
+            return Err(self.unexpected("end of expression"));
+        }
+        Ok(expression)
+    }
+
+    pub fn parse(&mut self) -> Result<(), ParseError> {
         loop {
-            while self.match_type(TokenType::Line) {}
+            while self.match_type(TokenType::Line) || self.match_type(TokenType::Colon) {}
+            self.line = self.get(0).line;
 
+            if self.get(0).t_type == TokenType::Label && is_keyword(&self.get(0).text) {
+                self.split_keyword_label();
+            }
             if self.match_type(TokenType::Label) {
                 // Mark the index of the statement after the label
                 self.context
                     .put_label(self.last(1).text, self.context.statements_count());
-            } else if self.match_types(TokenType::Word, TokenType::Equals) {
-                let name = self.last(2).text;
-                let value = self.expression();
-                let statement = Statement::assign(name, value);
-                self.context.put_statement(statement);
-            } else if self.match_name(KEYWORD_PRINT) {
-                let statement = Statement::print(self.expression());
-                self.context.put_statement(statement);
-            } else if self.match_name(KEYWORD_INPUT) {
-                let name = self.consume_type(TokenType::Word).text;
-                let statement = Statement::input(name);
-                self.context.put_statement(statement);
-            } else if self.match_name(KEYWORD_GOTO) {
-                let name = self.consume_type(TokenType::Word).text;
-                let statement = Statement::goto(name);
-                self.context.put_statement(statement);
-            } else if self.match_name(KEYWORD_IF) {
-                let condition = self.expression();
-                self.consume_name(KEYWORD_THEN);
-                let label = self.consume_type(TokenType::Word).text;
+            } else if self.is_line_start() && self.match_type(TokenType::Number) {
+                // A line number is a label of the statement that follows it
+                let label = self.line_number()?;
+                self.context
+                    .put_label(label, self.context.statements_count());
+            } else if self.get(0).t_type == TokenType::Eof {
+                return Ok(());
+            } else if !self.statement()? {
+                return Err(self.error(&format!("Unexpected '{}'", self.get(0).text)));
+            }
+        }
+    }
+
+    /// Parses a single statement and puts it into the context. Returns
+    /// false if the next tokens don't start a statement.
+    fn statement(&mut self) -> Result<bool, ParseError> {
+        if self.match_types(TokenType::Word, TokenType::Equals) {
+            let variable = self.context.resolve_variable(self.last(2).text);
+            let value = self.expression()?;
+            let statement = Statement::assign(variable, value);
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_PRINT) {
+            let statement = self.print()?;
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_WRITE) {
+            let channel = self.channel()?;
+            let mut values = Vec::new();
+            while !self.is_statement_end() {
+                values.push(self.expression()?);
+                if !self.match_type(TokenType::Comma) && !self.match_type(TokenType::Semicolon) {
+                    break;
+                }
+            }
+            let statement = Statement::write(channel, values);
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_INPUT) {
+            let statement = if let Some(channel) = self.channel()? {
+                Statement::input_file(channel, self.variables()?)
+            } else {
+                let prompt = self.input_prompt("? ")?;
+                Statement::input(prompt, self.variables()?)
+            };
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_LINE) {
+            self.consume_name(KEYWORD_INPUT)?;
+            let statement = if let Some(channel) = self.channel()? {
+                Statement::line_input_file(channel, self.variable()?)
+            } else {
+                let prompt = self.input_prompt("")?;
+                Statement::line_input(prompt, self.variable()?)
+            };
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_DATA) {
+            let position = self.context.statements_count();
+            loop {
+                let text = self.data_item()?;
+                self.context.data().push(position, text);
+                if !self.match_type(TokenType::Comma) {
+                    break;
+                }
+            }
+            self.put_statement(Statement::Data);
+        } else if self.match_name(KEYWORD_READ) {
+            let statement = Statement::read(self.variables()?);
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_RESTORE) {
+            let label = if self.is_statement_end() {
+                None
+            } else {
+                Some(self.target()?)
+            };
+            let statement = Statement::restore(label);
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_OPEN) {
+            let path = self.expression()?;
+            self.consume_name(KEYWORD_FOR)?;
+            let mode = if self.match_name(KEYWORD_INPUT) {
+                FileMode::Input
+            } else if self.match_name(KEYWORD_OUTPUT) {
+                FileMode::Output
+            } else {
+                self.consume_name(KEYWORD_APPEND)?;
+                FileMode::Append
+            };
+            self.consume_name(KEYWORD_AS)?;
+            self.match_type(TokenType::Hash);
+            let number = self.expression()?;
+            let statement = Statement::open(path, mode, number);
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_CLOSE) {
+            let mut numbers = Vec::new();
+            while !self.is_statement_end() {
+                self.match_type(TokenType::Hash);
+                numbers.push(self.expression()?);
+                if !self.match_type(TokenType::Comma) {
+                    break;
+                }
+            }
+            let statement = Statement::close(numbers);
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_GOTO) {
+            let statement = Statement::goto(self.target()?);
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_GOSUB) {
+            let statement = Statement::gosub(self.target()?);
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_RETURN) {
+            self.put_statement(Statement::Return);
+        } else if self.match_name(KEYWORD_TRON) {
+            self.put_statement(Statement::Tron);
+        } else if self.match_name(KEYWORD_TROFF) {
+            self.put_statement(Statement::Troff);
+        } else if self.match_name(KEYWORD_IF) {
+            let condition = self.expression()?;
+            self.consume_name(KEYWORD_THEN)?;
+            if self.is_jump_target()
+                && matches!(self.get(1).t_type, TokenType::Line | TokenType::Eof)
+            {
+                let label = self.target()?;
                 let statement = Statement::if_then(condition, label);
-                self.context.put_statement(statement);
+                self.put_statement(statement);
+            } else {
+                self.then_block(condition)?;
+            }
+        } else if self.match_name(KEYWORD_RANDOMIZE) {
+            let seed = if self.is_statement_end() {
+                None
+            } else {
+                Some(self.expression()?)
+            };
+            let statement = Statement::randomize(seed);
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_SLEEP) {
+            let statement = Statement::sleep(self.expression()?);
+            self.put_statement(statement);
+        } else if self.match_name(KEYWORD_MID) {
+            self.consume_type(TokenType::OpenParenthesis)?;
+            let variable = self.variable()?;
+            self.consume_type(TokenType::Comma)?;
+            let start = self.expression()?;
+            let length = if self.match_type(TokenType::Comma) {
+                Some(self.expression()?)
             } else {
-                // Unexpected token (likely EOF), so end
+                None
+            };
+            self.consume_type(TokenType::CloseParenthesis)?;
+            self.consume_type(TokenType::Equals)?;
+            let value = self.expression()?;
+            let statement = Statement::mid_assign(variable, start, length, value);
+            self.put_statement(statement);
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Parses optional file number of I/O statements: #number followed
+    /// by comma.
+    fn channel(&mut self) -> Result<Option<Box<dyn Expression>>, ParseError> {
+        if !self.match_type(TokenType::Hash) {
+            return Ok(None);
+        }
+        let channel = self.expression()?;
+        self.consume_type(TokenType::Comma)?;
+        Ok(Some(channel))
+    }
+
+    /// Parses statements after THEN up to the end of the line, all of them
+    /// are skipped if the condition is false
+    fn then_block(&mut self, condition: Box<dyn Expression>) -> Result<(), ParseError> {
+        let index = self.context.statements_count();
+        self.put_statement(Statement::if_block(condition, 0));
+        loop {
+            if self.is_jump_target() {
+                // THEN label is a shortcut of THEN GOTO label
+                let statement = Statement::goto(self.target()?);
+                self.put_statement(statement);
+            } else if !self.statement()? {
+                return Err(self.error("Statement expected after THEN"));
+            }
+            if !self.match_type(TokenType::Colon) {
                 break;
             }
         }
+        let end = self.context.statements_count();
+        if let Some(Statement::If { end: position, .. }) = self.context.statement_mut(index) {
+            *position = end;
+        }
+        Ok(())
+    }
+
+    /// Checks if the next token is a line number or a label name which
+    /// ends the statement
+    fn is_jump_target(&self) -> bool {
+        let token = self.get(0);
+        match token.t_type {
+            TokenType::Number => true,
+            TokenType::Word => {
+                !is_keyword(&token.text)
+                    && matches!(
+                        self.get(1).t_type,
+                        TokenType::Line | TokenType::Eof | TokenType::Colon
+                    )
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks if the previous token ends a line, so the next one is the
+    /// first on its line
+    fn is_line_start(&self) -> bool {
+        self.position == 0 || self.last(1).t_type == TokenType::Line
+    }
+
+    /// Turns a label token named as a keyword, e.g. `RETURN:`, into the
+    /// keyword followed by the statements separator
+    fn split_keyword_label(&mut self) {
+        let token = &mut self.tokens[self.position];
+        token.t_type = TokenType::Word;
+        let column = token.column + token.text.chars().count();
+        let colon = Token::new(":", TokenType::Colon, token.line, column);
+        self.tokens.insert(self.position + 1, colon);
+    }
+
+    /// Parses a jump target which is either a label name or a line number
+    fn target(&mut self) -> Result<String, ParseError> {
+        if self.match_type(TokenType::Number) {
+            return self.line_number();
+        }
+        Ok(self.consume_type(TokenType::Word)?.text)
+    }
+
+    /// Converts the just consumed line number to the label it's registered
+    /// with, so 10 and 010 refer to the same line
+    fn line_number(&self) -> Result<String, ParseError> {
+        let text = self.last(1).text;
+        match text.parse::<u32>() {
+            Ok(number) => Ok(number.to_string()),
+            Err(_) => Err(self.error(&format!("Invalid line number {text}"))),
+        }
+    }
+
+    /// Parses a single value of DATA statement. It's either a quoted string
+    /// or the text up to the next comma.
+    fn data_item(&mut self) -> Result<String, ParseError> {
+        if self.match_type(TokenType::String) {
+            return Ok(self.last(1).text);
+        }
+        let mut text = String::new();
+        let mut previous = TokenType::Eof;
+        while !self.is_statement_end() && self.get(0).t_type != TokenType::Comma {
+            let token = self.get(0);
+            self.position += 1;
+            let is_word = matches!(token.t_type, TokenType::Word | TokenType::Number);
+            if is_word && matches!(previous, TokenType::Word | TokenType::Number) {
+                text.push(' ');
+            }
+            text.push_str(&token.text);
+            previous = token.t_type;
+        }
+        Ok(text)
     }
 
-    fn expression(&mut self) -> Box<dyn Expression> {
+    /// Parses comma separated list of variable names
+    fn variables(&mut self) -> Result<Vec<Variable>, ParseError> {
+        let mut variables = vec![self.variable()?];
+        while self.match_type(TokenType::Comma) {
+            variables.push(self.variable()?);
+        }
+        Ok(variables)
+    }
+
+    /// Parses the name of a variable and resolves it to the slot
+    fn variable(&mut self) -> Result<Variable, ParseError> {
+        let name = self.consume_type(TokenType::Word)?.text;
+        Ok(self.context.resolve_variable(name))
+    }
+
+    /// Parses optional prompt string of INPUT statements. A question mark is
+    /// added to the prompt followed by semicolon, but not by comma.
+    fn input_prompt(&mut self, default: &str) -> Result<String, ParseError> {
+        if !self.match_type(TokenType::String) {
+            return Ok(default.to_string());
+        }
+        let prompt = self.last(1).text;
+        if self.match_type(TokenType::Comma) {
+            return Ok(prompt);
+        }
+        self.consume_type(TokenType::Semicolon)?;
+        Ok(prompt + default)
+    }
+
+    fn print(&mut self) -> Result<Statement, ParseError> {
+        let channel = self.channel()?;
+        if self.match_name(KEYWORD_USING) {
+            return self.print_using(channel);
+        }
+        let mut items = Vec::new();
+        let mut newline = true;
+        while !self.is_statement_end() {
+            newline = false;
+            if self.match_type(TokenType::Semicolon) {
+                continue;
+            }
+            if self.match_type(TokenType::Comma) {
+                items.push(PrintItem::Zone);
+                continue;
+            }
+            newline = true;
+            let item = if self.match_function(KEYWORD_TAB) {
+                PrintItem::Tab(self.function_argument()?)
+            } else if self.match_function(KEYWORD_SPC) {
+                PrintItem::Spc(self.function_argument()?)
+            } else {
+                PrintItem::Expression(self.expression()?)
+            };
+            items.push(item);
+        }
+        Ok(Statement::print(channel, items, newline))
+    }
+
+    fn print_using(
+        &mut self,
+        channel: Option<Box<dyn Expression>>,
+    ) -> Result<Statement, ParseError> {
+        let format = self.expression()?;
+        self.consume_type(TokenType::Semicolon)?;
+        let mut values = Vec::new();
+        let mut newline = true;
+        while !self.is_statement_end() {
+            if self.match_type(TokenType::Semicolon) || self.match_type(TokenType::Comma) {
+                newline = false;
+                continue;
+            }
+            newline = true;
+            values.push(self.expression()?);
+        }
+        Ok(Statement::print_using(channel, format, values, newline))
+    }
+
+    /// Parses a single expression in parenthesis up to and including
+    /// the closing one.
+    fn function_argument(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let expr = self.expression()?;
+        self.consume_type(TokenType::CloseParenthesis)?;
+        Ok(expr)
+    }
+
+    fn expression(&mut self) -> Result<Box<dyn Expression>, ParseError> {
         self.operator()
     }
 
-    fn operator(&mut self) -> Box<dyn Expression> {
-        let mut expression = self.atomic();
+    fn operator(&mut self) -> Result<Box<dyn Expression>, ParseError> {
+        let mut expression = self.atomic()?;
 
         while self.match_type(TokenType::Operator) || self.match_type(TokenType::Equals) {
             let op = self
@@ -82,38 +498,80 @@ impl<'a> Parser<'a> {
                 .chars()
                 .next()
                 .expect("Operator can't be empty");
-            let right = self.atomic();
+            let right = self.atomic()?;
             expression = Box::new(OperatorExpression::new(expression, op, right));
         }
-        expression
+        Ok(expression)
     }
 
-    fn atomic(&mut self) -> Box<dyn Expression> {
+    fn atomic(&mut self) -> Result<Box<dyn Expression>, ParseError> {
         if self.match_type(TokenType::Word) {
+            let name = self.last(1).text;
+            if let Some(builtin) = builtins::lookup(&name) {
+                let arguments = if self.match_type(TokenType::OpenParenthesis) {
+                    self.arguments()?
+                } else {
+                    Vec::new()
+                };
+                if !builtin.accepts(arguments.len()) {
+                    let message = format!("Wrong number of arguments for {}", builtin.name);
+                    return Err(self.error(&message));
+                }
+                return Ok(Box::new(FunctionExpression::new(builtin, arguments)));
+            }
+            if self.match_type(TokenType::OpenParenthesis) {
+                // A word followed by parenthesis is an array element
+                let index = self.expression()?;
+                self.consume_type(TokenType::CloseParenthesis)?;
+                let variable = self.context.resolve_variable(name);
+                return Ok(Box::new(IndexExpression::new(variable, index)));
+            }
             // A word is a reference to a variable
-            return Box::new(VariableExpression::new(self.last(1).text));
+            let variable = self.context.resolve_variable(name);
+            return Ok(Box::new(VariableExpression::new(variable)));
         }
         if self.match_type(TokenType::Number) {
-            let val = self
-                .last(1)
-                .text
-                .parse::<Double>()
-                .expect("Failed to convert string to double");
-            return Box::new(Value::number(val));
+            let text = self.last(1).text;
+            let Ok(val) = text.parse::<Double>() else {
+                return Err(self.error(&format!("Invalid number {text}")));
+            };
+            return Ok(Box::new(Value::number(val)));
         }
         if self.match_type(TokenType::String) {
-            return Box::new(Value::string(self.last(1).text));
+            return Ok(Box::new(Value::string(self.last(1).text)));
         }
         if self.match_type(TokenType::OpenParenthesis) {
             // The contents of a parenthesized expression can be any
             // expression. This lets us "restart" the precedence cascade
             // so that you can have a lower precedence expression inside
             // the parentheses.
-            let expr = self.expression();
-            self.consume_type(TokenType::CloseParenthesis);
-            return expr;
+            let expr = self.expression()?;
+            self.consume_type(TokenType::CloseParenthesis)?;
+            return Ok(expr);
+        }
+        Err(self.unexpected("expression"))
+    }
+
+    /// Parses a comma separated list of expressions after the opening
+    /// parenthesis up to and including the closing one.
+    fn arguments(&mut self) -> Result<Vec<Box<dyn Expression>>, ParseError> {
+        let mut arguments = Vec::new();
+        if self.match_type(TokenType::CloseParenthesis) {
+            return Ok(arguments);
         }
-        panic!("Parse error")
+        loop {
+            arguments.push(self.expression()?);
+            if !self.match_type(TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume_type(TokenType::CloseParenthesis)?;
+        Ok(arguments)
+    }
+
+    /// Adds a statement to the program at the line being parsed
+    fn put_statement(&mut self, statement: Statement) {
+        self.context.put_statement(statement, self.line);
     }
 
     /// Gets a previously consumed token, indexing backwards. last(1) will
@@ -156,34 +614,81 @@ impl<'a> Parser<'a> {
         true
     }
 
+    /// Checks if there are no more tokens in the current statement
+    fn is_statement_end(&self) -> bool {
+        matches!(
+            self.get(0).t_type,
+            TokenType::Line | TokenType::Colon | TokenType::Eof
+        )
+    }
+
+    /// Consumes the next two tokens if they are a word with the given name
+    /// followed by an opening parenthesis.
+    fn match_function(&mut self, name: &str) -> bool {
+        if self.get(1).t_type != TokenType::OpenParenthesis || !self.match_name(name) {
+            return false;
+        }
+        self.position += 1;
+        true
+    }
+
     /// Gets an unconsumed token, indexing forward. get(0) will be the next
     /// token to be consumed, get(1) the one after that, etc.
     fn get(&self, offset: usize) -> Token {
-        self.tokens
-            .get(self.position + offset)
-            .unwrap_or(&Token::eof())
-            .clone()
+        match self.tokens.get(self.position + offset) {
+            Some(token) => token.clone(),
+            None => match self.tokens.last() {
+                Some(token) => Token::eof(token.line, token.column + token.text.chars().count()),
+                None => Token::eof(1, 0),
+            },
+        }
     }
 
-    /// Consumes the next token if it's the given type. If not, throws an
-    /// exception. This is for cases where the parser demands a token of a
+    /// Consumes the next token if it's the given type. If not, returns
+    /// an error. This is for cases where the parser demands a token of a
     /// certain type in a certain position, for example a matching ) after
     /// an opening (.
-    fn consume_type(&mut self, t_type: TokenType) -> Token {
+    fn consume_type(&mut self, t_type: TokenType) -> Result<Token, ParseError> {
         if self.get(0).t_type != t_type {
-            panic!("Expected {:?}", t_type)
+            return Err(self.unexpected(&format!("{t_type:?}")));
         }
         let token = self.tokens[self.position].clone();
         self.position += 1;
-        token
+        Ok(token)
     }
 
     /// Consumes the next token if it's a word with the given name. If not,
-    /// throws an exception.
-    fn consume_name(&mut self, name: &str) -> Token {
+    /// returns an error.
+    fn consume_name(&mut self, name: &str) -> Result<Token, ParseError> {
         if !self.match_name(name) {
-            panic!("Expected {name}")
+            return Err(self.unexpected(&name.to_uppercase()));
         }
-        self.last(1)
+        Ok(self.last(1))
     }
+
+    /// Error located at the next token
+    fn error(&self, message: &str) -> ParseError {
+        let token = self.get(0);
+        ParseError {
+            line: token.line,
+            column: token.column,
+            message: message.to_string(),
+            incomplete: token.t_type == TokenType::Eof,
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> ParseError {
+        let token = self.get(0);
+        let found = match token.t_type {
+            TokenType::Eof => "end of input".to_string(),
+            TokenType::Line => "end of line".to_string(),
+            _ => format!("'{}'", token.text),
+        };
+        self.error(&format!("Expected {expected}, found {found}"))
+    }
+}
+
+/// Checks if the word starts a statement
+pub fn is_keyword(text: &str) -> bool {
+    STATEMENT_KEYWORDS.contains(&text.to_lowercase().as_str())
 }
@@ -0,0 +1,248 @@
+use std::{cmp::Reverse, collections::BTreeMap, fmt::Write, path::PathBuf, time::Duration};
+
+use crate::interpreter::InterpreterContext;
+
+/// Number of the hottest statements listed in the report
+const REPORT_LIMIT: usize = 20;
+/// Block of the statements before the first label
+const MAIN_BLOCK: &str = "(main)";
+/// Root frame of the collapsed stacks
+const MAIN_FRAME: &str = "main";
+
+/// Which profile of the script is collected
+#[derive(Debug, Clone, Default)]
+pub struct ProfileConfig {
+    /// Measure the time spent in the statements and the labeled blocks
+    pub enabled: bool,
+    /// File the collapsed stacks are written to for the flame graph tools
+    pub stacks_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    pub count: u64,
+    pub time: Duration,
+}
+
+impl Stats {
+    fn add(&mut self, time: Duration) {
+        self.count += 1;
+        self.time += time;
+    }
+}
+
+/// Counts the executions and the wall time of the statements
+#[derive(Default)]
+pub struct Profiler {
+    /// Indexed by the statement positions
+    statements: Vec<Stats>,
+    /// Labels of the active subroutines, the innermost is last
+    frames: Vec<String>,
+    /// Time spent on each source line by the call stack it was reached
+    /// through, the keys are in the collapsed stack format
+    stacks: BTreeMap<String, Duration>,
+}
+
+impl Profiler {
+    pub fn record(&mut self, index: usize, line: usize, time: Duration) {
+        if self.statements.len() <= index {
+            self.statements.resize(index + 1, Stats::default());
+        }
+        self.statements[index].add(time);
+        let mut key = MAIN_FRAME.to_string();
+        for frame in &self.frames {
+            key.push(';');
+            key.push_str(frame);
+        }
+        let _ = write!(key, ";line {line}");
+        *self.stacks.entry(key).or_default() += time;
+    }
+
+    /// The subroutine at the label is called
+    pub fn enter(&mut self, label: &str) {
+        self.frames.push(label.to_string());
+    }
+
+    pub fn leave(&mut self) {
+        self.frames.pop();
+    }
+
+    #[cfg(test)]
+    pub fn statement(&self, index: usize) -> Stats {
+        self.statements.get(index).copied().unwrap_or_default()
+    }
+
+    /// Hot spots sorted by the time, the statements are grouped into the
+    /// blocks by the labels they follow
+    pub fn report(&self, context: &InterpreterContext) -> String {
+        let mut statements = self
+            .statements
+            .iter()
+            .enumerate()
+            .filter(|(_, stats)| stats.count > 0)
+            .map(|(index, stats)| {
+                let line = context.statement_line(index).unwrap_or_default();
+                let block = context.section(index).unwrap_or(MAIN_BLOCK);
+                (line, block, *stats)
+            })
+            .collect::<Vec<_>>();
+        let mut blocks: BTreeMap<&str, Stats> = BTreeMap::new();
+        for (_, block, stats) in &statements {
+            let total = blocks.entry(block).or_default();
+            total.count += stats.count;
+            total.time += stats.time;
+        }
+        let count = statements
+            .iter()
+            .map(|(.., stats)| stats.count)
+            .sum::<u64>();
+        let total = statements
+            .iter()
+            .map(|(.., stats)| stats.time)
+            .sum::<Duration>();
+        statements.sort_by(|a, b| {
+            (b.2.time, b.2.count)
+                .cmp(&(a.2.time, a.2.count))
+                .then(a.0.cmp(&b.0))
+        });
+        let mut blocks = blocks.into_iter().collect::<Vec<_>>();
+        blocks.sort_by_key(|(_, stats)| Reverse((stats.time, stats.count)));
+
+        let share = |time: Duration| {
+            if total.is_zero() {
+                0.0
+            } else {
+                time.as_secs_f64() / total.as_secs_f64() * 100.0
+            }
+        };
+        let ms = |time: Duration| time.as_secs_f64() * 1000.0;
+        let mut report = String::new();
+        let _ = writeln!(report, "Total: {count} statements, {:.3} ms", ms(total));
+        let _ = writeln!(report, "\nHot statements:");
+        let _ = writeln!(
+            report,
+            "{:>12} {:>7} {:>10} {:>5}  block",
+            "time ms", "share", "count", "line"
+        );
+        for (line, block, stats) in statements.iter().take(REPORT_LIMIT) {
+            let _ = writeln!(
+                report,
+                "{:>12.3} {:>6.1}% {:>10} {line:>5}  {block}",
+                ms(stats.time),
+                share(stats.time),
+                stats.count,
+            );
+        }
+        let _ = writeln!(report, "\nBlocks:");
+        let _ = writeln!(
+            report,
+            "{:>12} {:>7} {:>10}  block",
+            "time ms", "share", "count"
+        );
+        for (block, stats) in blocks {
+            let _ = writeln!(
+                report,
+                "{:>12.3} {:>6.1}% {:>10}  {block}",
+                ms(stats.time),
+                share(stats.time),
+                stats.count,
+            );
+        }
+        report
+    }
+
+    /// Lines of `main;sub;line 12 <microseconds>` as the flame graph tools
+    /// expect. The stacks which took less than a microsecond are omitted.
+    pub fn collapsed_stacks(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|(stack, time)| (stack, time.as_micros()))
+            .filter(|(_, micros)| *micros > 0)
+            .map(|(stack, micros)| format!("{stack} {micros}\n"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        clock::FakeClock,
+        interpreter::{Backend, Config},
+        io::MemoryIo,
+        parser::Parser,
+        tokenizer::Tokenizer,
+    };
+
+    use super::*;
+
+    /// Runs the script on each backend and checks they record the same
+    /// profile
+    fn profile(script: &str) -> InterpreterContext {
+        let [ast, bytecode] = [Backend::Ast, Backend::Bytecode].map(|backend| {
+            let config = Config {
+                profile: ProfileConfig {
+                    enabled: true,
+                    ..Default::default()
+                },
+                backend,
+                ..Default::default()
+            };
+            let mut context = InterpreterContext::new(&config);
+            context.set_clock(Box::new(FakeClock::new(Duration::ZERO)));
+            context.set_io(Box::new(MemoryIo::default()));
+            let tokens = Tokenizer::new().tokenize(script);
+            Parser::new(tokens, &mut context).parse().unwrap();
+            context.run().unwrap();
+            context
+        });
+        let report = |context: &InterpreterContext| {
+            let profiler = context.profiler().unwrap();
+            (profiler.report(context), profiler.collapsed_stacks())
+        };
+        assert_eq!(report(&ast), report(&bytecode));
+        bytecode
+    }
+
+    const SCRIPT: &str =
+        "GOSUB work\nGOSUB work\nGOTO done\nwork: SLEEP 1\nSLEEP 0.5\nRETURN\ndone:";
+
+    #[test]
+    fn profile_statements() {
+        let context = profile(SCRIPT);
+        let profiler = context.profiler().unwrap();
+        let stats = profiler.statement(3);
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.time, Duration::from_secs(2));
+        assert_eq!(profiler.statement(4).time, Duration::from_secs(1));
+        assert_eq!(profiler.statement(2).count, 1);
+        assert_eq!(profiler.statement(6), Stats::default());
+        assert_eq!(
+            profiler.collapsed_stacks(),
+            "main;work;line 4 2000000\nmain;work;line 5 1000000\n"
+        );
+    }
+
+    #[test]
+    fn profile_report() {
+        let context = profile(SCRIPT);
+        let report = context.profiler().unwrap().report(&context);
+        let expected = "\
+Total: 9 statements, 3000.000 ms
+
+Hot statements:
+     time ms   share      count  line  block
+    2000.000   66.7%          2     4  work
+    1000.000   33.3%          2     5  work
+       0.000    0.0%          2     6  work
+       0.000    0.0%          1     1  (main)
+       0.000    0.0%          1     2  (main)
+       0.000    0.0%          1     3  (main)
+
+Blocks:
+     time ms   share      count  block
+    3000.000  100.0%          6  work
+       0.000    0.0%          3  (main)
+";
+        assert_eq!(report, expected);
+    }
+}
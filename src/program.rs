@@ -0,0 +1,208 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::RangeInclusive,
+};
+
+use crate::tokenizer::{TokenType, Tokenizer};
+
+pub type LineNumber = u32;
+
+/// Keywords followed by a line number that RENUM has to update
+const JUMP_KEYWORDS: &[&str] = &["goto", "gosub", "then", "restore"];
+
+/// Numbered lines entered in the interactive mode
+#[derive(Default)]
+pub struct Program {
+    lines: BTreeMap<LineNumber, String>,
+}
+
+impl Program {
+    /// Stores or replaces the line, an empty text removes it
+    pub fn set_line(&mut self, number: LineNumber, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            self.lines.remove(&number);
+        } else {
+            self.lines.insert(number, text.to_string());
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    pub fn delete(&mut self, range: RangeInclusive<LineNumber>) {
+        self.lines.retain(|number, _| !range.contains(number));
+    }
+
+    /// Lines in the range in the source form
+    pub fn list(&self, range: RangeInclusive<LineNumber>) -> String {
+        self.lines
+            .range(range)
+            .map(|(number, text)| format!("{number} {text}\n"))
+            .collect()
+    }
+
+    /// The whole program in the source form
+    pub fn source(&self) -> String {
+        self.list(0..=LineNumber::MAX)
+    }
+
+    /// Replaces the program with the numbered lines of the source. Empty
+    /// lines are skipped, a line without a number is an error.
+    pub fn load(&mut self, source: &str) -> Result<(), String> {
+        let mut lines = BTreeMap::new();
+        for (index, line) in source.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Some((number, text)) = split_line_number(line) else {
+                return Err(format!("Line {} has no line number", index + 1));
+            };
+            lines.insert(number, text.trim().to_string());
+        }
+        self.lines = lines;
+        Ok(())
+    }
+
+    /// Renumbers the lines starting from `start` with the given increment
+    /// and updates the line numbers GOTO, GOSUB, THEN and RESTORE refer to
+    pub fn renumber(&mut self, start: LineNumber, step: LineNumber) -> Result<(), String> {
+        if step == 0 {
+            return Err("Step must be positive".to_string());
+        }
+        let count = self.lines.len() as u64;
+        if count > 0 && start as u64 + (count - 1) * step as u64 > LineNumber::MAX as u64 {
+            return Err("Line numbers are too large".to_string());
+        }
+        let mapping = self
+            .lines
+            .keys()
+            .enumerate()
+            .map(|(index, number)| (*number, start + index as LineNumber * step))
+            .collect::<HashMap<_, _>>();
+        self.lines = std::mem::take(&mut self.lines)
+            .into_iter()
+            .map(|(number, text)| (mapping[&number], renumber_targets(&text, &mapping)))
+            .collect();
+        Ok(())
+    }
+}
+
+/// Splits the line into its number and the rest of the text
+pub fn split_line_number(line: &str) -> Option<(LineNumber, &str)> {
+    let line = line.trim_start();
+    let end = line
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(line.len());
+    let number = line[..end].parse::<LineNumber>().ok()?;
+    Some((number, &line[end..]))
+}
+
+/// Parses a range of lines: `10`, `10-50`, `10-` or `-50`. Empty text is
+/// the whole program.
+pub fn parse_range(text: &str) -> Option<RangeInclusive<LineNumber>> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(0..=LineNumber::MAX);
+    }
+    let parse = |text: &str, default: LineNumber| match text.trim() {
+        "" => Some(default),
+        number => number.parse::<LineNumber>().ok(),
+    };
+    match text.split_once('-') {
+        Some((first, last)) => Some(parse(first, 0)?..=parse(last, LineNumber::MAX)?),
+        None => {
+            let number = parse(text, 0)?;
+            Some(number..=number)
+        }
+    }
+}
+
+/// Replaces the line numbers following the jump keywords, the numbers
+/// missing in the mapping are kept as is
+fn renumber_targets(text: &str, mapping: &HashMap<LineNumber, LineNumber>) -> String {
+    let tokens = Tokenizer::new().tokenize(text);
+    let mut chars = text.chars().collect::<Vec<_>>();
+    // Replaced from the end, so the columns of the preceding tokens are valid
+    for pair in tokens.windows(2).rev() {
+        let (keyword, target) = (&pair[0], &pair[1]);
+        let is_jump = keyword.t_type == TokenType::Word
+            && JUMP_KEYWORDS.contains(&keyword.text.to_lowercase().as_str());
+        if !is_jump || target.t_type != TokenType::Number {
+            continue;
+        }
+        let Some(number) = target
+            .text
+            .parse::<LineNumber>()
+            .ok()
+            .and_then(|number| mapping.get(&number))
+        else {
+            continue;
+        };
+        let range = target.column..target.column + target.text.chars().count();
+        chars.splice(range, number.to_string().chars());
+    }
+    chars.into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn program(source: &str) -> Program {
+        let mut program = Program::default();
+        program.load(source).unwrap();
+        program
+    }
+
+    #[test]
+    fn program_edit_lines() {
+        let mut program = Program::default();
+        program.set_line(20, "PRINT x");
+        program.set_line(10, " x = 1 ");
+        program.set_line(30, "END");
+        program.set_line(20, "PRINT x + 1");
+        program.set_line(30, "");
+        assert_eq!(program.source(), "10 x = 1\n20 PRINT x + 1\n");
+        assert_eq!(program.list(15..=LineNumber::MAX), "20 PRINT x + 1\n");
+        program.delete(0..=10);
+        assert_eq!(program.source(), "20 PRINT x + 1\n");
+    }
+
+    #[test]
+    fn program_load() {
+        let program = program("\n 20 PRINT 2\n10 PRINT 1\n");
+        assert_eq!(program.source(), "10 PRINT 1\n20 PRINT 2\n");
+        let mut program = Program::default();
+        assert!(program.load("10 PRINT 1\nPRINT 2").is_err());
+    }
+
+    #[test]
+    fn program_parse_range() {
+        assert_eq!(parse_range(""), Some(0..=LineNumber::MAX));
+        assert_eq!(parse_range("10"), Some(10..=10));
+        assert_eq!(parse_range("10 - 50"), Some(10..=50));
+        assert_eq!(parse_range("10-"), Some(10..=LineNumber::MAX));
+        assert_eq!(parse_range("-50"), Some(0..=50));
+        assert_eq!(parse_range("abc"), None);
+    }
+
+    #[test]
+    fn program_renumber() {
+        let mut program = program(
+            "5 GOSUB 7: goto 99\n\
+            7 IF x THEN 5\n\
+            8 RESTORE 8: PRINT \"GOTO 7\"; 7\n\
+            12 IF мир THEN   12 ELSE GOTO 8",
+        );
+        program.renumber(100, 10).unwrap();
+        let expected = "100 GOSUB 110: goto 99\n\
+            110 IF x THEN 100\n\
+            120 RESTORE 120: PRINT \"GOTO 7\"; 7\n\
+            130 IF мир THEN   130 ELSE GOTO 120\n";
+        assert_eq!(program.source(), expected);
+        assert!(program.renumber(10, 0).is_err());
+        assert!(program.renumber(LineNumber::MAX - 10, 10).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use std::io::{self, BufRead, Write};
+
+use crate::json::Json;
+
+/// Largest message accepted, the content is read into memory at once
+const MAX_MESSAGE_LENGTH: usize = 16 * 1024 * 1024;
+
+/// Reads a message framed with the `Content-Length` header as used by the
+/// debug adapter and the language server protocols. Returns None at the
+/// end of input.
+pub fn read_message(input: &mut dyn BufRead) -> io::Result<Option<Json>> {
+    let mut length = None;
+    let mut headers = false;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            if headers {
+                break;
+            }
+            // Blank lines between the messages
+            continue;
+        }
+        headers = true;
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let value = value.trim().parse::<usize>().map_err(invalid_data)?;
+                length = Some(value);
+            }
+        }
+    }
+    let length = length.ok_or_else(|| invalid_data("Missing Content-Length header"))?;
+    if length > MAX_MESSAGE_LENGTH {
+        return Err(invalid_data(format!(
+            "Message of {length} bytes is too long"
+        )));
+    }
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    let text = String::from_utf8(content).map_err(invalid_data)?;
+    Json::parse(&text).map(Some).map_err(invalid_data)
+}
+
+pub fn write_message(output: &mut dyn Write, message: &Json) -> io::Result<()> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    output.flush()
+}
+
+fn invalid_data(err: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn protocol_messages() {
+        let mut output = Vec::new();
+        write_message(&mut output, &Json::object([("text", "мир".into())])).unwrap();
+        write_message(&mut output, &Json::Array(vec![])).unwrap();
+        let text = String::from_utf8(output.clone()).unwrap();
+        assert!(text.starts_with("Content-Length: 17\r\n\r\n{\"text\":\"мир\"}"));
+        let mut input = output.as_slice();
+        let first = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(first.get("text").as_str(), Some("мир"));
+        assert_eq!(read_message(&mut input).unwrap(), Some(Json::Array(vec![])));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+        let mut input = "Content-Length: 3\r\n\r\n{]}".as_bytes();
+        assert!(read_message(&mut input).is_err());
+    }
+
+    #[test]
+    fn protocol_invalid_headers() {
+        for text in [
+            "Content-Type: json\r\n\r\n{}",
+            "Content-Length: x\r\n\r\n{}",
+            "Content-Length: 99999999999\r\n\r\n{}",
+        ] {
+            let err = read_message(&mut text.as_bytes()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{text}");
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::ast::value::Double;
+
+/// Seed used when a script doesn't call RANDOMIZE, so every run of the
+/// same script produces the same sequence as in classic BASIC.
+pub const DEFAULT_SEED: u64 = 0x5DEECE66D;
+
+/// Deterministic pseudo-random number generator (SplitMix64).
+/// The same seed always produces the same sequence on every platform.
+pub struct Random {
+    state: u64,
+    last: Double,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            last: 0.0,
+        }
+    }
+
+    /// Converts a script level seed value to the generator's seed
+    pub fn seed_from(value: Double) -> u64 {
+        value.to_bits() as u64
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.state = seed;
+        self.last = 0.0;
+    }
+
+    /// Next value in the range [0, 1)
+    pub fn next_double(&mut self) -> Double {
+        // Keep only as many bits as the mantissa can represent exactly
+        let bits = Double::MANTISSA_DIGITS;
+        let val = (self.next_u64() >> (64 - bits)) as Double / (1u64 << bits) as Double;
+        self.last = val;
+        val
+    }
+
+    /// The value returned by the latest call to `next_double`
+    pub fn last_double(&self) -> Double {
+        self.last
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn random_same_seed_same_sequence() {
+        let mut first = Random::new(42);
+        let mut second = Random::new(42);
+        for _ in 0..100 {
+            assert_eq!(first.next_double(), second.next_double());
+        }
+    }
+
+    #[test]
+    fn random_different_seed_different_sequence() {
+        let mut first = Random::new(1);
+        let mut second = Random::new(2);
+        let equal = (0..10).all(|_| first.next_double() == second.next_double());
+        assert!(!equal);
+    }
+
+    #[test]
+    fn random_in_range() {
+        let mut random = Random::new(7);
+        for _ in 0..10000 {
+            let val = random.next_double();
+            assert!((0.0..1.0).contains(&val));
+        }
+    }
+
+    #[test]
+    fn random_last_value() {
+        let mut random = Random::new(7);
+        let val = random.next_double();
+        assert_eq!(random.last_double(), val);
+    }
+}
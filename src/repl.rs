@@ -0,0 +1,399 @@
+use crate::{
+    interpreter::{Config, InterpreterContext},
+    line_editor::LineEditor,
+    parser::Parser,
+    program::{parse_range, split_line_number, LineNumber, Program},
+    tokenizer::{Token, TokenType, Tokenizer},
+};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+/// Interactive session, the statements are executed as soon as they are
+/// entered. Variables and open files are kept between the entries. Lines
+/// starting with a number are stored in the program which is run by RUN.
+pub struct Repl {
+    context: InterpreterContext,
+    program: Program,
+    /// Source entered so far that isn't executed yet
+    pending: String,
+    /// A line with a label starts a block which is entered until an empty
+    /// line, so the statements could jump back to the label
+    in_block: bool,
+}
+
+impl Repl {
+    pub fn new(context: InterpreterContext) -> Self {
+        Self {
+            context,
+            program: Program::default(),
+            pending: String::new(),
+            in_block: false,
+        }
+    }
+
+    pub fn prompt(&self) -> &'static str {
+        if self.pending.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        }
+    }
+
+    /// Handles the entered line. Returns false if the session is over.
+    pub fn enter(&mut self, line: &str) -> bool {
+        let is_empty = line.trim().is_empty();
+        if self.pending.is_empty() {
+            if is_empty {
+                return true;
+            }
+            if ["exit", "system"].contains(&line.trim().to_lowercase().as_str()) {
+                return false;
+            }
+            if !self.in_block && self.command(line) {
+                return true;
+            }
+        }
+        if self.in_block && !is_empty {
+            self.pending.push('\n');
+            self.pending.push_str(line);
+            return true;
+        }
+        if !self.in_block && self.pending.is_empty() && is_block_start(line) {
+            self.in_block = true;
+            self.pending.push_str(line);
+            return true;
+        }
+        if !self.in_block {
+            if !self.pending.is_empty() {
+                // The line continues the incomplete statement
+                self.pending.push(' ');
+            }
+            self.pending.push_str(line);
+        }
+        self.in_block = false;
+        self.execute(is_empty);
+        true
+    }
+
+    /// Parses and runs the pending source. An incomplete statement waits
+    /// for the next line unless it's empty.
+    fn execute(&mut self, force: bool) {
+        let tokens = Tokenizer::new().tokenize(&self.pending);
+        self.context.clear_program();
+        let result = Parser::new(tokens, &mut self.context).parse();
+        match result {
+            Err(err) if err.incomplete && !force => return,
+            Err(err) => self.report(&format!("Parse error: {err}")),
+            Ok(()) => {
+                if let Err(err) = self.context.run_immediate() {
+                    self.report(&format!("Runtime error: {err}"));
+                }
+            }
+        }
+        self.pending.clear();
+        // The prompt is shown on a new line
+        if self.context.column() > 0 {
+            let _ = self.context.write("\n");
+        }
+    }
+
+    /// Edits the program or executes the editor command. Returns false if
+    /// the line should be executed as statements.
+    fn command(&mut self, line: &str) -> bool {
+        if let Some((number, text)) = split_line_number(line) {
+            self.program.set_line(number, text);
+            return true;
+        }
+        let tokens = Tokenizer::new().tokenize(line);
+        let name = match tokens.as_slice() {
+            [first, rest @ ..] if first.t_type == TokenType::Word => {
+                // Assignment to a variable named as a command
+                if matches!(rest.first(), Some(token) if token.t_type == TokenType::Equals) {
+                    return false;
+                }
+                first.text.to_lowercase()
+            }
+            _ => return false,
+        };
+        // Text after the command word
+        let arguments = match tokens.get(1) {
+            Some(token) => line.chars().skip(token.column).collect::<String>(),
+            None => String::new(),
+        };
+        let result = match name.as_str() {
+            "list" => self.list(&arguments),
+            "run" if arguments.is_empty() => {
+                self.run_program();
+                Ok(())
+            }
+            "new" if arguments.is_empty() => {
+                self.program.clear();
+                self.context.clear_variables();
+                Ok(())
+            }
+            "delete" if !arguments.trim().is_empty() => match parse_range(&arguments) {
+                Some(range) => {
+                    self.program.delete(range);
+                    Ok(())
+                }
+                None => Err("Illegal line range".to_string()),
+            },
+            "save" => file_argument(&tokens[1..]).and_then(|path| {
+                let source = self.program.source();
+                self.context
+                    .save_file(&path, &source)
+                    .map_err(|err| err.to_string())
+            }),
+            "load" => file_argument(&tokens[1..]).and_then(|path| {
+                let source = self
+                    .context
+                    .load_file(&path)
+                    .map_err(|err| err.to_string())?;
+                self.program.load(&source)
+            }),
+            "renum" => renum_arguments(&tokens[1..])
+                .ok_or("Illegal RENUM arguments".to_string())
+                .and_then(|(start, step)| self.program.renumber(start, step)),
+            _ => return false,
+        };
+        if let Err(message) = result {
+            self.report(&format!("Error: {message}"));
+        }
+        true
+    }
+
+    fn list(&mut self, arguments: &str) -> Result<(), String> {
+        let range = parse_range(arguments).ok_or("Illegal line range".to_string())?;
+        let listing = self.program.list(range);
+        self.context.write(&listing).map_err(|err| err.to_string())
+    }
+
+    /// Runs the stored program from scratch, the variables are cleared and
+    /// the open files are closed
+    fn run_program(&mut self) {
+        let tokens = Tokenizer::new().tokenize(&self.program.source());
+        self.context.clear_program();
+        self.context.clear_variables();
+        let result = Parser::new(tokens, &mut self.context).parse();
+        match result {
+            Err(err) => self.report(&format!("Parse error: {err}")),
+            Ok(()) => {
+                if let Err(err) = self.context.run() {
+                    self.report(&format!("Runtime error: {err}"));
+                }
+            }
+        }
+        if self.context.column() > 0 {
+            let _ = self.context.write("\n");
+        }
+    }
+
+    fn report(&mut self, message: &str) {
+        if self.context.column() > 0 {
+            let _ = self.context.write("\n");
+        }
+        let _ = self.context.write(&format!("{message}\n"));
+    }
+
+    /// Closes the files left open by the session
+    fn finish(&mut self) {
+        if let Err(err) = self.context.files().close_all() {
+            self.report(&format!("Runtime error: {err}"));
+        }
+    }
+}
+
+/// Checks if the line only defines a label
+fn is_block_start(line: &str) -> bool {
+    let tokens = Tokenizer::new().tokenize(line);
+    matches!(tokens.as_slice(), [token] if token.t_type == TokenType::Label)
+}
+
+/// Path argument of SAVE and LOAD
+fn file_argument(tokens: &[Token]) -> Result<String, String> {
+    match tokens {
+        [token] if token.t_type == TokenType::String => Ok(token.text.clone()),
+        _ => Err("Expected a file name in quotes".to_string()),
+    }
+}
+
+/// Optional start and step of RENUM, both are 10 by default
+fn renum_arguments(tokens: &[Token]) -> Option<(LineNumber, LineNumber)> {
+    let number = |token: &Token| match token.t_type {
+        TokenType::Number => token.text.parse::<LineNumber>().ok(),
+        _ => None,
+    };
+    match tokens {
+        [] => Some((10, 10)),
+        [start] => Some((number(start)?, 10)),
+        [start, comma, step] if comma.t_type == TokenType::Comma => {
+            Some((number(start)?, number(step)?))
+        }
+        _ => None,
+    }
+}
+
+/// Runs the interactive session on the console until the end of input
+pub fn run_repl(config: &Config) {
+    println!("basic-like interactive mode, enter EXIT to quit");
+    let mut repl = Repl::new(InterpreterContext::new(config));
+    let mut editor = LineEditor::default();
+    loop {
+        match editor.read_line(repl.prompt()) {
+            Ok(Some(line)) => {
+                if !repl.enter(&line) {
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(err) => {
+                eprintln!("Failed to read the input: {err}");
+                break;
+            }
+        }
+    }
+    repl.finish();
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ast::value::Value, io::MemoryIo};
+
+    use super::*;
+
+    /// Enters the lines one by one and returns the output
+    fn session(lines: &[&str]) -> (Repl, String) {
+        let io = MemoryIo::default();
+        let mut context = InterpreterContext::default();
+        context.set_io(Box::new(io.clone()));
+        let mut repl = Repl::new(context);
+        for line in lines {
+            assert!(repl.enter(line), "{line}");
+        }
+        (repl, io.output())
+    }
+
+    #[test]
+    fn repl_keeps_variables() {
+        let (_, output) = session(&["x = 2", "y = x * 3: PRINT y;", "", "PRINT x + y"]);
+        assert_eq!(output, "6\n8\n");
+    }
+
+    #[test]
+    fn repl_reports_errors() {
+        let lines = [
+            "PRINT \"a\";",
+            "x = \"a\" - 1",
+            "PRINT (1",
+            "",
+            "GOTO",
+            "",
+            "PRINT 1 +)",
+            "PRINT \"still here\"",
+        ];
+        let (_, output) = session(&lines);
+        let expected = "a\n\
+            Runtime error: Type mismatch\n\
+            Parse error: Line 1: Expected CloseParenthesis, found end of input\n\
+            Parse error: Line 1: Expected Word, found end of input\n\
+            Parse error: Line 1: Expected expression, found ')'\n\
+            still here\n";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn repl_continuation() {
+        let (mut repl, _) = session(&["x = 1 +"]);
+        assert_eq!(repl.prompt(), CONTINUATION_PROMPT);
+        repl.enter("2");
+        assert_eq!(repl.prompt(), PROMPT);
+        assert_eq!(repl.context.variable("x"), Some(&Value::number(3.0)));
+    }
+
+    #[test]
+    fn repl_block() {
+        let lines = [
+            "i = 0",
+            "loop:",
+            "i = i + 1: PRINT i;",
+            "IF i < 3 THEN loop",
+            "",
+            "PRINT \"done\"",
+        ];
+        let (repl, output) = session(&lines);
+        assert_eq!(output, "123\ndone\n");
+        assert_eq!(repl.prompt(), PROMPT);
+    }
+
+    #[test]
+    fn repl_program_editor() {
+        let lines = [
+            "20 PRINT \"x =\"; x",
+            "10 x = 5",
+            "30 GOTO 20",
+            "30",
+            "x = 1",
+            "LIST",
+            "RUN",
+            "PRINT x",
+            "15 GOSUB 100: x = x * 2",
+            "100 RETURN",
+            "RENUM 100, 5",
+            "LIST 105-",
+            "RUN",
+            "DELETE 105-110",
+            "LIST",
+            "NEW",
+            "LIST",
+            "RENUM 0, 0",
+            "RENUM x",
+            "list = 3: PRINT list",
+        ];
+        let (_, output) = session(&lines);
+        let expected = "10 x = 5\n\
+            20 PRINT \"x =\"; x\n\
+            x =5\n\
+            5\n\
+            105 GOSUB 115: x = x * 2\n\
+            110 PRINT \"x =\"; x\n\
+            115 RETURN\n\
+            x =10\n\
+            Runtime error: RETURN without GOSUB\n\
+            100 x = 5\n\
+            115 RETURN\n\
+            Error: Step must be positive\n\
+            Error: Illegal RENUM arguments\n\
+            3\n";
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn repl_save_load() {
+        let io = MemoryIo::default();
+        let mut context = InterpreterContext::default();
+        context.set_io(Box::new(io.clone()));
+        let mut repl = Repl::new(context);
+        for line in ["10 PRINT 1", "20 PRINT 2", "SAVE \"prog.bas\"", "NEW"] {
+            repl.enter(line);
+        }
+        assert_eq!(io.file("prog.bas").unwrap(), "10 PRINT 1\n20 PRINT 2\n");
+        io.put_file("other.bas", "5 PRINT 3\n");
+        for line in ["LOAD \"prog.bas\"", "RUN", "LOAD \"other.bas\"", "LIST"] {
+            repl.enter(line);
+        }
+        repl.enter("LOAD \"missing.bas\"");
+        repl.enter("SAVE prog");
+        let expected = "1\n2\n\
+            5 PRINT 3\n\
+            Error: File not found: missing.bas\n\
+            Error: Expected a file name in quotes\n";
+        assert_eq!(io.output(), expected);
+    }
+
+    #[test]
+    fn repl_exit() {
+        let (mut repl, _) = session(&[]);
+        assert!(!repl.enter(" EXIT "));
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    env, fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{error::RuntimeError, io::FileMode};
+
+/// Restrictions on the files a script may access. The default policy
+/// allows everything the process itself is allowed to do.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sandbox {
+    /// Directories the files must be located in, any directory if empty
+    pub allowed_dirs: Vec<PathBuf>,
+    /// Files can be opened for input only
+    pub read_only: bool,
+    /// Size limit of files in bytes for both reading and writing
+    pub max_file_size: Option<u64>,
+    /// Scripts can't access files at all
+    pub deny_all: bool,
+}
+
+impl Sandbox {
+    /// Checks that the file may be opened in the given mode and returns
+    /// the path to open it by. If the directories are restricted it's the
+    /// resolved path which has been checked, so a link changed afterwards
+    /// can't redirect the access.
+    pub fn check(&self, path: &str, mode: FileMode) -> Result<String, RuntimeError> {
+        let denied = || RuntimeError::PermissionDenied(path.to_string());
+        if self.deny_all || (self.read_only && mode != FileMode::Input) {
+            return Err(denied());
+        }
+        if self.allowed_dirs.is_empty() {
+            return Ok(path.to_string());
+        }
+        let file = resolve(Path::new(path)).ok_or_else(denied)?;
+        let allowed = self
+            .allowed_dirs
+            .iter()
+            .filter_map(|dir| resolve(dir))
+            .any(|dir| file.starts_with(dir));
+        if !allowed {
+            return Err(denied());
+        }
+        file.into_os_string().into_string().map_err(|_| denied())
+    }
+
+    /// Number of bytes that may be written to the file which already has
+    /// the given size, None if there is no limit
+    pub fn write_limit(&self, size: u64) -> Option<u64> {
+        self.max_file_size
+            .map(|max_size| max_size.saturating_sub(size))
+    }
+
+    /// Checks that the file isn't too big to be read
+    pub fn check_size(&self, path: &str, size: u64) -> Result<(), RuntimeError> {
+        match self.max_file_size {
+            Some(max_size) if size > max_size => {
+                Err(RuntimeError::PermissionDenied(path.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Absolute path with the symbolic links resolved in the part of the
+/// path that exists, so a link can't be used to escape from an allowed
+/// directory. The `..` components are applied to the resolved links, in
+/// the part that doesn't exist yet they are rejected.
+fn resolve(path: &Path) -> Option<PathBuf> {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().ok()?.join(path)
+    };
+    let components = path.components().collect::<Vec<_>>();
+    let (existing, canonical) = (0..=components.len()).rev().find_map(|count| {
+        let prefix = components[..count].iter().collect::<PathBuf>();
+        fs::canonicalize(prefix).ok().map(|path| (count, path))
+    })?;
+    components[existing..]
+        .iter()
+        .try_fold(canonical, |path, component| match component {
+            Component::CurDir => Some(path),
+            Component::Normal(name) => Some(path.join(name)),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sandbox(dirs: &[&str]) -> Sandbox {
+        Sandbox {
+            allowed_dirs: dirs.iter().map(PathBuf::from).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn denied<T>(path: &str) -> Result<T, RuntimeError> {
+        Err(RuntimeError::PermissionDenied(path.to_string()))
+    }
+
+    #[test]
+    fn sandbox_default_allows_all() {
+        let sandbox = Sandbox::default();
+        assert_eq!(
+            sandbox.check("/any/file.txt", FileMode::Output),
+            Ok("/any/file.txt".to_string())
+        );
+        assert_eq!(sandbox.write_limit(100), None);
+        assert_eq!(sandbox.check_size("a.txt", u64::MAX), Ok(()));
+    }
+
+    #[test]
+    fn sandbox_allowed_dirs() {
+        let sandbox = sandbox(&["/no-such-root/data", "/no-such-root/tmp/"]);
+        let allowed = ["/no-such-root/data/a.txt", "/no-such-root/tmp/sub/./b.txt"];
+        for path in allowed {
+            assert!(sandbox.check(path, FileMode::Append).is_ok(), "{path}");
+        }
+        let rejected = [
+            "/no-such-root/database.txt",
+            "/no-such-root/data/../secret.txt",
+            "/no-such-root/tmp/../data/c.txt",
+            "/etc/passwd",
+            "relative.txt",
+        ];
+        for path in rejected {
+            assert_eq!(sandbox.check(path, FileMode::Input), denied(path), "{path}");
+        }
+    }
+
+    #[test]
+    fn sandbox_modes() {
+        let read_only = Sandbox {
+            read_only: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            read_only.check("a.txt", FileMode::Input),
+            Ok("a.txt".to_string())
+        );
+        assert_eq!(read_only.check("a.txt", FileMode::Output), denied("a.txt"));
+        assert_eq!(read_only.check("a.txt", FileMode::Append), denied("a.txt"));
+        let deny_all = Sandbox {
+            deny_all: true,
+            ..Default::default()
+        };
+        assert_eq!(deny_all.check("a.txt", FileMode::Input), denied("a.txt"));
+    }
+
+    #[test]
+    fn sandbox_file_size() {
+        let sandbox = Sandbox {
+            max_file_size: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(sandbox.write_limit(0), Some(10));
+        assert_eq!(sandbox.write_limit(4), Some(6));
+        assert_eq!(sandbox.write_limit(20), Some(0));
+        assert_eq!(sandbox.check_size("a.txt", 10), Ok(()));
+        assert_eq!(sandbox.check_size("a.txt", 11), denied("a.txt"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn sandbox_symbolic_links() {
+        let root = env::temp_dir().join(format!("basic-sandbox-{}", std::process::id()));
+        let allowed = root.join("allowed");
+        let inner = root.join("outside").join("inner");
+        fs::create_dir_all(&allowed).unwrap();
+        fs::create_dir_all(&inner).unwrap();
+        std::os::unix::fs::symlink(&inner, allowed.join("link")).unwrap();
+        let sandbox = Sandbox {
+            allowed_dirs: vec![allowed.clone()],
+            ..Default::default()
+        };
+        let check = |path: PathBuf| sandbox.check(path.to_str().unwrap(), FileMode::Input);
+        let escape = allowed.join("link").join("..").join("secret.txt");
+        let result = check(escape.clone());
+        let through_link = check(allowed.join("link").join("file.txt"));
+        let inside = check(allowed.join("file.txt"));
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(result, denied(escape.to_str().unwrap()));
+        assert!(through_link.is_err());
+        let canonical = fs::canonicalize(env::temp_dir()).unwrap();
+        let expected = canonical
+            .join(root.file_name().unwrap())
+            .join("allowed")
+            .join("file.txt");
+        assert_eq!(inside, Ok(expected.to_str().unwrap().to_string()));
+    }
+}
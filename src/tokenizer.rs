@@ -11,6 +11,11 @@ pub enum TokenType {
     Operator,
     OpenParenthesis,
     CloseParenthesis,
+    Comma,
+    Semicolon,
+    Hash,
+    /// Separator of statements on the same line
+    Colon,
     Eof,
 }
 
@@ -18,25 +23,24 @@ pub enum TokenType {
 pub struct Token {
     pub text: String,
     pub t_type: TokenType,
+    /// 1-based number of the source line the token is located on
+    pub line: usize,
+    /// 0-based position of the first character of the token in its line
+    pub column: usize,
 }
 
 impl Token {
-    fn new(text: &str, t_type: TokenType) -> Self {
+    pub fn new(text: &str, t_type: TokenType, line: usize, column: usize) -> Self {
         Self {
             text: text.to_string(),
             t_type,
+            line,
+            column,
         }
     }
 
-    fn with_char(ch: char, t_type: TokenType) -> Self {
-        Self {
-            text: ch.to_string(),
-            t_type,
-        }
-    }
-
-    pub fn eof() -> Self {
-        Self::new("", TokenType::Eof)
+    pub fn eof(line: usize, column: usize) -> Self {
+        Self::new("", TokenType::Eof, line, column)
     }
 }
 
@@ -54,6 +58,16 @@ pub struct Tokenizer {
     accumulator: String,
     tokens: Vec<Token>,
     state: State,
+    line: usize,
+    column: usize,
+    /// Column the accumulated token starts at
+    start: usize,
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Tokenizer {
@@ -63,6 +77,9 @@ impl Tokenizer {
             accumulator: String::new(),
             tokens: Default::default(),
             state: State::Default,
+            line: 1,
+            column: 0,
+            start: 0,
         }
     }
 
@@ -78,6 +95,10 @@ impl Tokenizer {
             ('>', TokenType::Operator),
             ('(', TokenType::OpenParenthesis),
             (')', TokenType::CloseParenthesis),
+            (',', TokenType::Comma),
+            (';', TokenType::Semicolon),
+            ('#', TokenType::Hash),
+            (':', TokenType::Colon),
         ];
         tuples.into_iter().collect()
     }
@@ -86,13 +107,18 @@ impl Tokenizer {
         self.state = State::Default;
         self.accumulator.clear();
         self.tokens.clear();
+        self.line = 1;
+        self.column = 0;
         for ch in source.chars() {
             loop {
                 let mut redo = false;
                 match self.state {
                     State::Default => {
+                        self.start = self.column;
                         if let Some(t_type) = self.char_token_map.get(&ch) {
-                            self.tokens.push(Token::with_char(ch, *t_type))
+                            let token =
+                                Token::new(&ch.to_string(), *t_type, self.line, self.column);
+                            self.tokens.push(token)
                         } else if ch.is_alphabetic() {
                             self.accumulator.push(ch);
                             self.state = State::Word;
@@ -108,7 +134,11 @@ impl Tokenizer {
                     State::Word => {
                         if ch.is_alphanumeric() {
                             self.accumulator.push(ch);
-                        } else if ch == ':' {
+                        } else if ch == '$' {
+                            // String type suffix always ends the word
+                            self.accumulator.push(ch);
+                            self.push_accumulator(TokenType::Word);
+                        } else if ch == ':' && self.is_line_start() {
                             self.push_accumulator(TokenType::Label);
                         } else {
                             self.push_accumulator(TokenType::Word);
@@ -146,6 +176,12 @@ impl Tokenizer {
                     break;
                 }
             }
+            if ch == '\n' {
+                self.line += 1;
+                self.column = 0;
+            } else {
+                self.column += 1;
+            }
         }
         self.flush();
         let mut result = vec![];
@@ -166,8 +202,21 @@ impl Tokenizer {
         self.accumulator.clear();
     }
 
+    /// Checks if the word being accumulated is the first one on its line,
+    /// possibly after a line number. Only such words with a colon are
+    /// labels, otherwise the colon separates statements.
+    fn is_line_start(&self) -> bool {
+        let mut previous = self.tokens.iter().rev().map(|token| token.t_type);
+        match previous.next() {
+            None | Some(TokenType::Line) => true,
+            Some(TokenType::Number) => matches!(previous.next(), None | Some(TokenType::Line)),
+            _ => false,
+        }
+    }
+
     fn push_accumulator(&mut self, t_type: TokenType) {
-        self.tokens.push(Token::new(&self.accumulator, t_type));
+        let token = Token::new(&self.accumulator, t_type, self.line, self.start);
+        self.tokens.push(token);
         self.accumulator.clear();
         self.state = State::Default;
     }
@@ -215,6 +264,52 @@ mod test {
         assert_eq!(tokens[0].text, "abc");
     }
 
+    #[test]
+    fn tokenize_colon_separator() {
+        let script = "start: a = 1: b: c\n10 loop: PRINT";
+        let mut tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize(script);
+        let types = tokens.iter().map(|token| token.t_type).collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                TokenType::Label,
+                TokenType::Word,
+                TokenType::Equals,
+                TokenType::Number,
+                TokenType::Colon,
+                TokenType::Word,
+                TokenType::Colon,
+                TokenType::Word,
+                TokenType::Line,
+                TokenType::Number,
+                TokenType::Label,
+                TokenType::Word,
+            ]
+        );
+        assert_eq!(tokens[0].text, "start");
+        assert_eq!(tokens[10].text, "loop");
+    }
+
+    #[test]
+    fn tokenize_line_numbers() {
+        let script = "a\n\"b\"\n' comment\nc";
+        let mut tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize(script);
+        let lines = tokens.iter().map(|token| token.line).collect::<Vec<_>>();
+        assert_eq!(lines, [1, 1, 2, 2, 4]);
+        let script = "ab = \"мир\" + 12\n  x:";
+        let tokens = tokenizer.tokenize(script);
+        let columns = tokens
+            .iter()
+            .map(|token| (token.line, token.column))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            [(1, 0), (1, 3), (1, 5), (1, 11), (1, 13), (1, 15), (2, 2)]
+        );
+    }
+
     #[test]
     fn tokenize_word_with_digits() {
         let script = "abc123";
@@ -259,6 +354,27 @@ mod test {
         assert!(matches!(tokens[4].t_type, TokenType::Number));
     }
 
+    #[test]
+    fn tokenize_string_suffix() {
+        let script = "LEFT$(a$,2)";
+        let mut tokenizer = Tokenizer::new();
+        let tokens = tokenizer.tokenize(script);
+        let types = tokens.iter().map(|token| token.t_type).collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                TokenType::Word,
+                TokenType::OpenParenthesis,
+                TokenType::Word,
+                TokenType::Comma,
+                TokenType::Number,
+                TokenType::CloseParenthesis,
+            ]
+        );
+        assert_eq!(tokens[0].text, "LEFT$");
+        assert_eq!(tokens[2].text, "a$");
+    }
+
     #[test]
     fn tokenize_decimal_digit() {
         let script = "12.345";
@@ -0,0 +1,182 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    ops::RangeInclusive,
+    path::PathBuf,
+};
+
+use crate::{ast::value::Value, debugger::describe};
+
+/// Part of the script which is traced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceFilter {
+    /// Statements from the label up to the next one
+    Label(String),
+    /// Statements on the source lines
+    Lines(RangeInclusive<usize>),
+}
+
+impl TraceFilter {
+    /// Parses a comma separated list of labels and line ranges, e.g.
+    /// `loop,10-20,35`. The numbers are source lines, not labels.
+    pub fn parse_list(text: &str) -> Result<Vec<Self>, String> {
+        text.split(',')
+            .map(|item| Self::parse(item.trim()))
+            .collect()
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid trace filter '{text}'");
+        if text.is_empty() {
+            return Err(invalid());
+        }
+        if !text.starts_with(|ch: char| ch.is_ascii_digit()) {
+            return Ok(TraceFilter::Label(text.to_string()));
+        }
+        let (first, last) = text.split_once('-').unwrap_or((text, text));
+        let line = |text: &str| text.trim().parse::<usize>().map_err(|_| invalid());
+        let (first, last) = (line(first)?, line(last)?);
+        if first > last {
+            return Err(invalid());
+        }
+        Ok(TraceFilter::Lines(first..=last))
+    }
+
+    fn matches(&self, line: usize, section: Option<&str>) -> bool {
+        match self {
+            TraceFilter::Label(label) => section == Some(label),
+            TraceFilter::Lines(lines) => lines.contains(&line),
+        }
+    }
+}
+
+/// Tracing the script starts with
+#[derive(Debug, Clone, Default)]
+pub struct TraceConfig {
+    /// Trace from the start as if the script began with TRON
+    pub enabled: bool,
+    /// Log the values assigned by the statements
+    pub values: bool,
+    /// The trace is written to stderr if there is no file
+    pub file: Option<PathBuf>,
+    /// Everything is traced if the filter is empty
+    pub filter: Vec<TraceFilter>,
+}
+
+/// Logs the executed statements while it's enabled with TRON or the
+/// command line option
+pub struct Tracer {
+    pub enabled: bool,
+    values: bool,
+    file: Option<PathBuf>,
+    filter: Vec<TraceFilter>,
+    /// Opened when the first statement is traced
+    output: Option<Box<dyn Write>>,
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Self::new(&TraceConfig::default())
+    }
+}
+
+impl Tracer {
+    pub fn new(config: &TraceConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            values: config.values,
+            file: config.file.clone(),
+            filter: config.filter.clone(),
+            output: None,
+        }
+    }
+
+    /// Replaces the destination, e.g. to capture the trace in tests
+    #[cfg(test)]
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = Some(output);
+    }
+
+    /// Checks if the statement on the line is traced. The section is the
+    /// label the statement is located after.
+    pub fn is_traced(&self, line: usize, section: Option<&str>) -> bool {
+        self.enabled
+            && (self.filter.is_empty()
+                || self
+                    .filter
+                    .iter()
+                    .any(|filter| filter.matches(line, section)))
+    }
+
+    pub fn traces_values(&self) -> bool {
+        self.values
+    }
+
+    pub fn statement(&mut self, line: usize, section: Option<&str>) -> io::Result<()> {
+        match section {
+            Some(label) => self.log(&format!("[line {line}, {label}]")),
+            None => self.log(&format!("[line {line}]")),
+        }
+    }
+
+    pub fn value(&mut self, name: &str, value: &Value) -> io::Result<()> {
+        self.log(&format!("  {name} = {}", describe(value)))
+    }
+
+    fn log(&mut self, text: &str) -> io::Result<()> {
+        let output = match &mut self.output {
+            Some(output) => output,
+            None => {
+                let output: Box<dyn Write> = match &self.file {
+                    Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                    None => Box::new(io::stderr()),
+                };
+                self.output.insert(output)
+            }
+        };
+        writeln!(output, "{text}")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.output {
+            Some(output) => output.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn trace_filter_parse() {
+        let filter = TraceFilter::parse_list("loop, 10-20,35").unwrap();
+        assert_eq!(
+            filter,
+            [
+                TraceFilter::Label("loop".to_string()),
+                TraceFilter::Lines(10..=20),
+                TraceFilter::Lines(35..=35),
+            ]
+        );
+        for text in ["", "a,,b", "20-10", "1-x", "5-"] {
+            assert!(TraceFilter::parse_list(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn trace_filter_matches() {
+        let config = TraceConfig {
+            enabled: true,
+            filter: TraceFilter::parse_list("sub,3-4").unwrap(),
+            ..Default::default()
+        };
+        let mut tracer = Tracer::new(&config);
+        assert!(tracer.is_traced(3, None));
+        assert!(tracer.is_traced(9, Some("sub")));
+        assert!(!tracer.is_traced(5, Some("main")));
+        tracer.enabled = false;
+        assert!(!tracer.is_traced(3, None));
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use crate::ast::value::Value;
+
+/// Position of a variable in the storage
+pub type Slot = usize;
+
+/// Name of a variable with the slot it's resolved to when parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    pub name: String,
+    pub slot: Slot,
+}
+
+impl AsRef<str> for Variable {
+    fn as_ref(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Values of the variables indexed by the slots. The statements access
+/// them by the slots, the names are kept for the debugger and the host.
+#[derive(Default)]
+pub struct Variables {
+    slots: HashMap<String, Slot>,
+    names: Vec<String>,
+    /// A variable has no value until it's assigned
+    values: Vec<Option<Value>>,
+}
+
+impl Variables {
+    /// Slot of the variable, a new one is allocated for a new name
+    pub fn resolve(&mut self, name: &str) -> Slot {
+        if let Some(slot) = self.slots.get(name) {
+            return *slot;
+        }
+        let slot = self.names.len();
+        self.slots.insert(name.to_string(), slot);
+        self.names.push(name.to_string());
+        self.values.push(None);
+        slot
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.slots.get(name).and_then(|slot| self.at(*slot))
+    }
+
+    pub fn at(&self, slot: Slot) -> Option<&Value> {
+        self.values.get(slot).and_then(Option::as_ref)
+    }
+
+    pub fn set(&mut self, name: &str, value: Value) {
+        let slot = self.resolve(name);
+        self.set_at(slot, value);
+    }
+
+    pub fn set_at(&mut self, slot: Slot, value: Value) {
+        self.values[slot] = Some(value);
+    }
+
+    /// Names of the variables in the order of the slots
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Assigned variables in the order of the slots
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.names
+            .iter()
+            .zip(&self.values)
+            .filter_map(|(name, value)| value.as_ref().map(|value| (name, value)))
+    }
+
+    /// Removes the values, the slots stay valid for the parsed statements
+    pub fn clear(&mut self) {
+        self.values.fill(None);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn variables_slots() {
+        let mut variables = Variables::default();
+        let a = variables.resolve("a");
+        let b = variables.resolve("b$");
+        assert_ne!(a, b);
+        assert_eq!(variables.resolve("a"), a);
+        assert_eq!(variables.get("a"), None);
+        variables.set_at(a, Value::number(1.0));
+        variables.set("b$", Value::string("x".to_string()));
+        variables.set("c", Value::number(2.0));
+        assert_eq!(variables.get("a"), Some(&Value::number(1.0)));
+        assert_eq!(variables.at(b), Some(&Value::string("x".to_string())));
+        let names = variables.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b$", "c"]);
+        variables.clear();
+        assert_eq!(variables.iter().count(), 0);
+        assert_eq!(variables.resolve("c"), 2);
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{
+    ast::{
+        statement::{PrintItem, Statement},
+        Expression,
+    },
+    error::RuntimeError,
+};
+
+use super::{Chunk, Instruction};
+
+/// Translates the parsed statements to the VM instructions. The jumps
+/// have to be resolved to the statement positions before.
+#[derive(Default)]
+pub struct Compiler {
+    code: Vec<Instruction>,
+}
+
+impl Compiler {
+    pub fn emit(&mut self, instruction: Instruction) {
+        self.code.push(instruction);
+    }
+
+    pub fn compile<'s>(mut self, statements: impl IntoIterator<Item = &'s Statement>) -> Chunk {
+        let mut offsets = Vec::new();
+        for (index, statement) in statements.into_iter().enumerate() {
+            offsets.push(self.code.len());
+            self.emit(Instruction::Statement(index));
+            self.statement(index, statement);
+        }
+        offsets.push(self.code.len());
+        self.emit(Instruction::Halt);
+        Chunk {
+            code: self.code,
+            offsets,
+        }
+    }
+
+    fn statement(&mut self, index: usize, statement: &Statement) {
+        use Statement::*;
+        match statement {
+            Assign { variable, value } => {
+                value.compile(self);
+                self.emit(Instruction::Store(variable.slot));
+            }
+            Goto { label, target } => match target {
+                Some(target) => self.emit(Instruction::Jump(*target)),
+                None => self.emit(Instruction::Fail(RuntimeError::UndefinedLabel(
+                    label.clone(),
+                ))),
+            },
+            Gosub { label, target } => match target {
+                Some(target) => self.emit(Instruction::Gosub {
+                    target: *target,
+                    next: index + 1,
+                }),
+                None => self.emit(Instruction::Fail(RuntimeError::UndefinedLabel(
+                    label.clone(),
+                ))),
+            },
+            Return => self.emit(Instruction::Return),
+            If { condition, end } => {
+                condition.compile(self);
+                self.emit(Instruction::JumpUnless {
+                    index,
+                    target: *end,
+                });
+            }
+            IfThen {
+                condition,
+                label,
+                target,
+            } => {
+                condition.compile(self);
+                match target {
+                    Some(target) => self.emit(Instruction::JumpIf {
+                        index,
+                        target: *target,
+                    }),
+                    // Fails only if the jump is taken
+                    None => {
+                        self.emit(Instruction::JumpUnless {
+                            index,
+                            target: index + 1,
+                        });
+                        self.emit(Instruction::Fail(RuntimeError::UndefinedLabel(
+                            label.clone(),
+                        )));
+                    }
+                }
+            }
+            Print {
+                channel,
+                items,
+                newline,
+            } => {
+                self.channel(channel);
+                for item in items {
+                    match item {
+                        PrintItem::Expression(expression) => {
+                            expression.compile(self);
+                            self.emit(Instruction::Print);
+                        }
+                        PrintItem::Tab(expression) => {
+                            expression.compile(self);
+                            self.emit(Instruction::Tab);
+                        }
+                        PrintItem::Spc(expression) => {
+                            expression.compile(self);
+                            self.emit(Instruction::Spc);
+                        }
+                        PrintItem::Zone => self.emit(Instruction::Zone),
+                    }
+                }
+                if *newline {
+                    self.emit(Instruction::Newline);
+                }
+            }
+            // The text is formatted before the file number is evaluated
+            PrintUsing {
+                channel,
+                format,
+                values,
+                newline,
+            } => {
+                format.compile(self);
+                for value in values {
+                    value.compile(self);
+                }
+                self.emit(Instruction::Using {
+                    count: values.len(),
+                    newline: *newline,
+                });
+                self.channel(channel);
+                self.emit(Instruction::Print);
+            }
+            Write { channel, values } => {
+                self.channel(channel);
+                for value in values {
+                    value.compile(self);
+                }
+                self.emit(Instruction::Write(values.len()));
+            }
+            Input { prompt, variables } => self.emit(Instruction::Input {
+                prompt: prompt.clone(),
+                variables: variables.clone(),
+            }),
+            LineInput { prompt, variable } => self.emit(Instruction::LineInput {
+                prompt: prompt.clone(),
+                variable: variable.clone(),
+            }),
+            InputFile { channel, variables } => {
+                channel.compile(self);
+                self.emit(Instruction::InputFile(variables.clone()));
+            }
+            LineInputFile { channel, variable } => {
+                channel.compile(self);
+                self.emit(Instruction::LineInputFile(variable.clone()));
+            }
+            Data => {}
+            Read { variables } => self.emit(Instruction::Read(variables.clone())),
+            Restore { label: None, .. } => self.emit(Instruction::Restore(0)),
+            Restore {
+                label: Some(label),
+                target,
+            } => match target {
+                Some(position) => self.emit(Instruction::Restore(*position)),
+                None => self.emit(Instruction::Fail(RuntimeError::UndefinedLabel(
+                    label.clone(),
+                ))),
+            },
+            Open { path, mode, number } => {
+                path.compile(self);
+                number.compile(self);
+                self.emit(Instruction::Open(*mode));
+            }
+            Close { numbers } => {
+                if numbers.is_empty() {
+                    self.emit(Instruction::CloseAll);
+                }
+                for number in numbers {
+                    number.compile(self);
+                    self.emit(Instruction::Close);
+                }
+            }
+            Randomize { seed } => {
+                if let Some(seed) = seed {
+                    seed.compile(self);
+                }
+                self.emit(Instruction::Randomize(seed.is_some()));
+            }
+            Sleep { seconds } => {
+                seconds.compile(self);
+                self.emit(Instruction::Sleep);
+            }
+            MidAssign {
+                variable,
+                start,
+                length,
+                value,
+            } => {
+                start.compile(self);
+                if let Some(length) = length {
+                    length.compile(self);
+                }
+                value.compile(self);
+                self.emit(Instruction::MidAssign {
+                    variable: variable.clone(),
+                    length: length.is_some(),
+                });
+            }
+            Tron => self.emit(Instruction::Trace(true)),
+            Troff => self.emit(Instruction::Trace(false)),
+        }
+    }
+
+    /// Selects the file or the console for the output of the statement
+    fn channel(&mut self, channel: &Option<Box<dyn Expression>>) {
+        if let Some(expression) = channel {
+            expression.compile(self);
+        }
+        self.emit(Instruction::Channel(channel.is_some()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ast::value::Value, interpreter::InterpreterContext, parser::Parser, tokenizer::Tokenizer,
+    };
+
+    use super::*;
+
+    fn compile(source: &str) -> Chunk {
+        let mut context = InterpreterContext::default();
+        let tokens = Tokenizer::new().tokenize(source);
+        Parser::new(tokens, &mut context).parse().unwrap();
+        context.resolve_jumps();
+        let statements =
+            (0..context.statements_count()).filter_map(|index| context.statement(index));
+        Compiler::default().compile(statements)
+    }
+
+    #[test]
+    fn compile_expression() {
+        let chunk = compile("x = LEN(a$) + 2 * y");
+        assert!(matches!(
+            chunk.code.as_slice(),
+            [
+                Instruction::Statement(0),
+                Instruction::Load(1),
+                Instruction::Call { count: 1, .. },
+                Instruction::Push(Value::Number(2.0)),
+                Instruction::Operator('+'),
+                Instruction::Load(2),
+                Instruction::Operator('*'),
+                Instruction::Store(0),
+                Instruction::Halt,
+            ]
+        ));
+    }
+
+    #[test]
+    fn compile_jumps() {
+        let source =
+            "loop: IF x < 3 THEN loop\nGOTO nowhere\nIF x THEN nowhere\nGOSUB nowhere\nRETURN";
+        let chunk = compile(source);
+        assert_eq!(chunk.offsets, [0, 5, 7, 11, 13, 15]);
+        assert!(matches!(
+            chunk.code.as_slice(),
+            [
+                Instruction::Statement(0),
+                Instruction::Load(_),
+                Instruction::Push(_),
+                Instruction::Operator('<'),
+                Instruction::JumpIf {
+                    index: 0,
+                    target: 0
+                },
+                Instruction::Statement(1),
+                Instruction::Fail(RuntimeError::UndefinedLabel(_)),
+                Instruction::Statement(2),
+                Instruction::Load(_),
+                Instruction::JumpUnless {
+                    index: 2,
+                    target: 3
+                },
+                Instruction::Fail(RuntimeError::UndefinedLabel(_)),
+                Instruction::Statement(3),
+                Instruction::Fail(RuntimeError::UndefinedLabel(_)),
+                Instruction::Statement(4),
+                Instruction::Return,
+                Instruction::Halt,
+            ]
+        ));
+    }
+}
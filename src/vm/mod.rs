@@ -0,0 +1,290 @@
+use crate::{
+    ast::{expr_index::element, expr_operator::apply, statement::Statement, value::Value},
+    builtins::Builtin,
+    error::RuntimeError,
+    files::FileNumber,
+    interpreter::{file_number, print_using, InterpreterContext, Step},
+    io::FileMode,
+    variables::{Slot, Variable},
+};
+
+pub mod compiler;
+
+/// Operation of the stack machine. The jump targets are statement indices,
+/// the chunk maps them to the instruction offsets.
+pub enum Instruction {
+    /// Start of the statement at the index, runs the statement hooks
+    Statement(usize),
+    Push(Value),
+    /// Value of the variable, zero if it isn't assigned
+    Load(Slot),
+    /// Element of the array variable, the index is on the stack
+    LoadIndex(Slot),
+    Store(Slot),
+    /// Binary operator applied to the two values on the top of the stack
+    Operator(char),
+    /// Calls the function with the arguments on the top of the stack
+    Call {
+        builtin: &'static Builtin,
+        count: usize,
+    },
+    Jump(usize),
+    /// Pops the condition of the IF statement at the index and jumps to
+    /// the target if it's true
+    JumpIf {
+        index: usize,
+        target: usize,
+    },
+    /// Pops the condition of the IF statement at the index and jumps to
+    /// the target if it's false
+    JumpUnless {
+        index: usize,
+        target: usize,
+    },
+    /// Calls the subroutine remembering the statement to RETURN to
+    Gosub {
+        target: usize,
+        next: usize,
+    },
+    Return,
+    /// Fails when executed, e.g. on GOSUB to an undefined label
+    Fail(RuntimeError),
+    /// Pops the file number the following output goes to if it's true,
+    /// otherwise the output goes to the console
+    Channel(bool),
+    /// Outputs the text of the value
+    Print,
+    /// Moves to the column as TAB in PRINT does
+    Tab,
+    /// Outputs the number of spaces as SPC in PRINT does
+    Spc,
+    /// Moves to the next print zone
+    Zone,
+    Newline,
+    /// Pushes the text of the format and the number of values formatted
+    /// as in PRINT USING
+    Using {
+        count: usize,
+        newline: bool,
+    },
+    /// Outputs the number of values separated with commas as WRITE does
+    Write(usize),
+    Input {
+        prompt: String,
+        variables: Vec<Variable>,
+    },
+    LineInput {
+        prompt: String,
+        variable: Variable,
+    },
+    /// Reads the variables from the file with the number on the stack
+    InputFile(Vec<Variable>),
+    LineInputFile(Variable),
+    Read(Vec<Variable>),
+    /// Moves the DATA pointer to the statement position
+    Restore(usize),
+    /// Opens the path with the number on the top of the stack
+    Open(FileMode),
+    Close,
+    CloseAll,
+    /// Reseeds the generator, the seed is on the stack if it's true
+    Randomize(bool),
+    Sleep,
+    /// The start, the length if it's present and the replacement are on
+    /// the stack
+    MidAssign {
+        variable: Variable,
+        length: bool,
+    },
+    /// Turns the tracing on or off
+    Trace(bool),
+    Halt,
+}
+
+/// Compiled statements
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    /// Offset of the first instruction of each statement, the last one is
+    /// the offset of the final Halt
+    pub offsets: Vec<usize>,
+}
+
+impl Chunk {
+    /// Offset of the statement, the positions past the end halt
+    fn offset(&self, position: usize) -> usize {
+        self.offsets
+            .get(position)
+            .or(self.offsets.last())
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Stack machine executing the compiled statements in the interpreter
+/// context, so the state is shared with the tree walking backend
+#[derive(Default)]
+pub struct Vm {
+    stack: Vec<Value>,
+    /// File number of the output of the current statement
+    channel: Option<FileNumber>,
+    /// Index of the statement being run
+    statement: usize,
+}
+
+impl Vm {
+    pub fn run(
+        &mut self,
+        context: &mut InterpreterContext,
+        chunk: &Chunk,
+        statements: &[Statement],
+    ) -> Result<(), RuntimeError> {
+        self.stack.clear();
+        let mut step: Option<Step> = None;
+        let mut pc = 0;
+        while let Some(instruction) = chunk.code.get(pc) {
+            pc += 1;
+            use Instruction::*;
+            match instruction {
+                Statement(index) => {
+                    self.statement = *index;
+                    if let Some(step) = step.take() {
+                        let statement = &statements[step.index()];
+                        context.end_statement(step, statement)?;
+                    }
+                    // TRON takes effect from the next statement, so the
+                    // hooks may be skipped until one of them is active
+                    if context.has_hooks() {
+                        step = Some(context.begin_statement(*index)?);
+                    }
+                }
+                Push(value) => self.stack.push(value.clone()),
+                Load(slot) => self.stack.push(context.value_at(*slot)),
+                LoadIndex(slot) => {
+                    let index = self.pop();
+                    self.stack.push(element(context, *slot, &index)?);
+                }
+                Store(slot) => {
+                    let value = self.pop();
+                    context.put_at(*slot, value);
+                }
+                Operator(operator) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(apply(*operator, &left, &right)?);
+                }
+                Call { builtin, count } => {
+                    let start = self.stack.len() - count;
+                    let value = builtin.call(context, &self.stack[start..])?;
+                    self.stack.truncate(start);
+                    self.stack.push(value);
+                }
+                Jump(target) => pc = chunk.offset(*target),
+                JumpIf { index, target } => {
+                    let taken = self.pop().try_number()? != 0.0;
+                    if taken {
+                        pc = chunk.offset(*target);
+                    }
+                    context.cover_branch(*index, taken);
+                }
+                JumpUnless { index, target } => {
+                    let taken = self.pop().try_number()? != 0.0;
+                    if !taken {
+                        pc = chunk.offset(*target);
+                    }
+                    context.cover_branch(*index, taken);
+                }
+                Gosub { target, next } => {
+                    context.push_call(*next);
+                    pc = chunk.offset(*target);
+                }
+                Return => pc = chunk.offset(context.return_position()?),
+                Fail(err) => return Err(err.clone()),
+                Channel(present) => {
+                    self.channel = if *present {
+                        Some(file_number(&self.pop())?)
+                    } else {
+                        None
+                    };
+                }
+                Print => {
+                    let value = self.pop();
+                    context.write_to(self.channel, &value.to_text())?;
+                }
+                Tab => {
+                    let column = self.pop();
+                    context.print_tab(self.channel, &column)?;
+                }
+                Spc => {
+                    let count = self.pop();
+                    context.print_spc(self.channel, &count)?;
+                }
+                Zone => context.print_zone(self.channel)?,
+                Newline => context.write_to(self.channel, "\n")?,
+                Using { count, newline } => {
+                    let values = self.stack.split_off(self.stack.len() - count);
+                    let format = self.pop();
+                    let text = print_using(&format, &values, *newline)?;
+                    self.stack.push(Value::string(text));
+                }
+                Write(count) => {
+                    let values = self.stack.split_off(self.stack.len() - count);
+                    context.write_values(self.channel, &values)?;
+                }
+                Input { prompt, variables } => context.input(prompt, variables)?,
+                LineInput { prompt, variable } => context.line_input(prompt, variable)?,
+                InputFile(variables) => {
+                    let number = file_number(&self.pop())?;
+                    context.input_file(number, variables)?;
+                }
+                LineInputFile(variable) => {
+                    let number = file_number(&self.pop())?;
+                    context.line_input_file(number, variable)?;
+                }
+                Read(variables) => context.read_data(variables)?,
+                Restore(position) => context.data().restore(*position),
+                Open(mode) => {
+                    let number = self.pop();
+                    let path = self.pop().to_text();
+                    context.open_file(&path, *mode, file_number(&number)?)?;
+                }
+                Close => {
+                    let number = file_number(&self.pop())?;
+                    context.files().close(number)?;
+                }
+                CloseAll => context.files().close_all()?,
+                Randomize(present) => {
+                    let seed = present.then(|| self.pop());
+                    context.randomize(seed.as_ref())?;
+                }
+                Sleep => {
+                    let seconds = self.pop();
+                    context.sleep(&seconds)?;
+                }
+                MidAssign { variable, length } => {
+                    let value = self.pop();
+                    let length = length.then(|| self.pop());
+                    let start = self.pop();
+                    context.mid_assign(variable, &start, length.as_ref(), &value)?;
+                }
+                Trace(enabled) => context.set_tracing(*enabled),
+                Halt => break,
+            }
+        }
+        if let Some(step) = step {
+            let statement = &statements[step.index()];
+            context.end_statement(step, statement)?;
+        }
+        Ok(())
+    }
+
+    /// Index of the statement which has run last, e.g. the failed one
+    pub fn statement(&self) -> usize {
+        self.statement
+    }
+
+    /// The compiler guarantees the operands are pushed before they are
+    /// used, so the stack can't be empty here
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("VM stack underflow")
+    }
+}